@@ -0,0 +1,162 @@
+use rand::RngCore;
+use rocket::data::{self, ByteUnit, Data, FromData};
+use rocket::form::{Form, FromForm};
+use rocket::http::{RawStr, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use sha2::{Digest, Sha256};
+use std::ops::Deref;
+
+use crate::site::constant_time_eq;
+use crate::updating_site::UpdatingSite;
+
+/// The prefix of the `Authorization` header value that precedes the token.
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// Request guard that only succeeds for requests bearing the configured admin token.
+pub struct AdminToken;
+
+/// Request guard that rejects requests whose bodies are larger than the configured admin body limit.
+/// Only the `Content-Length` header is checked, so routes that read the body should use `AdminForm` instead.
+pub struct AdminBodyLimit;
+
+/// Data guard that parses a URL-encoded form from a request body no larger than the configured admin body limit.
+/// The limit is enforced while the body is read, so it also applies to bodies without a `Content-Length` header, and Rocket's own form limits stay as they are for every other route.
+pub struct AdminForm<T>(pub T);
+
+/// Request guard that only succeeds for requests from the machine the server is running on, or bearing the configured admin token.
+/// Requests are only considered local based on the address they actually came from, not on headers like `X-Real-IP` that any client can set.
+pub struct LocalOrAdminToken;
+
+/// The secret that tokens protecting the admin forms from cross-site request forgery are derived from.
+/// A new one is generated every time the server starts, so forms loaded before a restart have to be reloaded.
+pub struct CsrfKey([u8; 32]);
+
+impl CsrfKey {
+    /// Generates a new random key.
+    pub fn random() -> CsrfKey {
+        let mut key = [0; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        CsrfKey(key)
+    }
+
+    /// Builds the token the form for editing the blog entry with the provided slug has to be submitted with.
+    pub fn token_for(&self, slug: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.0);
+        hasher.update(slug.as_bytes());
+        base64::encode_config(hasher.finalize(), base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Determines whether the provided token is the right one for the form for editing the blog entry with the provided slug.
+    pub fn verify(&self, slug: &str, token: &str) -> bool {
+        constant_time_eq(self.token_for(slug).as_bytes(), token.as_bytes())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminToken {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected_token = request
+            .rocket()
+            .state::<UpdatingSite>()
+            .and_then(|updating_site| updating_site.config.admin_token.as_ref());
+
+        let provided_token = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix(BEARER_PREFIX));
+
+        match (expected_token, provided_token) {
+            (Some(expected), Some(provided))
+                if constant_time_eq(expected.as_bytes(), provided.as_bytes()) =>
+            {
+                Outcome::Success(AdminToken)
+            }
+            _ => Outcome::Failure((Status::Unauthorized, ())),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminBodyLimit {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let limit = request
+            .rocket()
+            .state::<UpdatingSite>()
+            .map(|updating_site| updating_site.config.admin_body_limit);
+
+        let content_length = request
+            .headers()
+            .get_one("Content-Length")
+            .and_then(|header| header.parse::<u64>().ok());
+
+        match (limit, content_length) {
+            (Some(limit), Some(content_length)) if content_length > limit => {
+                Outcome::Failure((Status::PayloadTooLarge, ()))
+            }
+            _ => Outcome::Success(AdminBodyLimit),
+        }
+    }
+}
+
+impl<T> Deref for AdminForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: for<'a> FromForm<'a> + 'static> FromData<'r> for AdminForm<T> {
+    type Error = ();
+
+    async fn from_data(request: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        if !request
+            .content_type()
+            .is_some_and(|content_type| content_type.is_form())
+        {
+            return data::Outcome::Forward(data);
+        }
+
+        let limit = request
+            .rocket()
+            .state::<UpdatingSite>()
+            .map_or(ByteUnit::max_value(), |updating_site| {
+                ByteUnit::from(updating_site.config.admin_body_limit)
+            });
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => return data::Outcome::Failure((Status::PayloadTooLarge, ())),
+            Err(_) => return data::Outcome::Failure((Status::BadRequest, ())),
+        };
+
+        match Form::<T>::parse_encoded(RawStr::new(&body)) {
+            Ok(form) => data::Outcome::Success(AdminForm(form)),
+            Err(_) => data::Outcome::Failure((Status::UnprocessableEntity, ())),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LocalOrAdminToken {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if request
+            .remote()
+            .is_some_and(|address| address.ip().is_loopback())
+        {
+            return Outcome::Success(LocalOrAdminToken);
+        }
+
+        request
+            .guard::<AdminToken>()
+            .await
+            .map(|_| LocalOrAdminToken)
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::Context;
+use rocket_dyn_templates::tera;
+use serde::Serialize;
+use sha2::{Digest, Sha384};
+use std::{collections::HashMap, fs::read, path::Path};
+
+/// The extensions of the static files that integrity hashes are generated for.
+const HASHED_EXTENSIONS: [&str; 2] = ["css", "js"];
+
+/// The number of bytes of an asset's hash to include in its URL to bust caches when it changes.
+const URL_HASH_BYTES: usize = 8;
+
+/// Subresource integrity hashes and cache-busting URLs for static CSS and JS files, keyed by the URL path they're served at.
+#[derive(Debug, Clone, Default)]
+pub struct AssetManifest {
+    assets: HashMap<String, AssetInfo>,
+}
+
+/// The URL and integrity hash of a static asset.
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetInfo {
+    /// The URL to load the asset from, which changes when the asset's contents do.
+    pub url: String,
+    /// The value for the `integrity` attribute of elements that load the asset, or `None` if it isn't known.
+    pub integrity: Option<String>,
+}
+
+impl AssetManifest {
+    /// Builds the manifest for the CSS and JS files in the provided directory and its subdirectories, which is served at `/`.
+    ///
+    /// # Errors
+    /// Returns any errors that occur while reading the files.
+    pub fn from_dir(dir: &Path) -> anyhow::Result<AssetManifest> {
+        let mut assets = HashMap::new();
+        add_dir_assets(dir, dir, &mut assets)?;
+
+        Ok(AssetManifest { assets })
+    }
+
+    /// Gets the info for the asset served at the provided URL path.
+    /// Assets that aren't in the manifest are loaded from their plain URL, without an integrity hash.
+    pub fn get(&self, path: &str) -> AssetInfo {
+        self.assets.get(path).cloned().unwrap_or_else(|| AssetInfo {
+            url: path.to_string(),
+            integrity: None,
+        })
+    }
+
+    /// Converts this manifest into a Tera function that gets the info for the asset at its `path` argument.
+    pub fn into_tera_function(self) -> impl tera::Function {
+        move |args: &HashMap<String, tera::Value>| {
+            let path = args
+                .get("path")
+                .and_then(tera::Value::as_str)
+                .ok_or_else(|| tera::Error::msg("asset requires a string path argument"))?;
+
+            Ok(tera::to_value(self.get(path))?)
+        }
+    }
+}
+
+/// Adds the CSS and JS files in `dir` and its subdirectories to `assets`, keyed by their paths relative to `base_dir`.
+fn add_dir_assets(
+    dir: &Path,
+    base_dir: &Path,
+    assets: &mut HashMap<String, AssetInfo>,
+) -> anyhow::Result<()> {
+    for file in dir
+        .read_dir()
+        .with_context(|| format!("error reading from {}", dir.to_string_lossy()))?
+    {
+        let path = file
+            .with_context(|| format!("error reading from {}", dir.to_string_lossy()))?
+            .path();
+        if path.is_dir() {
+            add_dir_assets(&path, base_dir, assets)?;
+            continue;
+        }
+
+        let is_hashed = path
+            .extension()
+            .is_some_and(|extension| HASHED_EXTENSIONS.iter().any(|hashed| extension == *hashed));
+        if !is_hashed {
+            continue;
+        }
+
+        let contents =
+            read(&path).with_context(|| format!("error reading {}", path.to_string_lossy()))?;
+        let hash = Sha384::digest(&contents);
+        let url_path = format!(
+            "/{}",
+            path.strip_prefix(base_dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/")
+        );
+        let url_hash = hash[..URL_HASH_BYTES]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        assets.insert(
+            url_path.clone(),
+            AssetInfo {
+                url: format!("{}?v={}", url_path, url_hash),
+                integrity: Some(integrity_for(&hash)),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Builds the value of an `integrity` attribute from the provided SHA-384 hash.
+fn integrity_for(sha384_hash: &[u8]) -> String {
+    format!("sha384-{}", base64::encode(sha384_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+    use tempfile::TempDir;
+
+    #[test]
+    fn integrity_hashes_match_asset_contents() {
+        let dir = TempDir::new().unwrap();
+        create_dir_all(dir.path().join("css")).unwrap();
+        write(dir.path().join("css/style.css"), "body { color: red; }").unwrap();
+        write(dir.path().join("logo.png"), "not hashed").unwrap();
+
+        let manifest = AssetManifest::from_dir(dir.path()).unwrap();
+
+        let style = manifest.get("/css/style.css");
+        assert_eq!(
+            Some("sha384-BN8siYsJqlPeNsRFs2pYbTW0uiUBy9v6JVVKpHaS+KNqD0ZFotD5OFKMkI6/s6sb"),
+            style.integrity.as_deref()
+        );
+        assert_eq!("/css/style.css?v=04df2c898b09aa53", style.url);
+        let logo = manifest.get("/logo.png");
+        assert_eq!(None, logo.integrity);
+        assert_eq!("/logo.png", logo.url);
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::HashMap,
+    fs::{metadata, read_to_string, write},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use crate::config::SiteConfig;
+use crate::site::AssociatedFile;
+use crate::toc::TocEntry;
+
+/// The name of the file the build cache is stored in, inside the rendered HTML directory.
+const BUILD_CACHE_FILE_NAME: &str = ".build_cache.toml";
+
+/// Keeps track of the source each blog entry's rendered HTML was built from, so unchanged entries don't have to be rendered again.
+#[derive(Debug, Default)]
+pub struct BuildCache {
+    /// The file the cache is stored in.
+    file: Option<PathBuf>,
+    /// The renders recorded by the previous build.
+    previous: HashMap<String, CachedRender>,
+    /// The renders recorded by the current build.
+    current: HashMap<String, CachedRender>,
+}
+
+/// The details of a blog entry render that are needed to skip rendering it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRender {
+    /// The hash of everything the rendered HTML was built from.
+    pub source_hash: String,
+    /// The slugs of the blog entries the rendered HTML links to.
+    pub linked_slugs: Vec<String>,
+    /// The external URLs the rendered HTML links to.
+    #[serde(default)]
+    pub outbound_links: Vec<String>,
+    /// The table of contents built from the rendered HTML's headings.
+    /// This has no default so caches from before it was recorded are discarded, rather than leaving entries without one.
+    pub toc: Vec<TocEntry>,
+    /// The warnings found while rendering, which are repeated whenever the render is reused.
+    /// This has no default so caches from before it was recorded are discarded, rather than hiding the warnings of unchanged entries.
+    pub warnings: Vec<String>,
+}
+
+impl BuildCache {
+    /// Loads the build cache stored in the provided HTML directory.
+    /// If there isn't one, or it can't be read, the cache starts out empty.
+    pub fn load(html_dir: &Path) -> BuildCache {
+        let file = html_dir.join(BUILD_CACHE_FILE_NAME);
+        let previous = read_to_string(&file)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        BuildCache {
+            file: Some(file),
+            previous,
+            current: HashMap::new(),
+        }
+    }
+
+    /// Gets the render of the blog entry with the provided key from the previous build, if it was built from a source with the provided hash.
+    pub fn get(&self, key: &str, source_hash: &str) -> Option<&CachedRender> {
+        self.previous
+            .get(key)
+            .filter(|render| render.source_hash == source_hash)
+    }
+
+    /// Records the render of the blog entry with the provided key for the current build.
+    pub fn insert(&mut self, key: String, render: CachedRender) {
+        self.current.insert(key, render);
+    }
+
+    /// Saves the renders recorded by the current build, replacing the ones from the previous build.
+    /// Does nothing if the cache wasn't loaded from a directory.
+    ///
+    /// # Errors
+    /// Returns any errors that occur while writing the cache file.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+
+        let contents = toml::to_string(&self.current).context("error serializing build cache")?;
+        write(file, contents)
+            .with_context(|| format!("error writing to {}", file.to_string_lossy()))
+    }
+}
+
+/// Hashes everything that affects how a blog entry is rendered: its slug, its markdown, its associated files, the available shared assets, and the relevant configuration.
+/// Associated files are hashed by their sizes and modification times rather than their contents, so they don't all have to be read on every build.
+///
+/// # Errors
+/// Returns any errors that occur while reading the metadata of associated files.
+pub fn hash_entry_source(
+    slug: &str,
+    markdown: &str,
+    associated_files: &[AssociatedFile],
+    shared_assets: &[AssociatedFile],
+    config: &SiteConfig,
+) -> anyhow::Result<String> {
+    // the hash only needs to be stable between builds of the same binary, since a mismatch just causes a re-render
+    let mut hasher = DefaultHasher::new();
+    slug.hash(&mut hasher);
+    markdown.hash(&mut hasher);
+    for file in associated_files {
+        file.relative_path.hash(&mut hasher);
+        let metadata = metadata(&file.full_path).with_context(|| {
+            format!(
+                "error reading metadata of {}",
+                file.full_path.to_string_lossy()
+            )
+        })?;
+        metadata.len().hash(&mut hasher);
+        metadata
+            .modified()
+            .with_context(|| {
+                format!(
+                    "error reading modification time of {}",
+                    file.full_path.to_string_lossy()
+                )
+            })?
+            .hash(&mut hasher);
+    }
+    for file in shared_assets {
+        file.relative_path.hash(&mut hasher);
+    }
+    config.asset_cdn_base.hash(&mut hasher);
+    config.shared_assets_url_prefix.hash(&mut hasher);
+    config.strict.hash(&mut hasher);
+    config.markdown_parser_options().bits().hash(&mut hasher);
+    config.heading_level_offset.hash(&mut hasher);
+    config.syntax_theme.hash(&mut hasher);
+    config.enable_math.hash(&mut hasher);
+    config.active_prose_lint_forbidden_terms().hash(&mut hasher);
+
+    Ok(format!("{:016x}", hasher.finish()))
+}
@@ -0,0 +1,479 @@
+use chrono::FixedOffset;
+use pulldown_cmark::Options;
+use rocket::figment::value::magic::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, num::NonZeroUsize, path::PathBuf};
+
+/// The URL the site is publicly served at, if not configured.
+const DEFAULT_SITE_BASE_URL: &str = "https://www.rotoclone.zone";
+
+/// The name of the site, if not configured.
+const DEFAULT_SITE_TITLE: &str = "The Rotoclone Zone";
+
+/// The description of the site, if not configured.
+const DEFAULT_SITE_DESCRIPTION: &str = "It's The Rotoclone Zone";
+
+/// The author of blog entries that don't specify one, if not configured.
+const DEFAULT_AUTHOR: &str = "Rotoclone";
+
+/// The locale to use for blog entries that don't specify a language, if one isn't configured.
+const DEFAULT_SITE_LOCALE: &str = "en_US";
+
+/// The number of blog entries to display on a single page, if not configured.
+const DEFAULT_BLOG_PAGE_SIZE: usize = 10;
+
+/// The number of recent blog entries to display on the index page, if not configured.
+const DEFAULT_RECENT_ENTRIES_LIMIT: usize = 5;
+
+/// The URL path shared assets are served under, if not configured.
+const DEFAULT_SHARED_ASSETS_URL_PREFIX: &str = "/shared";
+
+/// The rank of the route blog entries can be reached at directly under `/blog`, if not configured.
+/// This is after the fixed `/blog/...` routes, so entries can't hide them, but before the static files and custom taxonomies.
+const DEFAULT_BLOG_ENTRY_ROUTE_RANK: isize = 8;
+
+/// The names of files in blog entry and shared asset directories to leave out of the site, if not configured.
+/// These are the backup and lock files some editors and operating systems leave around.
+const DEFAULT_IGNORED_FILE_PATTERNS: [&str; 3] = ["*~", "#*#", "Thumbs.db"];
+
+/// The directory templates are loaded from, if not configured.
+const DEFAULT_TEMPLATE_DIR: &str = "templates";
+
+/// The template to render tag pages with, if the tag has no template configured.
+const DEFAULT_TAG_TEMPLATE_NAME: &str = "blog_tag";
+
+/// The text of links from blog entry listings to the full entries, if not configured.
+const DEFAULT_READ_MORE_TEXT: &str = "Read more";
+
+/// The message displayed when there are no blog entries, if not configured.
+const DEFAULT_EMPTY_BLOG_MESSAGE: &str = "Nothing's been posted yet. Check back later!";
+
+/// The maximum number of similar blog entries to suggest on the not found page, if not configured.
+const DEFAULT_SLUG_SUGGESTION_LIMIT: usize = 3;
+
+/// How similar slugs have to be to be suggested on the not found page, if not configured.
+const DEFAULT_SLUG_SUGGESTION_MIN_SIMILARITY: f64 = 0.5;
+
+/// The number of levels to shift headings in blog entries down by, if not configured.
+const DEFAULT_HEADING_LEVEL_OFFSET: u32 = 1;
+
+/// The reading speed used to estimate reading times, if not configured.
+const DEFAULT_WORDS_PER_MINUTE: usize = 200;
+
+/// The markdown extensions to enable, if not configured.
+const DEFAULT_MARKDOWN_OPTIONS: [MarkdownOption; 4] = [
+    MarkdownOption::Strikethrough,
+    MarkdownOption::Footnotes,
+    MarkdownOption::Tables,
+    MarkdownOption::SmartPunctuation,
+];
+
+/// The theme to highlight code blocks with, if one isn't configured.
+const DEFAULT_SYNTAX_THEME: &str = "InspiredGitHub";
+
+/// The number of blog entries to include in feeds, if not configured.
+const DEFAULT_FEED_MAX_ITEMS: usize = 20;
+
+/// The largest request body, in bytes, the admin endpoints will accept, if not configured.
+const DEFAULT_ADMIN_BODY_LIMIT: u64 = 2 * 1024 * 1024;
+
+/// The number of seconds to wait for an admin-requested rebuild, if not configured.
+const DEFAULT_ADMIN_TIMEOUT_SECS: u64 = 30;
+
+/// The number of milliseconds to wait for more file changes before rebuilding, if not configured.
+const DEFAULT_REBUILD_DEBOUNCE_MS: u64 = 500;
+
+/// Configuration that controls how the site is built, read from the Rocket config.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SiteConfig {
+    /// The URL the site is publicly served at, without a trailing slash, used to build absolute URLs.
+    pub site_base_url: String,
+    /// The name of the site, used in page titles.
+    pub site_title: String,
+    /// The description of the site, used in the meta descriptions of its main pages.
+    pub site_description: String,
+    /// The name of the author of blog entries that don't specify one.
+    pub default_author: String,
+    /// The base URL of a CDN that serves blog entries' associated files.
+    /// If set, local image URLs in blog entries are rewritten to point at the CDN instead of this site.
+    pub asset_cdn_base: Option<String>,
+    /// The bearer token required to use the admin endpoints.
+    /// If not set, the admin endpoints reject all requests.
+    pub admin_token: Option<String>,
+    /// The locale of the site's content, used for blog entries that don't specify a language.
+    pub site_locale: String,
+    /// The Open Graph article section for blog entries without any tags.
+    /// If not set, those entries have no section.
+    pub default_article_section: Option<String>,
+    /// The maximum number of blog entries to include in a feed.
+    pub feed_max_items: usize,
+    /// The number of blog entries in each page of the main feed.
+    /// If set, older entries are available in archived feed pages linked from the main feed, as described in RFC 5005.
+    /// If not set, the main feed is a single document of the most recent `feed_max_items` entries.
+    pub feed_page_size: Option<usize>,
+    /// Whether the whitespace between tags should be stripped out of generated XML, like the feeds.
+    pub minify_xml: bool,
+    /// Whether robots.txt should ask all crawlers to stay away from the whole site, like while it's still being drafted.
+    pub disallow_all: bool,
+    /// Whether rendered HTML files that no longer correspond to a blog entry should be deleted after the site is built.
+    pub remove_orphaned_html: bool,
+    /// Whether a leading `YYYY-MM-DD-` date in a blog entry's directory name should be left out of its default slug.
+    /// The date is also used as the entry's creation time if its front matter doesn't specify one.
+    pub strip_date_prefix: bool,
+    /// The number of blog entries to display on a single page.
+    #[serde(alias = "page_size")]
+    pub blog_page_size: usize,
+    /// How pages of the blog index are identified.
+    pub pagination_style: PaginationStyle,
+    /// The text of links from blog entry listings to the full entries, for entries that don't specify their own.
+    pub read_more_text: String,
+    /// The message displayed in place of blog entry listings when there are no blog entries.
+    pub empty_blog_message: String,
+    /// The maximum number of similar blog entries to suggest when a blog entry can't be found.
+    pub slug_suggestion_limit: usize,
+    /// How similar a blog entry's slug has to be to a missing one to be suggested, from 0 to 1.
+    pub slug_suggestion_min_similarity: f64,
+    /// The number of blog entries to display on a single page of a tag's entries.
+    /// If not set, `blog_page_size` is used.
+    pub tag_page_size: Option<usize>,
+    /// Whether problems with content that would normally only cause warnings should fail the build instead.
+    pub strict: bool,
+    /// Whether blog entries that fail to parse should be skipped instead of failing the whole build.
+    /// Skipped entries are recorded in the build report.
+    pub continue_on_error: bool,
+    /// The names to display for tags, keyed by their canonical lowercase form.
+    /// Tags not in this map are displayed the way the newest blog entry that uses them writes them.
+    pub tag_display_names: HashMap<String, String>,
+    /// The names of the templates to render tags' pages with, keyed by their canonical lowercase form.
+    /// Tags not in this map are rendered with the default tag template.
+    pub tag_templates: HashMap<String, String>,
+    /// The directory templates are loaded from, used to check that the templates blog entries, pages, and tags use exist.
+    /// This is the same setting Rocket loads the templates with.
+    pub template_dir: RelativePathBuf,
+    /// The minimum number of tags each blog entry must have. If not set, entries can have no tags.
+    pub min_tags: Option<usize>,
+    /// The maximum number of tags each blog entry can have. If not set, entries can have any number of tags.
+    pub max_tags: Option<usize>,
+    /// The format of the feed that generic feed URLs like `/feed` redirect to.
+    pub feed_default_format: FeedFormat,
+    /// The number of minutes feed readers should cache feeds for.
+    pub feed_ttl: Option<u32>,
+    /// The hours of the day (0-23, in GMT) feed readers should skip checking for updates during.
+    pub feed_skip_hours: Vec<u32>,
+    /// The days of the week (e.g. `Saturday`) feed readers should skip checking for updates during.
+    pub feed_skip_days: Vec<String>,
+    /// Whether to warn about forbidden terms in the prose of blog entries.
+    pub prose_lint: bool,
+    /// The words or phrases to warn about if `prose_lint` is enabled. Matching is case-insensitive and only matches whole words.
+    pub prose_lint_forbidden_terms: Vec<String>,
+    /// The markdown extensions to enable when rendering blog entries.
+    pub markdown_options: Vec<MarkdownOption>,
+    /// Deprecated, use `markdown_options` instead.
+    /// If set, smart punctuation is turned on or off regardless of `markdown_options`, so configs from before `markdown_options` existed keep working.
+    pub smart_typography: Option<bool>,
+    /// The number of levels to shift headings in blog entries down by, so they fit under the entry's title.
+    /// Headings are never shifted past `<h6>`.
+    pub heading_level_offset: u32,
+    /// The reading speed used to estimate how long blog entries take to read.
+    pub words_per_minute: NonZeroUsize,
+    /// The name of the theme to highlight fenced code blocks in blog entries with.
+    pub syntax_theme: String,
+    /// Whether `$...$` and `$$...$$` math in blog entries should be wrapped in elements for KaTeX to render client-side.
+    pub enable_math: bool,
+    /// The markdown file to render as the content of the index page, relative to the site content directory.
+    /// If not set, the default index page content is used.
+    pub index_source: Option<PathBuf>,
+    /// The markdown file to render and display after the content of every blog entry, relative to the site content directory.
+    pub post_footer: Option<PathBuf>,
+    /// The URL path that files in the shared assets directory are served under.
+    pub shared_assets_url_prefix: String,
+    /// The rank of the route that redirects `/blog/<slug>` to the blog entry with that slug. Routes with lower ranks are tried first.
+    /// An entry whose slug matches a fixed route, like `/blog/tags`, is hidden by it unless this is lower than that route's rank.
+    pub blog_entry_route_rank: isize,
+    /// The names of files and directories in blog entry and shared asset directories to leave out of the site, in addition to hidden ones.
+    /// A `*` in a pattern matches any number of characters.
+    pub ignored_file_patterns: Vec<String>,
+    /// Whether blog entries marked as drafts should be included in the site, for previewing them.
+    pub include_drafts: bool,
+    /// The order to display recent blog entries on the index page in.
+    pub index_order: IndexOrder,
+    /// The number of recent blog entries to display on the index page and in the navigation tree.
+    pub recent_entries_limit: usize,
+    /// Whether to keep rendered HTML between builds and only re-render blog entries whose source has changed.
+    pub build_cache: bool,
+    /// Whether to serve endpoints that dump information about how the site was built, for debugging.
+    /// These expose details about the server's file system, so they shouldn't be enabled in production.
+    pub debug_endpoints: bool,
+    /// The largest request body, in bytes, the admin endpoints that change the site will accept.
+    pub admin_body_limit: u64,
+    /// The number of seconds to wait for a rebuild requested through the admin endpoints to finish before giving up on it.
+    pub admin_timeout_secs: u64,
+    /// The number of milliseconds to wait for more file changes before rebuilding the site, so a burst of changes only causes one rebuild.
+    pub rebuild_debounce_ms: u64,
+    /// The offset from UTC, in minutes, of the timezone to use when grouping blog entries by calendar period for display.
+    pub display_utc_offset_minutes: i32,
+}
+
+/// The formats the blog feed is available in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    /// RSS 2.0.
+    Rss,
+    /// Atom 1.0.
+    Atom,
+    /// JSON Feed 1.1.
+    Json,
+}
+
+impl FeedFormat {
+    /// The URL the feed in this format is served at.
+    pub fn url(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "/blog/feed",
+            FeedFormat::Atom => "/blog/feed.atom",
+            FeedFormat::Json => "/blog/feed.json",
+        }
+    }
+}
+
+/// The markdown extensions that can be enabled for blog entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkdownOption {
+    /// `~~strikethrough~~` text.
+    Strikethrough,
+    /// Footnote references and definitions.
+    Footnotes,
+    /// GitHub-style tables.
+    Tables,
+    /// Converting straight quotes, `--`, `---`, and `...` in prose to curly quotes, dashes, and ellipses.
+    /// Code is left untouched.
+    SmartPunctuation,
+    /// `- [ ]` and `- [x]` task list items, rendered as checkboxes.
+    Tasklists,
+}
+
+impl MarkdownOption {
+    /// The parser option that enables this extension.
+    pub fn parser_option(self) -> Options {
+        match self {
+            MarkdownOption::Strikethrough => Options::ENABLE_STRIKETHROUGH,
+            MarkdownOption::Footnotes => Options::ENABLE_FOOTNOTES,
+            MarkdownOption::Tables => Options::ENABLE_TABLES,
+            MarkdownOption::SmartPunctuation => Options::ENABLE_SMART_PUNCTUATION,
+            MarkdownOption::Tasklists => Options::ENABLE_TASKLISTS,
+        }
+    }
+}
+
+/// The ways pages of the blog index can be identified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaginationStyle {
+    /// By page number.
+    Offset,
+    /// By the creation time of the last entry on the previous page, so pages don't shift when entries are added.
+    Cursor,
+}
+
+/// The orders recent blog entries can be displayed on the index page in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexOrder {
+    /// Most recently created first.
+    Created,
+    /// Most recently updated first, treating entries that have never been updated as updated when they were created.
+    Updated,
+}
+
+impl SiteConfig {
+    /// The number of blog entries to display on a single page of a tag's entries.
+    pub fn tag_page_size(&self) -> usize {
+        self.tag_page_size.unwrap_or(self.blog_page_size)
+    }
+
+    /// The name of the template to render the page for the provided canonical tag with.
+    pub fn tag_template_name(&self, tag: &str) -> &str {
+        self.tag_templates
+            .get(tag)
+            .map_or(DEFAULT_TAG_TEMPLATE_NAME, String::as_str)
+    }
+
+    /// The URL shared asset paths should be resolved against, taking the CDN into account if one is configured.
+    pub fn shared_asset_base_url(&self) -> String {
+        format!(
+            "{}{}/",
+            self.asset_cdn_base
+                .as_deref()
+                .unwrap_or_default()
+                .trim_end_matches('/'),
+            self.shared_assets_url_prefix.trim_end_matches('/')
+        )
+    }
+
+    /// Determines whether the file or directory with the provided name should be left out of the site.
+    /// Hidden files, like `.DS_Store`, are always left out.
+    pub fn is_ignored_file(&self, name: &str) -> bool {
+        name.starts_with('.')
+            || self
+                .ignored_file_patterns
+                .iter()
+                .any(|pattern| matches_file_pattern(pattern, name))
+    }
+
+    /// The options to parse markdown with, taking the deprecated `smart_typography` setting into account.
+    pub fn markdown_parser_options(&self) -> Options {
+        let mut options = Options::empty();
+        for markdown_option in &self.markdown_options {
+            options.insert(markdown_option.parser_option());
+        }
+        if let Some(smart_typography) = self.smart_typography {
+            options.set(Options::ENABLE_SMART_PUNCTUATION, smart_typography);
+        }
+
+        options
+    }
+
+    /// The terms the prose lint should warn about, which is none of them if the prose lint is disabled.
+    pub fn active_prose_lint_forbidden_terms(&self) -> &[String] {
+        if self.prose_lint {
+            &self.prose_lint_forbidden_terms
+        } else {
+            &[]
+        }
+    }
+
+    /// The timezone to use when grouping blog entries by calendar period for display, or `None` if the configured offset is a day or more.
+    pub fn display_timezone(&self) -> Option<FixedOffset> {
+        self.display_utc_offset_minutes
+            .checked_mul(60)
+            .and_then(FixedOffset::east_opt)
+    }
+}
+
+/// Determines whether the provided file name matches the provided pattern, where each `*` in the pattern matches any number of characters.
+fn matches_file_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect::<Vec<&str>>();
+    let Some(last) = parts.pop() else {
+        // there's no `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        SiteConfig {
+            site_base_url: DEFAULT_SITE_BASE_URL.to_string(),
+            site_title: DEFAULT_SITE_TITLE.to_string(),
+            site_description: DEFAULT_SITE_DESCRIPTION.to_string(),
+            default_author: DEFAULT_AUTHOR.to_string(),
+            asset_cdn_base: None,
+            admin_token: None,
+            site_locale: DEFAULT_SITE_LOCALE.to_string(),
+            default_article_section: None,
+            feed_max_items: DEFAULT_FEED_MAX_ITEMS,
+            feed_page_size: None,
+            minify_xml: false,
+            disallow_all: false,
+            remove_orphaned_html: true,
+            strip_date_prefix: false,
+            blog_page_size: DEFAULT_BLOG_PAGE_SIZE,
+            pagination_style: PaginationStyle::Offset,
+            read_more_text: DEFAULT_READ_MORE_TEXT.to_string(),
+            empty_blog_message: DEFAULT_EMPTY_BLOG_MESSAGE.to_string(),
+            slug_suggestion_limit: DEFAULT_SLUG_SUGGESTION_LIMIT,
+            slug_suggestion_min_similarity: DEFAULT_SLUG_SUGGESTION_MIN_SIMILARITY,
+            tag_page_size: None,
+            strict: false,
+            continue_on_error: false,
+            tag_display_names: HashMap::new(),
+            tag_templates: HashMap::new(),
+            template_dir: RelativePathBuf::from(DEFAULT_TEMPLATE_DIR),
+            min_tags: None,
+            max_tags: None,
+            feed_default_format: FeedFormat::Rss,
+            feed_ttl: None,
+            feed_skip_hours: Vec::new(),
+            feed_skip_days: Vec::new(),
+            prose_lint: false,
+            prose_lint_forbidden_terms: Vec::new(),
+            markdown_options: DEFAULT_MARKDOWN_OPTIONS.to_vec(),
+            smart_typography: None,
+            heading_level_offset: DEFAULT_HEADING_LEVEL_OFFSET,
+            words_per_minute: NonZeroUsize::new(DEFAULT_WORDS_PER_MINUTE).unwrap(),
+            syntax_theme: DEFAULT_SYNTAX_THEME.to_string(),
+            enable_math: false,
+            index_source: None,
+            post_footer: None,
+            shared_assets_url_prefix: DEFAULT_SHARED_ASSETS_URL_PREFIX.to_string(),
+            blog_entry_route_rank: DEFAULT_BLOG_ENTRY_ROUTE_RANK,
+            ignored_file_patterns: DEFAULT_IGNORED_FILE_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            include_drafts: false,
+            index_order: IndexOrder::Created,
+            recent_entries_limit: DEFAULT_RECENT_ENTRIES_LIMIT,
+            build_cache: false,
+            debug_endpoints: false,
+            admin_body_limit: DEFAULT_ADMIN_BODY_LIMIT,
+            admin_timeout_secs: DEFAULT_ADMIN_TIMEOUT_SECS,
+            rebuild_debounce_ms: DEFAULT_REBUILD_DEBOUNCE_MS,
+            display_utc_offset_minutes: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::figment::Figment;
+
+    #[test]
+    fn smart_typography_overrides_markdown_options() {
+        let config = Figment::new()
+            .merge(("smart_typography", false))
+            .extract::<SiteConfig>()
+            .unwrap();
+
+        assert!(config
+            .markdown_options
+            .contains(&MarkdownOption::SmartPunctuation));
+        assert!(!config
+            .markdown_parser_options()
+            .contains(Options::ENABLE_SMART_PUNCTUATION));
+        assert!(config
+            .markdown_parser_options()
+            .contains(Options::ENABLE_TABLES));
+    }
+
+    #[test]
+    fn smart_typography_can_enable_smart_punctuation() {
+        let config = SiteConfig {
+            markdown_options: Vec::new(),
+            smart_typography: Some(true),
+            ..SiteConfig::default()
+        };
+
+        assert_eq!(
+            Options::ENABLE_SMART_PUNCTUATION,
+            config.markdown_parser_options()
+        );
+    }
+}
@@ -1,39 +1,87 @@
-use chrono::{DateTime, Datelike, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, SecondsFormat, Utc};
 use ordinal::Ordinal;
+use rocket::http::RawStr;
+use rocket::serde::json::serde_json;
 use serde::Serialize;
-use std::{fs::read_to_string, num::NonZeroUsize};
+use std::{cmp::Reverse, num::NonZeroUsize};
 
-use crate::site::{BlogEntry, Site};
+use crate::config::{FeedFormat, IndexOrder, PaginationStyle, SiteConfig};
+use crate::site::{
+    blog_entry_url, canonicalize_tag, page_url, tag_slug, BlogEntry, ExternalDiscussion, GoneEntry,
+    Page, Site,
+};
+use crate::toc::TocEntry;
 
-/// The number of blog entries to display on the index page.
-const RECENT_BLOG_ENTRIES_LIMIT: usize = 5;
+#[derive(Serialize)]
+pub struct TagContext {
+    pub name: String,
+    pub slug: String,
+}
+
+impl Site {
+    /// Builds a `TagContext` for the provided canonical tag.
+    fn tag_context(&self, tag: &str) -> TagContext {
+        TagContext {
+            name: self
+                .config
+                .tag_display_names
+                .get(tag)
+                .or_else(|| self.tag_names.get(tag))
+                .cloned()
+                .unwrap_or_else(|| tag.to_string()),
+            slug: self
+                .tag_slugs
+                .get(tag)
+                .cloned()
+                .unwrap_or_else(|| tag_slug(tag)),
+        }
+    }
 
-/// The number of items to display on a single page.
-const PAGE_SIZE: usize = 10;
+    /// Builds the title of the blog, which page titles for blog pages start with.
+    fn blog_title(&self) -> String {
+        format!("{} Blog", self.config.site_title)
+    }
 
-/// The number of blog entries to include in the RSS feed.
-const BLOG_FEED_SIZE: usize = 20;
+    /// Builds `TagContext`s for the provided canonical tags.
+    fn tag_contexts(&self, tags: &[String]) -> Vec<TagContext> {
+        tags.iter().map(|tag| self.tag_context(tag)).collect()
+    }
+}
 
 #[derive(Serialize)]
 pub struct BlogEntryStub {
     pub title: String,
     pub description: String,
-    pub tags: Vec<String>,
+    pub tags: Vec<TagContext>,
     pub url: String,
     pub created_at: String,
     pub comments_enabled: bool,
+    pub read_more_text: String,
+    pub reading_time_minutes: usize,
+    pub excerpt: String,
 }
 
 impl BlogEntry {
-    /// Builds a `BlogEntryStub` that represents this `BlogEntry`.
-    fn to_stub(&self) -> BlogEntryStub {
+    /// Builds a `BlogEntryStub` that represents this `BlogEntry` on the provided site.
+    fn to_stub(&self, site: &Site) -> BlogEntryStub {
         BlogEntryStub {
             title: self.title.clone(),
             description: self.description.clone(),
-            tags: self.tags.clone(),
-            url: format!("/blog/posts/{}", self.metadata.slug),
+            tags: site.tag_contexts(&self.tags),
+            url: blog_entry_url(&self.metadata.slug),
             created_at: format_datetime(self.created_at),
             comments_enabled: self.comments_enabled,
+            read_more_text: self
+                .read_more
+                .clone()
+                .unwrap_or_else(|| site.config.read_more_text.clone()),
+            reading_time_minutes: self.reading_time_minutes(site.config.words_per_minute),
+            // the content of password-protected entries is secret, so it can't be previewed
+            excerpt: if self.is_password_protected() {
+                String::new()
+            } else {
+                self.excerpt.clone()
+            },
         }
     }
 }
@@ -42,30 +90,149 @@ impl BlogEntry {
 pub struct BaseContext {
     pub title: String,
     pub meta_description: String,
+    pub canonical_url: Option<String>,
+    pub open_graph: Option<OpenGraphContext>,
+}
+
+#[derive(Serialize)]
+pub struct OpenGraphContext {
+    pub title: String,
+    pub description: String,
+    pub url: String,
+    pub og_type: String,
+    pub image: Option<String>,
+    pub twitter_card: String,
+}
+
+impl Site {
+    /// Builds the `BaseContext` for the page at the provided canonical path, which is described to link previews as part of the website.
+    /// Paginated pages should only include their page number in the path if it isn't the first page.
+    fn base_context(&self, title: String, meta_description: String, path: &str) -> BaseContext {
+        let canonical_url = format!("{}{}", self.config.site_base_url, path);
+        BaseContext {
+            open_graph: Some(OpenGraphContext {
+                title: title.clone(),
+                description: meta_description.clone(),
+                url: canonical_url.clone(),
+                og_type: "website".to_string(),
+                image: None,
+                twitter_card: "summary".to_string(),
+            }),
+            canonical_url: Some(canonical_url),
+            title,
+            meta_description,
+        }
+    }
+
+    /// Builds the `OpenGraphContext` describing the provided blog entry to link previews as an article.
+    /// The entry's description is used as the preview text, or its excerpt if it doesn't have one.
+    fn entry_open_graph_context(&self, entry: &BlogEntry) -> OpenGraphContext {
+        let entry_url = format!(
+            "{}{}",
+            self.config.site_base_url,
+            blog_entry_url(&entry.metadata.slug)
+        );
+        let description = if !entry.description.is_empty() || entry.is_password_protected() {
+            entry.description.clone()
+        } else {
+            entry.excerpt.clone()
+        };
+        let image = entry.image.as_ref().map(|image| {
+            if image.contains("://") {
+                image.clone()
+            } else if image.starts_with('/') {
+                format!("{}{}", self.config.site_base_url, image)
+            } else {
+                format!("{}/{}", entry_url, image)
+            }
+        });
+
+        OpenGraphContext {
+            title: entry.title.clone(),
+            description,
+            url: entry_url,
+            og_type: "article".to_string(),
+            twitter_card: if image.is_some() {
+                "summary_large_image".to_string()
+            } else {
+                "summary".to_string()
+            },
+            image,
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct IndexContext {
     pub base: BaseContext,
+    pub index_content: Option<String>,
     pub recent_blog_entries: Vec<BlogEntryStub>,
+    pub posts_this_month: PostsThisMonthContext,
+    pub empty_blog_message: String,
+}
+
+#[derive(Serialize)]
+pub struct PostsThisMonthContext {
+    pub count: usize,
+    pub entries: Vec<BlogEntryStub>,
 }
 
 impl Site {
-    /// Builds the context for the index page.
-    pub fn build_index_context(&self) -> IndexContext {
-        let recent_blog_entries = self
+    /// Builds the context describing the published blog entries created during the current calendar month, in the display timezone.
+    fn build_posts_this_month_context(&self) -> PostsThisMonthContext {
+        self.build_posts_in_month_context(Utc::now())
+    }
+
+    /// Builds the context describing the published blog entries created during the calendar month `now` is in, in the display timezone.
+    fn build_posts_in_month_context(&self, now: DateTime<Utc>) -> PostsThisMonthContext {
+        // the timezone is checked when the site is built
+        let timezone = self
+            .config
+            .display_timezone()
+            .unwrap_or_else(|| FixedOffset::east(0));
+        let now = now.with_timezone(&timezone);
+        let entries = self
             .blog_entries
             .iter()
-            .take(RECENT_BLOG_ENTRIES_LIMIT)
-            .map(BlogEntry::to_stub)
+            .filter(|entry| {
+                let created_at = entry.created_at.with_timezone(&timezone);
+                !entry.draft && created_at.year() == now.year() && created_at.month() == now.month()
+            })
+            .map(|entry| entry.to_stub(self))
+            .collect::<Vec<BlogEntryStub>>();
+
+        PostsThisMonthContext {
+            count: entries.len(),
+            entries,
+        }
+    }
+}
+
+impl Site {
+    /// Builds the context for the index page.
+    pub fn build_index_context(&self) -> IndexContext {
+        let mut recent_blog_entries = self.blog_entries.iter().collect::<Vec<&BlogEntry>>();
+        if self.config.index_order == IndexOrder::Updated {
+            recent_blog_entries
+                .sort_by_key(|entry| Reverse(entry.updated_at.unwrap_or(entry.created_at)));
+        }
+
+        let recent_blog_entries = recent_blog_entries
+            .into_iter()
+            .take(self.config.recent_entries_limit)
+            .map(|entry| entry.to_stub(self))
             .collect();
 
         IndexContext {
-            base: BaseContext {
-                title: "The Rotoclone Zone".to_string(),
-                meta_description: "It's The Rotoclone Zone".to_string(),
-            },
+            base: self.base_context(
+                self.config.site_title.clone(),
+                self.config.site_description.clone(),
+                "/",
+            ),
+            index_content: self.index_content_html.clone(),
             recent_blog_entries,
+            posts_this_month: self.build_posts_this_month_context(),
+            empty_blog_message: self.config.empty_blog_message.clone(),
         }
     }
 }
@@ -79,10 +246,35 @@ impl Site {
     /// Builds the context for the about page.
     pub fn build_about_context(&self) -> AboutContext {
         AboutContext {
-            base: BaseContext {
-                title: "About The Rotoclone Zone".to_string(),
-                meta_description: "It's The Rotoclone Zone".to_string(),
-            },
+            base: self.base_context(
+                format!("About {}", self.config.site_title),
+                self.config.site_description.clone(),
+                "/about",
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PageContext {
+    base: BaseContext,
+    slug: String,
+    title: String,
+    page_content: String,
+}
+
+impl Site {
+    /// Builds the context for the provided standalone page.
+    pub fn build_page_context(&self, page: &Page) -> PageContext {
+        PageContext {
+            base: self.base_context(
+                format!("{} - {}", self.config.site_title, page.title),
+                page.description.clone(),
+                &page_url(&page.slug),
+            ),
+            slug: page.slug.clone(),
+            title: page.title.clone(),
+            page_content: page.html_content.clone(),
         }
     }
 }
@@ -93,31 +285,108 @@ pub struct BlogIndexContext {
     entries: Vec<BlogEntryStub>,
     previous_page: Option<usize>,
     next_page: Option<usize>,
+    next_cursor: Option<String>,
+    remaining_after: usize,
+    pagination: PaginationContext,
+    empty_blog_message: String,
+}
+
+#[derive(Serialize)]
+pub struct PaginationContext {
+    current_page: usize,
+    total_pages: usize,
+    total_entries: usize,
 }
 
 impl Site {
     /// Builds the context for the blog index page.
-    pub fn build_blog_index_context(&self, page: NonZeroUsize) -> BlogIndexContext {
-        let start_index = (page.get() - 1) * PAGE_SIZE;
+    /// If the site uses cursor pagination, `before` determines the page and `page` is ignored. Otherwise, `before` is ignored.
+    /// Returns `None` if the page is past the last page. The first page always exists, even if there are no entries.
+    pub fn build_blog_index_context(
+        &self,
+        page: NonZeroUsize,
+        before: Option<DateTime<Utc>>,
+    ) -> Option<BlogIndexContext> {
+        if self.config.pagination_style == PaginationStyle::Cursor {
+            return Some(self.build_blog_index_cursor_context(before));
+        }
+
+        let page_size = self.config.blog_page_size;
+        let start_index = (page.get() - 1) * page_size;
+        if page.get() > 1 && start_index >= self.blog_entries.len() {
+            return None;
+        }
+
         let entries = self
             .blog_entries
             .iter()
             .skip(start_index)
-            .take(PAGE_SIZE)
-            .map(BlogEntry::to_stub)
+            .take(page_size)
+            .map(|entry| entry.to_stub(self))
             .collect();
 
         let (previous_page, next_page) =
-            calculate_pages(page, start_index, self.blog_entries.len(), PAGE_SIZE);
+            calculate_pages(page, start_index, self.blog_entries.len(), page_size);
 
-        BlogIndexContext {
-            base: BaseContext {
-                title: "The Rotoclone Zone Blog".to_string(),
-                meta_description: "It's The Rotoclone Zone Blog".to_string(),
-            },
+        Some(BlogIndexContext {
+            base: self.base_context(
+                self.blog_title(),
+                self.config.site_description.clone(),
+                &paginated_path("/blog", page),
+            ),
             entries,
             previous_page,
             next_page,
+            next_cursor: None,
+            remaining_after: calculate_remaining_after(
+                start_index,
+                self.blog_entries.len(),
+                page_size,
+            ),
+            pagination: build_pagination_context(page.get(), self.blog_entries.len(), page_size),
+            empty_blog_message: self.config.empty_blog_message.clone(),
+        })
+    }
+
+    /// Builds the context for the page of the blog index with the entries created before `before`, or the newest entries if it's `None`.
+    fn build_blog_index_cursor_context(&self, before: Option<DateTime<Utc>>) -> BlogIndexContext {
+        let older_entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| before.is_none_or(|before| entry.created_at < before))
+            .collect::<Vec<&BlogEntry>>();
+        let page_entries = &older_entries[..older_entries.len().min(self.config.blog_page_size)];
+        let remaining_after = older_entries.len() - page_entries.len();
+        let next_cursor = match page_entries.last() {
+            Some(last_entry) if remaining_after > 0 => Some(
+                last_entry
+                    .created_at
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ),
+            _ => None,
+        };
+
+        BlogIndexContext {
+            base: self.base_context(
+                self.blog_title(),
+                self.config.site_description.clone(),
+                &blog_index_cursor_path(before),
+            ),
+            entries: page_entries
+                .iter()
+                .map(|entry| entry.to_stub(self))
+                .collect(),
+            previous_page: None,
+            next_page: None,
+            next_cursor,
+            remaining_after,
+            // pages found by following cursors always start at a multiple of the page size
+            pagination: build_pagination_context(
+                (self.blog_entries.len() - older_entries.len()) / self.config.blog_page_size + 1,
+                self.blog_entries.len(),
+                self.config.blog_page_size,
+            ),
+            empty_blog_message: self.config.empty_blog_message.clone(),
         }
     }
 }
@@ -126,14 +395,35 @@ impl Site {
 pub struct BlogEntryContext {
     base: BaseContext,
     slug: String,
-    tags: Vec<String>,
+    url: String,
+    tags: Vec<TagContext>,
     created_at: String,
     updated_at: Option<String>,
+    author: String,
     comments_enabled: bool,
     external_discussions: Vec<ExternalDiscussionContext>,
     entry_content: String,
     previous_entry: Option<BlogEntryStub>,
     next_entry: Option<BlogEntryStub>,
+    navigation_tag: Option<TagContext>,
+    locale: String,
+    alternate_locales: Vec<String>,
+    translations: Vec<TranslationContext>,
+    is_draft_preview: bool,
+    post_footer: Option<String>,
+    outbound_links: Vec<String>,
+    article: ArticleContext,
+    json_ld: String,
+    reading_time_minutes: usize,
+    toc: Vec<TocEntry>,
+}
+
+#[derive(Serialize)]
+pub struct ArticleContext {
+    section: Option<String>,
+    tags: Vec<String>,
+    published_time: String,
+    modified_time: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -142,62 +432,249 @@ pub struct ExternalDiscussionContext {
     url: String,
 }
 
+impl ExternalDiscussion {
+    /// Builds an `ExternalDiscussionContext` that represents this `ExternalDiscussion`.
+    fn to_context(&self) -> ExternalDiscussionContext {
+        ExternalDiscussionContext {
+            name: self.name.clone(),
+            url: self.url.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TranslationContext {
+    lang: String,
+    url: String,
+}
+
 impl Site {
     /// Builds the context for the blog entry page for the provided blog entry.
-    ///
-    /// # Errors
-    /// Returns any errors encountered while reading the content of the blog entry from the filesystem.
-    pub fn build_blog_entry_context(
+    pub fn build_blog_entry_context(&self, entry: &BlogEntry) -> BlogEntryContext {
+        let entries = self.blog_entries.iter().collect::<Vec<&BlogEntry>>();
+        self.build_blog_entry_context_among(entry, &entries, None)
+    }
+
+    /// Builds the context for the blog entry page for the provided blog entry, with the previous and next entries limited to ones with the provided tag.
+    /// If the entry doesn't have the tag, the previous and next entries are the same as on its normal page.
+    pub fn build_blog_entry_context_within_tag(
+        &self,
+        entry: &BlogEntry,
+        tag: &str,
+    ) -> BlogEntryContext {
+        let tag = self.tag_for_slug(tag);
+        if !entry.tags.contains(&tag) {
+            return self.build_blog_entry_context(entry);
+        }
+
+        let entries = self.entries_tagged(&tag);
+        self.build_blog_entry_context_among(entry, &entries, Some(self.tag_context(&tag)))
+    }
+
+    /// Builds the context for the blog entry page for the provided blog entry, with the previous and next entries taken from `entries`.
+    fn build_blog_entry_context_among(
         &self,
         entry: &BlogEntry,
-    ) -> Result<BlogEntryContext, std::io::Error> {
+        entries: &[&BlogEntry],
+        navigation_tag: Option<TagContext>,
+    ) -> BlogEntryContext {
         //TODO this looks up the entry again, refactor this method to take in a slug so the entries list only has to be searched once
         // the list of blog entries is sorted by creation date descending, so the previous entry in the list is the next entry chronologically
-        let (next_entry, previous_entry) = stubs_for_surrounding_entries(&self.blog_entries, entry);
+        let (next_entry, previous_entry) = stubs_for_surrounding_entries(self, entries, entry);
 
-        Ok(BlogEntryContext {
+        BlogEntryContext {
             base: BaseContext {
-                title: entry.title.clone(),
-                meta_description: entry.description.clone(),
+                open_graph: Some(self.entry_open_graph_context(entry)),
+                ..self.base_context(
+                    entry.title.clone(),
+                    entry.description.clone(),
+                    &blog_entry_url(&entry.metadata.slug),
+                )
             },
             slug: entry.metadata.slug.clone(),
-            tags: entry.tags.clone(),
+            url: blog_entry_url(&entry.metadata.slug),
+            tags: self.tag_contexts(&entry.tags),
             created_at: format_datetime(entry.created_at),
             updated_at: entry.updated_at.map(format_datetime),
+            author: entry.author(&self.config.default_author).to_string(),
             comments_enabled: entry.comments_enabled,
             external_discussions: entry
                 .external_discussions
                 .iter()
-                .map(|d| ExternalDiscussionContext {
-                    name: d.name.clone(),
-                    url: d.url.clone(),
-                })
+                .map(ExternalDiscussion::to_context)
                 .collect(),
-            entry_content: read_to_string(&entry.metadata.html_content_file)?,
+            entry_content: entry.html_content.clone(),
             previous_entry,
             next_entry,
-        })
+            navigation_tag,
+            locale: entry
+                .lang
+                .as_deref()
+                .map_or_else(|| self.config.site_locale.clone(), lang_to_locale),
+            alternate_locales: entry
+                .translations
+                .iter()
+                .map(|t| lang_to_locale(&t.lang))
+                .collect(),
+            translations: entry
+                .translations
+                .iter()
+                .map(|t| TranslationContext {
+                    lang: t.lang.clone(),
+                    url: blog_entry_url(&t.slug),
+                })
+                .collect(),
+            is_draft_preview: entry.draft,
+            post_footer: self.post_footer_html.clone(),
+            outbound_links: entry.metadata.outbound_links.clone(),
+            article: self.build_article_context(entry),
+            json_ld: self.build_json_ld(entry),
+            reading_time_minutes: entry.reading_time_minutes(self.config.words_per_minute),
+            toc: entry.metadata.toc.clone(),
+        }
+    }
+
+    /// Builds the JSON-LD structured data describing the provided blog entry, to be embedded in a `<script>` element.
+    fn build_json_ld(&self, entry: &BlogEntry) -> String {
+        let json_ld = serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": "BlogPosting",
+            "headline": entry.title,
+            "description": entry.description,
+            "url": format!("{}{}", self.config.site_base_url, blog_entry_url(&entry.metadata.slug)),
+            "datePublished": entry.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "dateModified": entry
+                .updated_at
+                .unwrap_or(entry.created_at)
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            "author": {
+                "@type": "Person",
+                "name": entry.author(&self.config.default_author),
+            },
+            "keywords": self
+                .tag_contexts(&entry.tags)
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect::<Vec<String>>(),
+        });
+
+        // a `</script>` in any of the strings would end the element early
+        json_ld.to_string().replace("</", "<\\/")
+    }
+
+    /// Builds the Open Graph article metadata for the provided blog entry.
+    /// The section is the entry's first tag, or the configured default section if it has no tags.
+    fn build_article_context(&self, entry: &BlogEntry) -> ArticleContext {
+        let tags = self.tag_contexts(&entry.tags);
+        ArticleContext {
+            section: tags
+                .first()
+                .map(|tag| tag.name.clone())
+                .or_else(|| self.config.default_article_section.clone()),
+            tags: tags.into_iter().map(|tag| tag.name).collect(),
+            published_time: entry.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            modified_time: entry
+                .updated_at
+                .map(|updated_at| updated_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        }
     }
 }
 
-/// Builds `BlogEntryStub`s for the blog entries from the provided list positioned immediately before and after the provided entry, if they exist.
+#[derive(Serialize)]
+pub struct BlogEntryPasswordContext {
+    base: BaseContext,
+    slug: String,
+    url: String,
+    incorrect_password: bool,
+}
+
+impl Site {
+    /// Builds the context for the page that prompts for the password to a password-protected blog entry.
+    /// None of the entry's content is included.
+    pub fn build_blog_entry_password_context(
+        &self,
+        entry: &BlogEntry,
+        incorrect_password: bool,
+    ) -> BlogEntryPasswordContext {
+        BlogEntryPasswordContext {
+            base: BaseContext {
+                open_graph: Some(self.entry_open_graph_context(entry)),
+                ..self.base_context(
+                    entry.title.clone(),
+                    entry.description.clone(),
+                    &blog_entry_url(&entry.metadata.slug),
+                )
+            },
+            slug: entry.metadata.slug.clone(),
+            url: blog_entry_url(&entry.metadata.slug),
+            incorrect_password,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AdminEditContext {
+    base: BaseContext,
+    slug: String,
+    url: String,
+    csrf_token: String,
+    front_matter: String,
+    content: String,
+    error: Option<String>,
+}
+
+impl Site {
+    /// Builds the context for the page for editing the source of the provided blog entry.
+    ///
+    /// # Arguments
+    /// * `entry` - The blog entry being edited.
+    /// * `csrf_token` - The token the form has to be submitted with.
+    /// * `front_matter` - The unparsed front matter to fill the form with.
+    /// * `content` - The markdown content to fill the form with.
+    /// * `error` - The error that occurred when saving the previous edit, if any.
+    pub fn build_admin_edit_context(
+        &self,
+        entry: &BlogEntry,
+        csrf_token: String,
+        front_matter: String,
+        content: String,
+        error: Option<String>,
+    ) -> AdminEditContext {
+        AdminEditContext {
+            base: self.base_context(
+                format!("Editing {}", entry.title),
+                entry.description.clone(),
+                &format!("/admin/edit/{}", entry.metadata.slug),
+            ),
+            slug: entry.metadata.slug.clone(),
+            url: blog_entry_url(&entry.metadata.slug),
+            csrf_token,
+            front_matter,
+            content,
+            error,
+        }
+    }
+}
+
+/// Builds `BlogEntryStub`s for the blog entries from `entries` positioned immediately before and after the provided entry, if they exist.
 fn stubs_for_surrounding_entries(
-    entries: &[BlogEntry],
+    site: &Site,
+    entries: &[&BlogEntry],
     entry: &BlogEntry,
 ) -> (Option<BlogEntryStub>, Option<BlogEntryStub>) {
-    let entry_index = entries.iter().position(|x| x == entry);
+    let entry_index = entries.iter().position(|x| *x == entry);
 
     entry_index.map_or((None, None), |index| {
         let previous = if index == 0 {
             None
         } else {
-            Some(entries[index - 1].to_stub())
+            Some(entries[index - 1].to_stub(site))
         };
 
         let next = if index == entries.len() - 1 {
             None
         } else {
-            Some(entries[index + 1].to_stub())
+            Some(entries[index + 1].to_stub(site))
         };
 
         (previous, next)
@@ -205,150 +682,1509 @@ fn stubs_for_surrounding_entries(
 }
 
 #[derive(Serialize)]
-pub struct BlogTagsContext {
+pub struct BlogDiscussionsContext {
     base: BaseContext,
-    tags: Vec<String>,
+    entries: Vec<DiscussedEntryContext>,
+}
+
+#[derive(Serialize)]
+pub struct DiscussedEntryContext {
+    entry: BlogEntryStub,
+    external_discussions: Vec<ExternalDiscussionContext>,
 }
 
 impl Site {
-    /// Builds the context for the page of all the blog tags.
-    pub fn build_blog_tags_context(&self) -> BlogTagsContext {
-        let mut tags = self
+    /// Builds the context for the page listing the blog entries that are being discussed on other sites.
+    pub fn build_discussions_context(&self) -> BlogDiscussionsContext {
+        let entries = self
             .blog_entries
             .iter()
-            .flat_map(|entry| entry.tags.clone())
-            .collect::<Vec<String>>();
-        tags.sort_unstable();
-        tags.dedup();
+            .filter(|entry| !entry.external_discussions.is_empty())
+            .map(|entry| DiscussedEntryContext {
+                entry: entry.to_stub(self),
+                external_discussions: entry
+                    .external_discussions
+                    .iter()
+                    .map(ExternalDiscussion::to_context)
+                    .collect(),
+            })
+            .collect();
 
-        BlogTagsContext {
-            base: BaseContext {
-                title: "The Rotoclone Zone Blog - All Tags".to_string(),
-                meta_description: "All the tags".to_string(),
-            },
-            tags,
+        BlogDiscussionsContext {
+            base: self.base_context(
+                format!("{} - Discussions", self.blog_title()),
+                "Posts being discussed elsewhere".to_string(),
+                "/blog/discussions",
+            ),
+            entries,
         }
     }
 }
 
 #[derive(Serialize)]
-pub struct BlogTagContext {
+pub struct ChangelogContext {
     base: BaseContext,
-    tag: String,
-    entries: Vec<BlogEntryStub>,
-    previous_page: Option<usize>,
-    next_page: Option<usize>,
+    entries: Vec<ChangelogEntryContext>,
+}
+
+#[derive(Serialize)]
+pub struct ChangelogEntryContext {
+    title: String,
+    url: String,
+    created_at: String,
+    updated_at: Option<String>,
+    edited: bool,
 }
 
 impl Site {
-    /// Builds the context for a blog tag page.
-    /// Returns `None` if there are no entries with the provided tag.
-    pub fn build_blog_tag_context(
-        &self,
-        tag: String,
-        page: NonZeroUsize,
-    ) -> Option<BlogTagContext> {
-        let start_index = (page.get() - 1) * PAGE_SIZE;
-        let all_matching_entries = self
-            .blog_entries
-            .iter()
-            .filter(|entry| entry.tags.contains(&tag))
-            .collect::<Vec<&BlogEntry>>();
+    /// Builds the context for the page listing every blog entry by when it was last changed, most recent first.
+    pub fn build_changelog_context(&self) -> ChangelogContext {
+        let mut entries = self.blog_entries.iter().collect::<Vec<&BlogEntry>>();
+        entries.sort_by_key(|entry| Reverse(entry.updated_at.unwrap_or(entry.created_at)));
 
-        if all_matching_entries.is_empty() {
-            return None;
+        ChangelogContext {
+            base: self.base_context(
+                format!("{} - Changelog", self.config.site_title),
+                "Every post on the site, by when it was last changed".to_string(),
+                "/changelog",
+            ),
+            entries: entries
+                .into_iter()
+                .map(|entry| ChangelogEntryContext {
+                    title: entry.title.clone(),
+                    url: blog_entry_url(&entry.metadata.slug),
+                    created_at: format_datetime(entry.created_at),
+                    updated_at: entry.updated_at.map(format_datetime),
+                    edited: entry.updated_at.is_some(),
+                })
+                .collect(),
         }
-
-        let total_matching_entries = all_matching_entries.len();
-        let entries = all_matching_entries
-            .into_iter()
-            .skip(start_index)
-            .take(PAGE_SIZE)
-            .map(BlogEntry::to_stub)
-            .collect::<Vec<BlogEntryStub>>();
-
-        let (previous_page, next_page) =
-            calculate_pages(page, start_index, total_matching_entries, PAGE_SIZE);
-
-        Some(BlogTagContext {
-            base: BaseContext {
-                title: format!("The Rotoclone Zone Blog - Posts Tagged {}", tag),
-                meta_description: format!("All the posts tagged {}", tag),
-            },
-            tag,
-            entries,
-            previous_page,
-            next_page,
-        })
     }
 }
 
 #[derive(Serialize)]
-pub struct FeedContext {
-    title: String,
-    description: String,
-    base_url: String,
-    feed_url: String,
-    items: Vec<FeedItemContext>,
+pub struct BookContext {
+    base: BaseContext,
+    tag: Option<TagContext>,
+    entries: Vec<BookEntryContext>,
 }
 
 #[derive(Serialize)]
-pub struct FeedItemContext {
+pub struct BookEntryContext {
     title: String,
-    published_date: String,
+    anchor: String,
     url: String,
-}
-
-impl BlogEntry {
-    fn to_feed_item(&self) -> FeedItemContext {
-        FeedItemContext {
-            title: self.title.clone(),
-            published_date: format_datetime_feed(self.created_at),
-            url: format!("/posts/{}", self.metadata.slug),
-        }
-    }
+    created_at: String,
+    content: String,
 }
 
 impl Site {
-    pub fn build_blog_feed_context(&self) -> FeedContext {
-        let items = self
+    /// Builds the context for a single page containing the content of every published blog entry, oldest first.
+    /// If a tag is provided, only entries with that tag are included, and `None` is returned if there aren't any.
+    /// Drafts and password-protected entries are left out.
+    pub fn build_book_context(&self, tag: Option<String>) -> Option<BookContext> {
+        let tag = tag.map(|tag| self.tag_for_slug(&tag));
+        let entries = self
             .blog_entries
             .iter()
-            .take(BLOG_FEED_SIZE)
-            .map(BlogEntry::to_feed_item)
-            .collect();
+            .rev()
+            .filter(|entry| !entry.draft && !entry.is_password_protected())
+            .filter(|entry| tag.as_ref().is_none_or(|tag| entry.tags.contains(tag)))
+            .map(|entry| BookEntryContext {
+                title: entry.title.clone(),
+                anchor: entry.metadata.slug.clone(),
+                url: blog_entry_url(&entry.metadata.slug),
+                created_at: format_datetime(entry.created_at),
+                content: entry.html_content.clone(),
+            })
+            .collect::<Vec<BookEntryContext>>();
 
-        FeedContext {
-            title: "The Rotoclone Zone Blog".to_string(),
-            description: "Some guy's blog I dunno".to_string(),
-            base_url: "https://www.rotoclone.zone/blog".to_string(),
-            feed_url: "/feed".to_string(),
-            items,
+        if tag.is_some() && entries.is_empty() {
+            return None;
         }
+
+        let tag = tag.map(|tag| self.tag_context(&tag));
+        let base = match &tag {
+            Some(tag) => self.base_context(
+                format!("{} - Posts Tagged {}", self.blog_title(), tag.name),
+                format!("Every post tagged {}, on one page", tag.name),
+                &format!("/blog/book.html?tag={}", encode_path_segment(&tag.slug)),
+            ),
+            None => self.base_context(
+                self.blog_title(),
+                "Every post, on one page".to_string(),
+                "/blog/book.html",
+            ),
+        };
+
+        Some(BookContext { base, tag, entries })
     }
 }
 
 #[derive(Serialize)]
-pub struct ErrorContext {
-    pub base: BaseContext,
-    pub header: String,
-    pub message: String,
+pub struct BlogTagsContext {
+    base: BaseContext,
+    tags: Vec<TagCount>,
 }
 
-/// Converts the provided `DateTime` into a nice human-readable string.
-fn format_datetime(datetime: DateTime<Utc>) -> String {
-    let month = datetime.format("%B");
+#[derive(Serialize)]
+pub struct TagCount {
+    name: String,
+    slug: String,
+    count: usize,
+}
+
+impl Site {
+    /// Builds the context for the page of all the blog tags.
+    /// The tags are sorted by how many blog entries they're used by, most first, then by name.
+    pub fn build_blog_tags_context(&self) -> BlogTagsContext {
+        let mut tags = self
+            .tag_index
+            .iter()
+            .map(|(tag, indices)| {
+                let tag = self.tag_context(tag);
+                TagCount {
+                    name: tag.name,
+                    slug: tag.slug,
+                    count: indices.len(),
+                }
+            })
+            .collect::<Vec<TagCount>>();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+        BlogTagsContext {
+            base: self.base_context(
+                format!("{} - All Tags", self.blog_title()),
+                "All the tags".to_string(),
+                "/blog/tags",
+            ),
+            tags,
+        }
+    }
+}
+
+impl Site {
+    /// Gets all the tags used by any blog entry, sorted and without duplicates.
+    fn all_tags(&self) -> Vec<String> {
+        let mut tags = self.tag_index.keys().cloned().collect::<Vec<String>>();
+        tags.sort_unstable();
+
+        tags
+    }
+}
+
+#[derive(Serialize)]
+pub struct SeriesIndexContext {
+    base: BaseContext,
+    series: Vec<SeriesSummaryContext>,
+}
+
+#[derive(Serialize)]
+pub struct SeriesSummaryContext {
+    name: String,
+    url: String,
+    post_count: usize,
+}
+
+impl Site {
+    /// Builds the context for the page listing every series of blog entries, most recently updated first.
+    /// Series that only contain drafts are left out.
+    pub fn build_series_index_context(&self) -> SeriesIndexContext {
+        let mut series = Vec::<(&str, usize, DateTime<Utc>)>::new();
+        for entry in self.blog_entries.iter().filter(|entry| !entry.draft) {
+            let name = match &entry.series {
+                Some(name) => name,
+                None => continue,
+            };
+            let last_updated = entry.updated_at.unwrap_or(entry.created_at);
+            match series.iter_mut().find(|(existing, _, _)| existing == name) {
+                Some((_, post_count, series_last_updated)) => {
+                    *post_count += 1;
+                    *series_last_updated = (*series_last_updated).max(last_updated);
+                }
+                None => series.push((name, 1, last_updated)),
+            }
+        }
+        series.sort_by_key(|(_, _, last_updated)| Reverse(*last_updated));
+
+        SeriesIndexContext {
+            base: self.base_context(
+                format!("{} - Series", self.blog_title()),
+                "All the series of posts".to_string(),
+                "/blog/series",
+            ),
+            series: series
+                .into_iter()
+                .map(|(name, post_count, _)| SeriesSummaryContext {
+                    name: name.to_string(),
+                    url: format!("/blog/series/{}", encode_path_segment(name)),
+                    post_count,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BlogSeriesContext {
+    base: BaseContext,
+    name: String,
+    entries: Vec<BlogEntryStub>,
+}
+
+impl Site {
+    /// Builds the context for the page listing the blog entries in a series, in the order they were posted.
+    /// Returns `None` if there are no entries in the provided series.
+    pub fn build_blog_series_context(&self, name: String) -> Option<BlogSeriesContext> {
+        let entries = self
+            .blog_entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.series.as_ref() == Some(&name))
+            .map(|entry| entry.to_stub(self))
+            .collect::<Vec<BlogEntryStub>>();
+
+        if entries.is_empty() {
+            return None;
+        }
+
+        Some(BlogSeriesContext {
+            base: self.base_context(
+                format!("{} - {}", self.blog_title(), name),
+                format!("All the posts in the series {}", name),
+                &format!("/blog/series/{}", encode_path_segment(&name)),
+            ),
+            name,
+            entries,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchContext {
+    base: BaseContext,
+    query: String,
+    entries: Vec<BlogEntryStub>,
+}
+
+impl Site {
+    /// Builds the context for the page of blog entries matching the provided search query, best matches first.
+    /// An entry matches if every word of the query appears in its title, description, tags, or content, ignoring case.
+    /// Matches in the title count the most and matches in the content count the least. The content of password-protected entries isn't searched.
+    /// There are no results for a query without any words in it.
+    pub fn build_search_context(&self, query: String) -> SearchContext {
+        let terms = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<String>>();
+
+        let mut matches = Vec::new();
+        if !terms.is_empty() {
+            for entry in &self.blog_entries {
+                if let Some(score) = search_score(entry, &terms) {
+                    matches.push((entry, score));
+                }
+            }
+        }
+        // the sort is stable, so entries with the same score stay newest first
+        matches.sort_by_key(|(_, score)| Reverse(*score));
+
+        SearchContext {
+            base: self.base_context(
+                format!("{} - Search", self.blog_title()),
+                "Search the posts".to_string(),
+                &format!("/blog/search?q={}", encode_path_segment(&query)),
+            ),
+            query,
+            entries: matches
+                .into_iter()
+                .map(|(entry, _)| entry.to_stub(self))
+                .collect(),
+        }
+    }
+}
+
+/// Scores how well the provided blog entry matches the provided lowercase search terms.
+/// Returns `None` if any of the terms don't appear in the entry.
+fn search_score(entry: &BlogEntry, terms: &[String]) -> Option<usize> {
+    let title = entry.title.to_lowercase();
+    let description = entry.description.to_lowercase();
+    let content = if entry.is_password_protected() {
+        ""
+    } else {
+        entry.search_text.as_str()
+    };
+
+    let mut score = 0;
+    for term in terms {
+        let term_score = 10 * title.matches(term.as_str()).count()
+            + 5 * entry
+                .tags
+                .iter()
+                .filter(|tag| tag.contains(term.as_str()))
+                .count()
+            + 3 * description.matches(term.as_str()).count()
+            + content.matches(term.as_str()).count();
+        if term_score == 0 {
+            return None;
+        }
+        score += term_score;
+    }
+
+    Some(score)
+}
+
+#[derive(Serialize)]
+pub struct BlogArchiveContext {
+    base: BaseContext,
+    period: String,
+    entries: Vec<BlogEntryStub>,
+    posts_this_month: PostsThisMonthContext,
+}
+
+impl Site {
+    /// Builds the context for the page listing the blog entries created during the provided year, or the provided month of it if `month` is set.
+    /// Returns `None` if there are no entries from that period, including if `month` isn't a valid month.
+    pub fn build_archive_context(
+        &self,
+        year: i32,
+        month: Option<u32>,
+    ) -> Option<BlogArchiveContext> {
+        let period = match month {
+            Some(month) => NaiveDate::from_ymd_opt(year, month, 1)?
+                .format("%B %Y")
+                .to_string(),
+            None => year.to_string(),
+        };
+
+        let entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| {
+                entry.created_at.year() == year
+                    && month.is_none_or(|month| entry.created_at.month() == month)
+            })
+            .map(|entry| entry.to_stub(self))
+            .collect::<Vec<BlogEntryStub>>();
+
+        if entries.is_empty() {
+            return None;
+        }
+
+        Some(BlogArchiveContext {
+            base: self.base_context(
+                format!("{} - Posts From {}", self.blog_title(), period),
+                format!("All the posts from {}", period),
+                &archive_path(year, month),
+            ),
+            period,
+            entries,
+            posts_this_month: self.build_posts_this_month_context(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct TaxonomyContext {
+    base: BaseContext,
+    taxonomy: String,
+    terms: Vec<TagContext>,
+}
+
+#[derive(Serialize)]
+pub struct TaxonomyTermContext {
+    base: BaseContext,
+    taxonomy: String,
+    term: TagContext,
+    entries: Vec<BlogEntryStub>,
+    previous_page: Option<usize>,
+    next_page: Option<usize>,
+    remaining_after: usize,
+    pagination: PaginationContext,
+}
+
+impl Site {
+    /// Builds the context for the page listing all the terms used in a custom taxonomy, sorted.
+    /// Returns `None` if no entries are classified under the provided taxonomy.
+    pub fn build_taxonomy_context(&self, taxonomy: String) -> Option<TaxonomyContext> {
+        let taxonomy = canonicalize_tag(&taxonomy);
+        let mut terms = self
+            .blog_entries
+            .iter()
+            .filter_map(|entry| entry.taxonomies.get(&taxonomy))
+            .flatten()
+            .cloned()
+            .collect::<Vec<String>>();
+
+        if terms.is_empty() {
+            return None;
+        }
+        terms.sort_unstable();
+        terms.dedup();
+
+        Some(TaxonomyContext {
+            base: self.base_context(
+                format!("{} - All The {}", self.blog_title(), taxonomy),
+                format!("All the {} posts are classified by", taxonomy),
+                &format!("/blog/{}", encode_path_segment(&taxonomy)),
+            ),
+            terms: terms
+                .iter()
+                .map(|term| TagContext {
+                    name: term.clone(),
+                    slug: term.clone(),
+                })
+                .collect(),
+            taxonomy,
+        })
+    }
+
+    /// Builds the context for the page listing the entries classified under a term of a custom taxonomy.
+    /// Returns `None` if there are no entries classified under the provided term, or the page is past the last page.
+    pub fn build_taxonomy_term_context(
+        &self,
+        taxonomy: String,
+        term: String,
+        page: NonZeroUsize,
+    ) -> Option<TaxonomyTermContext> {
+        let taxonomy = canonicalize_tag(&taxonomy);
+        let term = canonicalize_tag(&term);
+        let page_size = self.config.tag_page_size();
+        let start_index = (page.get() - 1) * page_size;
+        let all_matching_entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .taxonomies
+                    .get(&taxonomy)
+                    .is_some_and(|terms| terms.contains(&term))
+            })
+            .collect::<Vec<&BlogEntry>>();
+
+        if start_index >= all_matching_entries.len() {
+            return None;
+        }
+
+        let total_matching_entries = all_matching_entries.len();
+        let entries = all_matching_entries
+            .into_iter()
+            .skip(start_index)
+            .take(page_size)
+            .map(|entry| entry.to_stub(self))
+            .collect::<Vec<BlogEntryStub>>();
+
+        let (previous_page, next_page) =
+            calculate_pages(page, start_index, total_matching_entries, page_size);
+
+        Some(TaxonomyTermContext {
+            base: self.base_context(
+                format!("{} - Posts With {} {}", self.blog_title(), taxonomy, term),
+                format!("All the posts with {} {}", taxonomy, term),
+                &paginated_path(
+                    &format!(
+                        "/blog/{}/{}",
+                        encode_path_segment(&taxonomy),
+                        encode_path_segment(&term)
+                    ),
+                    page,
+                ),
+            ),
+            taxonomy,
+            term: TagContext {
+                name: term.clone(),
+                slug: term,
+            },
+            entries,
+            previous_page,
+            next_page,
+            remaining_after: calculate_remaining_after(
+                start_index,
+                total_matching_entries,
+                page_size,
+            ),
+            pagination: build_pagination_context(page.get(), total_matching_entries, page_size),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct BlogTagContext {
+    base: BaseContext,
+    tag: TagContext,
+    entries: Vec<BlogEntryStub>,
+    previous_page: Option<usize>,
+    next_page: Option<usize>,
+    remaining_after: usize,
+    pagination: PaginationContext,
+}
+
+impl Site {
+    /// Builds the context for a blog tag page.
+    /// Returns `None` if there are no entries with the provided tag, or the page is past the last page.
+    pub fn build_blog_tag_context(
+        &self,
+        tag: String,
+        page: NonZeroUsize,
+    ) -> Option<BlogTagContext> {
+        let tag = self.tag_for_slug(&tag);
+        let page_size = self.config.tag_page_size();
+        let start_index = (page.get() - 1) * page_size;
+        let all_matching_entries = self.entries_tagged(&tag);
+
+        if start_index >= all_matching_entries.len() {
+            return None;
+        }
+
+        let total_matching_entries = all_matching_entries.len();
+        let entries = all_matching_entries
+            .into_iter()
+            .skip(start_index)
+            .take(page_size)
+            .map(|entry| entry.to_stub(self))
+            .collect::<Vec<BlogEntryStub>>();
+
+        let (previous_page, next_page) =
+            calculate_pages(page, start_index, total_matching_entries, page_size);
+
+        let tag = self.tag_context(&tag);
+        Some(BlogTagContext {
+            base: self.base_context(
+                format!("{} - Posts Tagged {}", self.blog_title(), tag.name),
+                format!("All the posts tagged {}", tag.name),
+                &paginated_path(
+                    &format!("/blog/tags/{}", encode_path_segment(&tag.slug)),
+                    page,
+                ),
+            ),
+            tag,
+            entries,
+            previous_page,
+            next_page,
+            remaining_after: calculate_remaining_after(
+                start_index,
+                total_matching_entries,
+                page_size,
+            ),
+            pagination: build_pagination_context(page.get(), total_matching_entries, page_size),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct FeedContext {
+    title: String,
+    description: String,
+    base_url: String,
+    feed_url: String,
+    ttl: Option<u32>,
+    skip_hours: Vec<u32>,
+    skip_days: Vec<String>,
+    paging: Option<FeedPagingContext>,
+    items: Vec<FeedItemContext>,
+}
+
+#[derive(Serialize)]
+pub struct FeedPagingContext {
+    first_url: String,
+    previous_url: Option<String>,
+    next_url: Option<String>,
+    last_url: String,
+}
+
+#[derive(Serialize)]
+pub struct FeedItemContext {
+    title: String,
+    description: String,
+    published_date: String,
+    url: String,
+    guid: String,
+    guid_is_permalink: bool,
+    author: String,
+}
+
+impl BlogEntry {
+    /// Builds a `FeedItemContext` that represents this `BlogEntry` in the feeds of the provided site.
+    fn to_feed_item(&self, site: &Site) -> FeedItemContext {
+        let url = format!(
+            "{}{}",
+            site.config.site_base_url,
+            blog_entry_url(&self.metadata.slug)
+        );
+        FeedItemContext {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            published_date: format_datetime_feed(self.created_at),
+            guid: url.clone(),
+            guid_is_permalink: true,
+            url,
+            author: self.author(&site.config.default_author).to_string(),
+        }
+    }
+}
+
+impl Site {
+    /// Builds the context for a page of the feed of the most recent blog entries.
+    /// Returns `None` if the page is past the last page, or isn't the first page of a feed that isn't paged.
+    pub fn build_blog_feed_context(&self, page: NonZeroUsize) -> Option<FeedContext> {
+        let feed_entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| entry.is_in_site_feeds())
+            .collect::<Vec<&BlogEntry>>();
+
+        let (items, paging) = match self.config.feed_page_size {
+            Some(page_size) => {
+                let total_pages = calculate_total_pages(feed_entries.len(), page_size);
+                if page.get() > total_pages {
+                    return None;
+                }
+                let items = feed_entries
+                    .into_iter()
+                    .skip((page.get() - 1) * page_size)
+                    .take(page_size)
+                    .map(|entry| entry.to_feed_item(self))
+                    .collect();
+                let paging = FeedPagingContext {
+                    first_url: feed_page_url(1),
+                    previous_url: (page.get() > 1).then(|| feed_page_url(page.get() - 1)),
+                    next_url: (page.get() < total_pages).then(|| feed_page_url(page.get() + 1)),
+                    last_url: feed_page_url(total_pages),
+                };
+                (items, Some(paging))
+            }
+            None => {
+                if page.get() > 1 {
+                    return None;
+                }
+                let items = feed_entries
+                    .into_iter()
+                    .take(self.config.feed_max_items)
+                    .map(|entry| entry.to_feed_item(self))
+                    .collect();
+                (items, None)
+            }
+        };
+
+        Some(FeedContext {
+            title: self.blog_title(),
+            description: self.config.site_description.clone(),
+            base_url: format!("{}/blog", self.config.site_base_url),
+            feed_url: feed_page_url(page.get()),
+            ttl: self.config.feed_ttl,
+            skip_hours: self.config.feed_skip_hours.clone(),
+            skip_days: self.config.feed_skip_days.clone(),
+            paging,
+            items,
+        })
+    }
+}
+
+impl Site {
+    /// Builds the RSS 2.0 document for the feed of the most recent blog entries.
+    pub fn build_rss_feed(&self) -> String {
+        self.build_blog_feed_context(NonZeroUsize::new(1).unwrap())
+            .expect("the first page of the feed should always exist")
+            .to_rss()
+    }
+}
+
+impl FeedContext {
+    /// Serializes this feed into an RSS 2.0 document.
+    pub fn to_rss(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        xml.push_str(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">"#);
+        xml.push_str("<channel>");
+        push_xml_element(&mut xml, "title", &self.title);
+        push_xml_element(&mut xml, "description", &self.description);
+        push_xml_element(&mut xml, "link", &self.base_url);
+        push_atom_link(
+            &mut xml,
+            &format!("{}{}", self.base_url, self.feed_url),
+            "self",
+        );
+        if let Some(paging) = &self.paging {
+            push_atom_link(
+                &mut xml,
+                &format!("{}{}", self.base_url, paging.first_url),
+                "first",
+            );
+            if let Some(previous_url) = &paging.previous_url {
+                push_atom_link(
+                    &mut xml,
+                    &format!("{}{}", self.base_url, previous_url),
+                    "previous",
+                );
+            }
+            if let Some(next_url) = &paging.next_url {
+                push_atom_link(&mut xml, &format!("{}{}", self.base_url, next_url), "next");
+            }
+            push_atom_link(
+                &mut xml,
+                &format!("{}{}", self.base_url, paging.last_url),
+                "last",
+            );
+        }
+        if let Some(ttl) = self.ttl {
+            push_xml_element(&mut xml, "ttl", &ttl.to_string());
+        }
+        if !self.skip_hours.is_empty() {
+            xml.push_str("<skipHours>");
+            for hour in &self.skip_hours {
+                push_xml_element(&mut xml, "hour", &hour.to_string());
+            }
+            xml.push_str("</skipHours>");
+        }
+        if !self.skip_days.is_empty() {
+            xml.push_str("<skipDays>");
+            for day in &self.skip_days {
+                push_xml_element(&mut xml, "day", day);
+            }
+            xml.push_str("</skipDays>");
+        }
+        for item in &self.items {
+            xml.push_str("<item>");
+            push_xml_element(&mut xml, "title", &item.title);
+            push_xml_element(&mut xml, "description", &item.description);
+            push_xml_element(&mut xml, "pubDate", &item.published_date);
+            push_xml_element(&mut xml, "dc:creator", &item.author);
+            push_xml_element(&mut xml, "link", &item.url);
+            xml.push_str(&format!(
+                r#"<guid isPermaLink="{}">"#,
+                item.guid_is_permalink
+            ));
+            xml.push_str(&escape_xml(&item.guid));
+            xml.push_str("</guid>");
+            xml.push_str("</item>");
+        }
+        xml.push_str("</channel>");
+        xml.push_str("</rss>");
+        xml
+    }
+}
+
+/// Appends an element named `name` containing the escaped `text` to `xml`.
+fn push_xml_element(xml: &mut String, name: &str, text: &str) {
+    xml.push('<');
+    xml.push_str(name);
+    xml.push('>');
+    xml.push_str(&escape_xml(text));
+    xml.push_str("</");
+    xml.push_str(name);
+    xml.push('>');
+}
+
+/// Appends an `atom:link` element pointing at `href` with the provided relation to `xml`.
+fn push_atom_link(xml: &mut String, href: &str, rel: &str) {
+    xml.push_str(&format!(
+        r#"<atom:link href="{}" rel="{}" type="application/rss+xml"/>"#,
+        escape_xml(href),
+        rel
+    ));
+}
+
+/// Escapes the characters that can't appear literally in XML text or attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Builds the URL of the provided page of the main feed, relative to the blog.
+/// The first page is the main feed itself.
+fn feed_page_url(page: usize) -> String {
+    match page {
+        1 => "/feed".to_string(),
+        _ => format!("/feed.xml?page={}", page),
+    }
+}
+
+impl Site {
+    /// Builds the context for the feed of the most recently updated blog entries.
+    /// Only entries that have been updated since they were created are included.
+    /// Each update gets its own GUID, so feed readers show an entry again when it's updated again.
+    pub fn build_blog_updated_feed_context(&self) -> FeedContext {
+        let mut updated_entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| entry.updated_at.is_some() && entry.is_in_site_feeds())
+            .collect::<Vec<&BlogEntry>>();
+        updated_entries.sort_by_key(|entry| Reverse(entry.updated_at.unwrap_or(entry.created_at)));
+
+        let items = updated_entries
+            .into_iter()
+            .take(self.config.feed_max_items)
+            .map(|entry| {
+                let updated_at = entry.updated_at.unwrap_or(entry.created_at);
+                let item = entry.to_feed_item(self);
+                FeedItemContext {
+                    published_date: format_datetime_feed(updated_at),
+                    guid: format!("{}#updated-{}", item.url, format_datetime_atom(updated_at)),
+                    guid_is_permalink: false,
+                    ..item
+                }
+            })
+            .collect();
+
+        FeedContext {
+            title: format!("{} - Updated Posts", self.blog_title()),
+            description: format!("{} - Updated Posts", self.config.site_description),
+            base_url: format!("{}/blog", self.config.site_base_url),
+            feed_url: "/updated.xml".to_string(),
+            ttl: self.config.feed_ttl,
+            skip_hours: self.config.feed_skip_hours.clone(),
+            skip_days: self.config.feed_skip_days.clone(),
+            paging: None,
+            items,
+        }
+    }
+}
+
+impl Site {
+    /// Builds the context for the feed of the most recent blog entries with the provided tag.
+    /// Returns `None` if there are no entries with the provided tag.
+    pub fn build_blog_tag_feed_context(&self, tag: String) -> Option<FeedContext> {
+        let tag = self.tag_for_slug(&tag);
+        let tagged_entries = self.entries_tagged(&tag);
+        if tagged_entries.is_empty() {
+            return None;
+        }
+
+        let items = tagged_entries
+            .into_iter()
+            .filter(|entry| entry.is_in_tag_feed(&tag))
+            .take(self.config.feed_max_items)
+            .map(|entry| entry.to_feed_item(self))
+            .collect();
+
+        let tag = self.tag_context(&tag);
+        Some(FeedContext {
+            title: format!("{} - Posts Tagged {}", self.blog_title(), tag.name),
+            description: format!(
+                "{} - Posts Tagged {}",
+                self.config.site_description, tag.name
+            ),
+            base_url: format!("{}/blog", self.config.site_base_url),
+            feed_url: format!("/tags/{}/feed", tag.slug),
+            ttl: self.config.feed_ttl,
+            skip_hours: self.config.feed_skip_hours.clone(),
+            skip_days: self.config.feed_skip_days.clone(),
+            paging: None,
+            items,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct AtomFeedContext {
+    title: String,
+    base_url: String,
+    feed_url: String,
+    updated: String,
+    author: String,
+    entries: Vec<AtomEntryContext>,
+}
+
+#[derive(Serialize)]
+pub struct AtomEntryContext {
+    title: String,
+    url: String,
+    published: String,
+    updated: String,
+    author: String,
+    content: Option<String>,
+}
+
+impl Site {
+    /// Builds the context for the Atom feed of the most recent blog entries.
+    /// The content of password-protected entries is left out.
+    pub fn build_atom_feed_context(&self) -> AtomFeedContext {
+        let feed_entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| entry.is_in_site_feeds())
+            .take(self.config.feed_max_items)
+            .collect::<Vec<&BlogEntry>>();
+
+        let updated = feed_entries
+            .iter()
+            .map(|entry| entry.updated_at.unwrap_or(entry.created_at))
+            .max()
+            .unwrap_or(self.build_report.built_at);
+
+        let entries = feed_entries
+            .into_iter()
+            .map(|entry| AtomEntryContext {
+                title: entry.title.clone(),
+                url: blog_entry_url(&entry.metadata.slug),
+                published: format_datetime_atom(entry.created_at),
+                updated: format_datetime_atom(entry.updated_at.unwrap_or(entry.created_at)),
+                author: entry.author(&self.config.default_author).to_string(),
+                content: if entry.is_password_protected() {
+                    None
+                } else {
+                    Some(entry.html_content.clone())
+                },
+            })
+            .collect::<Vec<AtomEntryContext>>();
+
+        AtomFeedContext {
+            title: self.blog_title(),
+            base_url: self.config.site_base_url.clone(),
+            feed_url: FeedFormat::Atom.url().to_string(),
+            updated: format_datetime_atom(updated),
+            author: self.config.default_author.clone(),
+            entries,
+        }
+    }
+}
+
+/// The version of the JSON Feed spec the JSON feed follows.
+const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+#[derive(Serialize)]
+pub struct JsonFeedContext {
+    version: String,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<JsonFeedItemContext>,
+}
+
+#[derive(Serialize)]
+pub struct JsonFeedItemContext {
+    id: String,
+    url: String,
+    title: String,
+    date_published: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_modified: Option<String>,
+    authors: Vec<JsonFeedAuthorContext>,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_text: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct JsonFeedAuthorContext {
+    name: String,
+}
+
+impl Site {
+    /// Builds the JSON feed of the most recent blog entries.
+    /// Password-protected entries only include their description instead of their content.
+    pub fn build_json_feed(&self) -> JsonFeedContext {
+        let base_url = &self.config.site_base_url;
+        let items = self
+            .blog_entries
+            .iter()
+            .filter(|entry| entry.is_in_site_feeds())
+            .take(self.config.feed_max_items)
+            .map(|entry| {
+                let url = format!("{}{}", base_url, blog_entry_url(&entry.metadata.slug));
+                let (content_html, content_text) = if entry.is_password_protected() {
+                    (None, Some(entry.description.clone()))
+                } else {
+                    (Some(entry.html_content.clone()), None)
+                };
+                JsonFeedItemContext {
+                    id: url.clone(),
+                    url,
+                    title: entry.title.clone(),
+                    date_published: format_datetime_atom(entry.created_at),
+                    date_modified: entry.updated_at.map(format_datetime_atom),
+                    authors: vec![JsonFeedAuthorContext {
+                        name: entry.author(&self.config.default_author).to_string(),
+                    }],
+                    tags: self
+                        .tag_contexts(&entry.tags)
+                        .into_iter()
+                        .map(|tag| tag.name)
+                        .collect(),
+                    content_html,
+                    content_text,
+                }
+            })
+            .collect::<Vec<JsonFeedItemContext>>();
+
+        JsonFeedContext {
+            version: JSON_FEED_VERSION.to_string(),
+            title: self.blog_title(),
+            home_page_url: format!("{}/blog", base_url),
+            feed_url: format!("{}{}", base_url, FeedFormat::Json.url()),
+            items,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SitemapContext {
+    urls: Vec<SitemapUrlContext>,
+}
+
+#[derive(Serialize)]
+pub struct SitemapUrlContext {
+    loc: String,
+    lastmod: Option<String>,
+}
+
+impl Site {
+    /// Builds the context for the sitemap listing the main pages, tag pages, and blog entries.
+    /// Drafts are left out.
+    pub fn build_sitemap_context(&self) -> SitemapContext {
+        let base_url = self.config.site_base_url.trim_end_matches('/');
+        let published_entries = self
+            .blog_entries
+            .iter()
+            .filter(|entry| !entry.draft)
+            .collect::<Vec<&BlogEntry>>();
+        let last_modified = |entries: &[&BlogEntry]| {
+            entries
+                .iter()
+                .map(|entry| entry.updated_at.unwrap_or(entry.created_at))
+                .max()
+                .map(format_datetime_atom)
+        };
+        let url = |path: &str, lastmod: Option<String>| SitemapUrlContext {
+            loc: format!("{}{}", base_url, path),
+            lastmod,
+        };
+
+        let mut urls = vec![
+            url("/", last_modified(&published_entries)),
+            url("/about", None),
+            url("/blog", last_modified(&published_entries)),
+        ];
+        for page in &self.pages {
+            urls.push(url(&page_url(&page.slug), None));
+        }
+        for tag in self.all_tags() {
+            let tagged_entries = self
+                .entries_tagged(&tag)
+                .into_iter()
+                .filter(|entry| !entry.draft)
+                .collect::<Vec<&BlogEntry>>();
+            if !tagged_entries.is_empty() {
+                let tag = self.tag_context(&tag);
+                urls.push(url(
+                    &format!("/blog/tags/{}", RawStr::new(&tag.slug).percent_encode()),
+                    last_modified(&tagged_entries),
+                ));
+            }
+        }
+        for entry in &published_entries {
+            urls.push(url(
+                &blog_entry_url(&entry.metadata.slug),
+                last_modified(&[entry]),
+            ));
+        }
+
+        SitemapContext { urls }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RobotsContext {
+    disallow_all: bool,
+    sitemap_url: String,
+}
+
+impl Site {
+    /// Builds the context for robots.txt.
+    pub fn build_robots_context(&self) -> RobotsContext {
+        RobotsContext {
+            disallow_all: self.config.disallow_all,
+            sitemap_url: format!(
+                "{}/sitemap.xml",
+                self.config.site_base_url.trim_end_matches('/')
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SiteInfoContext {
+    title: String,
+    description: String,
+    total_posts: usize,
+    built_at: String,
+    feed_urls: Vec<String>,
+    version: String,
+}
+
+impl Site {
+    /// Builds the context describing the site as a whole, for tools that want to discover things about it.
+    /// Drafts aren't counted as posts, even when they're included in the site for previewing.
+    pub fn build_site_info_context(&self) -> SiteInfoContext {
+        SiteInfoContext {
+            title: self.config.site_title.clone(),
+            description: self.config.site_description.clone(),
+            total_posts: self
+                .blog_entries
+                .iter()
+                .filter(|entry| !entry.draft)
+                .count(),
+            built_at: self.build_report.built_at.to_rfc3339(),
+            feed_urls: vec![
+                FeedFormat::Rss.url().to_string(),
+                FeedFormat::Atom.url().to_string(),
+                FeedFormat::Json.url().to_string(),
+                "/blog/updated.xml".to_string(),
+            ],
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ApiPostContext {
+    slug: String,
+    title: String,
+    description: String,
+    url: String,
+    tags: Vec<String>,
+    created_at: String,
+    updated_at: Option<String>,
+    outbound_links: Vec<String>,
+}
+
+impl BlogEntry {
+    /// Builds the context describing this blog entry for API clients.
+    /// The outbound links of password-protected entries are left out, since they're part of the protected content.
+    pub fn to_api_context(&self) -> ApiPostContext {
+        ApiPostContext {
+            slug: self.metadata.slug.clone(),
+            title: self.title.clone(),
+            description: self.description.clone(),
+            url: blog_entry_url(&self.metadata.slug),
+            tags: self.tags.clone(),
+            created_at: self.created_at.to_rfc3339(),
+            updated_at: self.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+            outbound_links: if self.is_password_protected() {
+                Vec::new()
+            } else {
+                self.metadata.outbound_links.clone()
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct NavNodeContext {
+    title: String,
+    url: String,
+    children: Vec<NavNodeContext>,
+}
+
+impl NavNodeContext {
+    /// Builds a `NavNodeContext` with no children.
+    fn leaf(title: &str, url: String) -> NavNodeContext {
+        NavNodeContext {
+            title: title.to_string(),
+            url,
+            children: Vec::new(),
+        }
+    }
+}
+
+impl Site {
+    /// Builds the tree of the pages on the site that can be navigated to.
+    pub fn build_nav_context(&self) -> Vec<NavNodeContext> {
+        let tags = self
+            .all_tags()
+            .iter()
+            .map(|tag| {
+                let tag = self.tag_context(tag);
+                NavNodeContext::leaf(&tag.name, format!("/blog/tags/{}", tag.slug))
+            })
+            .collect();
+
+        let recent_posts = self
+            .blog_entries
+            .iter()
+            .take(self.config.recent_entries_limit)
+            .map(|entry| NavNodeContext::leaf(&entry.title, blog_entry_url(&entry.metadata.slug)))
+            .collect();
+
+        let mut nav = vec![
+            NavNodeContext::leaf("Home", "/".to_string()),
+            NavNodeContext::leaf("About", "/about".to_string()),
+        ];
+        nav.extend(
+            self.pages
+                .iter()
+                .map(|page| NavNodeContext::leaf(&page.title, page_url(&page.slug))),
+        );
+        nav.push(NavNodeContext {
+            title: "Blog".to_string(),
+            url: "/blog".to_string(),
+            children: vec![
+                NavNodeContext {
+                    title: "Tags".to_string(),
+                    url: "/blog/tags".to_string(),
+                    children: tags,
+                },
+                NavNodeContext {
+                    title: "Recent posts".to_string(),
+                    url: "/blog".to_string(),
+                    children: recent_posts,
+                },
+                NavNodeContext::leaf("Series", "/blog/series".to_string()),
+                NavNodeContext::leaf("Discussions", "/blog/discussions".to_string()),
+                NavNodeContext::leaf("Search", "/blog/search".to_string()),
+            ],
+        });
+
+        nav
+    }
+}
+
+#[derive(Serialize)]
+pub struct DebugEntryContext {
+    slug: String,
+    title: String,
+    source_file: String,
+    html_content_file: String,
+    template_name: String,
+    associated_files: Vec<String>,
+    linked_slugs: Vec<String>,
+    tags: Vec<String>,
+    created_at: String,
+    updated_at: Option<String>,
+    draft: bool,
+    password_protected: bool,
+}
+
+impl Site {
+    /// Builds the context describing the parsed blog entries, for debugging how they were built.
+    pub fn build_debug_entries_context(&self) -> Vec<DebugEntryContext> {
+        self.blog_entries
+            .iter()
+            .map(|entry| DebugEntryContext {
+                slug: entry.metadata.slug.clone(),
+                title: entry.title.clone(),
+                source_file: entry.metadata.source_file().to_string_lossy().into_owned(),
+                html_content_file: entry
+                    .metadata
+                    .html_content_file
+                    .to_string_lossy()
+                    .into_owned(),
+                template_name: entry.metadata.template_name.clone(),
+                associated_files: entry
+                    .metadata
+                    .associated_files
+                    .iter()
+                    .map(|file| file.relative_path.to_string_lossy().into_owned())
+                    .collect(),
+                linked_slugs: entry.metadata.linked_slugs.clone(),
+                tags: entry.tags.clone(),
+                created_at: entry.created_at.to_rfc3339(),
+                updated_at: entry.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+                draft: entry.draft,
+                password_protected: entry.is_password_protected(),
+            })
+            .collect()
+    }
+
+    /// Builds the context describing the configuration the site was built with, for debugging.
+    /// Secrets are redacted.
+    pub fn build_debug_config_context(&self) -> SiteConfig {
+        SiteConfig {
+            admin_token: self
+                .config
+                .admin_token
+                .as_ref()
+                .map(|_| "<redacted>".to_string()),
+            ..self.config.clone()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ErrorContext {
+    pub base: BaseContext,
+    pub header: String,
+    pub message: String,
+    pub suggestions: Vec<BlogEntryStub>,
+}
+
+impl Site {
+    /// Builds the context for the page displayed when the blog entry with the provided slug doesn't exist, suggesting entries with similar slugs.
+    pub fn build_entry_not_found_context(&self, slug: &str) -> ErrorContext {
+        let suggestions = self
+            .suggest_slugs(slug)
+            .into_iter()
+            .filter_map(|suggested_slug| {
+                self.blog_entries
+                    .iter()
+                    .find(|entry| entry.metadata.slug == suggested_slug)
+            })
+            .map(|entry| entry.to_stub(self))
+            .collect();
+
+        ErrorContext {
+            suggestions,
+            ..not_found_context()
+        }
+    }
+}
+
+/// Builds the context for the page displayed when a page can't be found.
+pub fn not_found_context() -> ErrorContext {
+    ErrorContext {
+        base: BaseContext {
+            title: "404".to_string(),
+            meta_description: "Not a page".to_string(),
+            canonical_url: None,
+            open_graph: None,
+        },
+        header: "404".to_string(),
+        message: "That's not a page".to_string(),
+        suggestions: Vec::new(),
+    }
+}
+
+/// Builds the context for the page displayed when an admin form is submitted without the right token, like when it was loaded before the server restarted.
+pub fn forbidden_context() -> ErrorContext {
+    ErrorContext {
+        base: BaseContext {
+            title: "403".to_string(),
+            meta_description: "Not allowed".to_string(),
+            open_graph: None,
+            canonical_url: None,
+        },
+        header: "403".to_string(),
+        message: "That form is out of date, reload it and try again".to_string(),
+        suggestions: Vec::new(),
+    }
+}
+
+/// Builds the context for the page displayed when something goes wrong while handling a request.
+pub fn internal_server_error_context() -> ErrorContext {
+    ErrorContext {
+        base: BaseContext {
+            title: "500".to_string(),
+            meta_description: "Something went wrong".to_string(),
+            canonical_url: None,
+            open_graph: None,
+        },
+        header: "500".to_string(),
+        message: "Something went wrong".to_string(),
+        suggestions: Vec::new(),
+    }
+}
+
+impl GoneEntry {
+    /// Builds the context for the page displayed in place of this removed blog entry.
+    pub fn to_error_context(&self) -> ErrorContext {
+        ErrorContext {
+            base: BaseContext {
+                title: "410".to_string(),
+                meta_description: "This page has been removed".to_string(),
+                canonical_url: None,
+                open_graph: None,
+            },
+            header: "410".to_string(),
+            message: self
+                .message
+                .clone()
+                .unwrap_or_else(|| "That page has been removed".to_string()),
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+/// Converts the provided `DateTime` into a nice human-readable string.
+fn format_datetime(datetime: DateTime<Utc>) -> String {
+    let month = datetime.format("%B");
     let day = Ordinal(datetime.day()).to_string();
     let year = datetime.format("%Y");
 
     format!("{} {}, {}", month, day, year)
 }
 
+/// Converts the provided language tag (e.g. `en-US`) into the locale format used by Open Graph (e.g. `en_US`).
+fn lang_to_locale(lang: &str) -> String {
+    lang.replace('-', "_")
+}
+
 /// Converts the provided `DateTime` into a format suitable for an RSS feed.
 fn format_datetime_feed(datetime: DateTime<Utc>) -> String {
     datetime.to_rfc2822()
 }
 
+/// Converts the provided `DateTime` into the RFC 3339 format used by Atom and JSON feeds.
+fn format_datetime_atom(datetime: DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Builds the path of the provided page of the listing at `base_path`. The first page is just `base_path`.
+fn paginated_path(base_path: &str, page: NonZeroUsize) -> String {
+    match page.get() {
+        1 => base_path.to_string(),
+        page => format!("{}?page={}", base_path, page),
+    }
+}
+
+/// Builds the path of the page of the blog index with the entries created before `before`, or the newest entries if it's `None`.
+fn blog_index_cursor_path(before: Option<DateTime<Utc>>) -> String {
+    match before {
+        Some(before) => format!(
+            "/blog?before={}",
+            encode_path_segment(&before.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        ),
+        None => "/blog".to_string(),
+    }
+}
+
+/// Builds the path of the archive page for the provided year, or month of it if `month` is set.
+fn archive_path(year: i32, month: Option<u32>) -> String {
+    match month {
+        Some(month) => format!("/blog/archive/{}/{}", year, month),
+        None => format!("/blog/archive/{}", year),
+    }
+}
+
+/// Percent-encodes the provided text so it can be used as part of a URL.
+fn encode_path_segment(text: &str) -> String {
+    RawStr::new(text).percent_encode().to_string()
+}
+
+/// Calculates the number of items after the page starting at `start_index`.
+fn calculate_remaining_after(start_index: usize, total_size: usize, page_size: usize) -> usize {
+    total_size.saturating_sub(start_index + page_size)
+}
+
+/// Calculates the number of pages needed to show `total_size` items. There's always at least one page, even if there are no items.
+fn calculate_total_pages(total_size: usize, page_size: usize) -> usize {
+    total_size.div_ceil(page_size).max(1)
+}
+
+/// Builds the context describing where the page numbered `current_page` is among the pages of `total_entries` entries.
+fn build_pagination_context(
+    current_page: usize,
+    total_entries: usize,
+    page_size: usize,
+) -> PaginationContext {
+    PaginationContext {
+        current_page,
+        total_pages: calculate_total_pages(total_entries, page_size),
+        total_entries,
+    }
+}
+
 fn calculate_pages(
     current_page: NonZeroUsize,
     start_index: usize,
@@ -368,3 +2204,835 @@ fn calculate_pages(
 
     (previous_page, next_page)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SiteConfig;
+    use crate::test_util::TestSite;
+    use chrono::{Duration, TimeZone};
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    fn first_page() -> NonZeroUsize {
+        NonZeroUsize::new(1).unwrap()
+    }
+
+    #[test]
+    fn tag_pages_use_their_own_page_size() {
+        let site = TestSite::new();
+        for day in 1..=12 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!(
+                    "tags = [\"stuff\"]\ncreated_at = \"2021-01-{:02}T00:00:00Z\"",
+                    day
+                ),
+                "Some content",
+            );
+        }
+        let config = SiteConfig {
+            tag_page_size: Some(25),
+            ..SiteConfig::default()
+        };
+        let site = site.build_with(&config).unwrap();
+
+        let index = site.build_blog_index_context(first_page(), None).unwrap();
+        let tag = site
+            .build_blog_tag_context("stuff".to_string(), first_page())
+            .unwrap();
+
+        assert_eq!(10, index.entries.len());
+        assert_eq!(Some(2), index.next_page);
+        assert_eq!(12, tag.entries.len());
+        assert_eq!(None, tag.next_page);
+    }
+
+    #[test]
+    fn tags_match_case_insensitively_and_use_display_names() {
+        let site = TestSite::new();
+        site.add_entry(
+            "upper-post",
+            "tags = [\"Rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "lower-post",
+            "tags = [\"rust\"]\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let mut config = SiteConfig::default();
+        config
+            .tag_display_names
+            .insert("rust".to_string(), "Rust Lang".to_string());
+        let site = site.build_with(&config).unwrap();
+
+        let tags = site.build_blog_tags_context();
+        let tag = site
+            .build_blog_tag_context("RUST".to_string(), first_page())
+            .unwrap();
+
+        assert_eq!(1, tags.tags.len());
+        assert_eq!("rust", tags.tags[0].slug);
+        assert_eq!("Rust Lang", tags.tags[0].name);
+        assert_eq!("rust", tag.tag.slug);
+        assert_eq!(2, tag.entries.len());
+        assert_eq!("Rust Lang", tag.entries[0].tags[0].name);
+    }
+
+    #[test]
+    fn remaining_after_counts_entries_after_page() {
+        let site = TestSite::new();
+        for day in 1..=25 {
+            let tags = if day % 2 == 0 { "[\"even\"]" } else { "[]" };
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!(
+                    "tags = {}\ncreated_at = \"2021-01-{:02}T00:00:00Z\"",
+                    tags, day
+                ),
+                "Some content",
+            );
+        }
+        let site = site.build();
+
+        let middle_page = site
+            .build_blog_index_context(NonZeroUsize::new(2).unwrap(), None)
+            .unwrap();
+        let last_page = site
+            .build_blog_index_context(NonZeroUsize::new(3).unwrap(), None)
+            .unwrap();
+        let tag_page = site
+            .build_blog_tag_context("even".to_string(), first_page())
+            .unwrap();
+
+        assert_eq!(5, middle_page.remaining_after);
+        assert_eq!(0, last_page.remaining_after);
+        assert_eq!(2, tag_page.remaining_after);
+    }
+
+    fn recent_titles(site: &Site) -> Vec<String> {
+        site.build_index_context()
+            .recent_blog_entries
+            .into_iter()
+            .map(|entry| entry.title)
+            .collect()
+    }
+
+    #[test]
+    fn index_order_controls_recent_entries_order() {
+        let site = TestSite::new();
+        site.add_entry(
+            "old-post",
+            "title = \"Old\"\ncreated_at = \"2021-01-01T00:00:00Z\"\nupdated_at = \"2021-03-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "new-post",
+            "title = \"New\"\ncreated_at = \"2021-02-01T00:00:00Z\"",
+            "Some content",
+        );
+        let by_created = site.build();
+        let by_updated = site
+            .build_with(&SiteConfig {
+                index_order: IndexOrder::Updated,
+                ..SiteConfig::default()
+            })
+            .unwrap();
+
+        assert_eq!(vec!["New", "Old"], recent_titles(&by_created));
+        assert_eq!(vec!["Old", "New"], recent_titles(&by_updated));
+    }
+
+    #[test]
+    fn read_more_text_uses_entry_then_config_then_default() {
+        let site = TestSite::new();
+        site.add_entry(
+            "custom-post",
+            "read_more = \"Keep going\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "plain-post",
+            "created_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let read_more_texts = |site: &Site| {
+            site.build_blog_index_context(first_page(), None)
+                .unwrap()
+                .entries
+                .into_iter()
+                .map(|entry| entry.read_more_text)
+                .collect::<Vec<String>>()
+        };
+
+        let default_site = site.build();
+        let configured_site = site
+            .build_with(&SiteConfig {
+                read_more_text: "Continue reading".to_string(),
+                ..SiteConfig::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            vec!["Keep going", "Read more"],
+            read_more_texts(&default_site)
+        );
+        assert_eq!(
+            vec!["Keep going", "Continue reading"],
+            read_more_texts(&configured_site)
+        );
+    }
+
+    #[test]
+    fn changelog_orders_entries_by_last_change_and_flags_edits() {
+        let site = TestSite::new();
+        site.add_entry(
+            "edited-post",
+            "title = \"Edited\"\ncreated_at = \"2021-01-01T00:00:00Z\"\nupdated_at = \"2021-03-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "new-post",
+            "title = \"New\"\ncreated_at = \"2021-02-01T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        let entries = site.build_changelog_context().entries;
+
+        let summary: Vec<(&str, bool)> = entries
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.edited))
+            .collect();
+        assert_eq!(vec![("Edited", true), ("New", false)], summary);
+        assert_eq!(Some("March 1st, 2021".to_string()), entries[0].updated_at);
+        assert_eq!(None, entries[1].updated_at);
+    }
+
+    fn entry_titles(context: &BlogIndexContext) -> Vec<&str> {
+        context
+            .entries
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn cursor_pages_are_stable_when_entries_are_added() {
+        let site = TestSite::new();
+        for day in 1..=5 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!(
+                    "title = \"Post {}\"\ncreated_at = \"2021-01-{:02}T00:00:00Z\"",
+                    day, day
+                ),
+                "Some content",
+            );
+        }
+        let config = SiteConfig {
+            blog_page_size: 2,
+            pagination_style: PaginationStyle::Cursor,
+            ..SiteConfig::default()
+        };
+        let built = site.build_with(&config).unwrap();
+        let first = built.build_blog_index_context(first_page(), None).unwrap();
+        let cursor = first.next_cursor.clone().unwrap();
+        let before = DateTime::parse_from_rfc3339(&cursor)
+            .unwrap()
+            .with_timezone(&Utc);
+        let second = built
+            .build_blog_index_context(first_page(), Some(before))
+            .unwrap();
+
+        site.add_entry(
+            "post-6",
+            "title = \"Post 6\"\ncreated_at = \"2021-01-06T00:00:00Z\"",
+            "Some content",
+        );
+        let rebuilt = site.build_with(&config).unwrap();
+        let second_after_adding = rebuilt
+            .build_blog_index_context(first_page(), Some(before))
+            .unwrap();
+
+        assert_eq!(vec!["Post 5", "Post 4"], entry_titles(&first));
+        assert_eq!("2021-01-04T00:00:00Z", cursor);
+        assert_eq!(vec!["Post 3", "Post 2"], entry_titles(&second));
+        assert_eq!(1, second.remaining_after);
+        assert_eq!(entry_titles(&second), entry_titles(&second_after_adding));
+    }
+
+    /// Whether the provided path is matched by a route path like `/blog/posts/<entry_name>`.
+    fn route_matches(route_path: &str, path: &str) -> bool {
+        let route_segments = route_path.split('/').collect::<Vec<&str>>();
+        let segments = path.split('/').collect::<Vec<&str>>();
+        route_segments.len() == segments.len()
+            && route_segments
+                .iter()
+                .zip(segments)
+                .all(|(route_segment, segment)| {
+                    route_segment.starts_with('<') || *route_segment == segment
+                })
+    }
+
+    #[test]
+    fn entry_urls_match_the_entry_route() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let site = test_site.build();
+        let client = test_site.client();
+
+        let url = site.blog_entries[0].to_stub(&site).url;
+
+        assert_eq!("/blog/posts/some-post", url);
+        assert!(client
+            .rocket()
+            .routes()
+            .filter(|route| route.method == rocket::http::Method::Get)
+            .any(|route| route_matches(route.uri.path(), &url)));
+        assert_eq!(
+            rocket::http::Status::Ok,
+            client.get(url.as_str()).dispatch().status()
+        );
+    }
+
+    #[test]
+    fn feed_item_urls_are_absolute_entry_urls() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "some-post",
+            "created_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let site = test_site.build();
+
+        let item = site.blog_entries[0].to_feed_item(&site);
+
+        assert_eq!(
+            format!("{}/blog/posts/some-post", site.config.site_base_url),
+            item.url
+        );
+    }
+
+    /// Parses the provided RSS document, returning the text of the `title` element of each item.
+    fn rss_item_titles(rss: &str) -> Vec<String> {
+        let mut reader = Reader::from_str(rss);
+        let mut titles = Vec::new();
+        let mut in_item = false;
+        let mut in_title = false;
+        loop {
+            match reader.read_event().expect("feed should be well-formed XML") {
+                Event::Start(element) if element.name().as_ref() == "item" => in_item = true,
+                Event::End(element) if element.name().as_ref() == "item" => in_item = false,
+                Event::Start(element) if element.name().as_ref() == "title" && in_item => {
+                    in_title = true;
+                    titles.push(String::new());
+                }
+                Event::End(element) if element.name().as_ref() == "title" => in_title = false,
+                Event::Text(text) if in_title => titles.last_mut().unwrap().push_str(text.as_ref()),
+                Event::GeneralRef(reference) if in_title => {
+                    let character = match reference.as_ref() {
+                        "amp" => "&",
+                        "lt" => "<",
+                        "gt" => ">",
+                        "quot" => "\"",
+                        "apos" => "'",
+                        _ => panic!("unexpected entity in feed"),
+                    };
+                    titles.last_mut().unwrap().push_str(character);
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        titles
+    }
+
+    #[test]
+    fn rss_feed_is_well_formed_with_an_item_per_entry() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "first",
+            "title = \"Fish & <Chips>\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "First",
+        );
+        test_site.add_entry(
+            "second",
+            "title = \"Second\"\ndescription = \"The second one\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Second",
+        );
+
+        let rss = test_site.build().build_rss_feed();
+
+        assert_eq!(vec!["Second", "Fish & <Chips>"], rss_item_titles(&rss));
+        assert!(rss.contains("<pubDate>Sat, 02 Jan 2021 00:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<description>The second one</description>"));
+        assert!(rss.contains(
+            "<guid isPermaLink=\"true\">https://www.rotoclone.zone/blog/posts/second</guid>"
+        ));
+    }
+
+    #[test]
+    fn tags_are_counted_and_sorted_by_count_then_name() {
+        let site = TestSite::new();
+        site.add_entry(
+            "post-1",
+            "tags = [\"rust\", \"cooking\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "post-2",
+            "tags = [\"rust\", \"baking\"]\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        let tags = site
+            .build_blog_tags_context()
+            .tags
+            .into_iter()
+            .map(|tag| (tag.name, tag.count))
+            .collect::<Vec<(String, usize)>>();
+
+        assert_eq!(
+            vec![
+                ("rust".to_string(), 2),
+                ("baking".to_string(), 1),
+                ("cooking".to_string(), 1)
+            ],
+            tags
+        );
+    }
+
+    #[test]
+    fn page_size_and_recent_entries_limit_are_configurable() {
+        let site = TestSite::new();
+        for day in 1..=5 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!("created_at = \"2021-01-{:02}T00:00:00Z\"", day),
+                "Some content",
+            );
+        }
+        let config: SiteConfig = toml::from_str("page_size = 2\nrecent_entries_limit = 3").unwrap();
+        let site = site.build_with(&config).unwrap();
+
+        let second_page = site
+            .build_blog_index_context(NonZeroUsize::new(2).unwrap(), None)
+            .unwrap();
+        let last_page = site
+            .build_blog_index_context(NonZeroUsize::new(3).unwrap(), None)
+            .unwrap();
+
+        assert_eq!(3, site.build_index_context().recent_blog_entries.len());
+        assert_eq!(2, second_page.entries.len());
+        assert_eq!(Some(3), second_page.next_page);
+        assert_eq!(1, last_page.entries.len());
+        assert_eq!(None, last_page.next_page);
+    }
+
+    #[test]
+    fn page_titles_use_the_configured_site_title_and_description() {
+        let config = SiteConfig {
+            site_title: "Some Site".to_string(),
+            site_description: "A site about things".to_string(),
+            ..SiteConfig::default()
+        };
+        let site = TestSite::new().build_with(&config).unwrap();
+
+        let index = site.build_index_context();
+        let blog_index = site.build_blog_index_context(first_page(), None).unwrap();
+
+        assert_eq!("Some Site", index.base.title);
+        assert_eq!("A site about things", index.base.meta_description);
+        assert_eq!("Some Site Blog", blog_index.base.title);
+        assert_eq!("A site about things", blog_index.base.meta_description);
+    }
+
+    #[test]
+    fn feed_descriptions_use_the_configured_site_description() {
+        let config = SiteConfig {
+            site_description: "A site about things".to_string(),
+            ..SiteConfig::default()
+        };
+        let site = TestSite::new();
+        site.add_entry(
+            "post-1",
+            "tags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build_with(&config).unwrap();
+
+        assert_eq!(
+            "A site about things",
+            site.build_blog_feed_context(first_page())
+                .unwrap()
+                .description
+        );
+        assert_eq!(
+            "A site about things - Updated Posts",
+            site.build_blog_updated_feed_context().description
+        );
+        assert_eq!(
+            "A site about things - Posts Tagged rust",
+            site.build_blog_tag_feed_context("rust".to_string())
+                .unwrap()
+                .description
+        );
+    }
+
+    #[test]
+    fn paginated_listings_include_their_position() {
+        let site = TestSite::new();
+        for day in 1..=5 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!(
+                    "tags = [\"stuff\"]\ncreated_at = \"2021-01-{:02}T00:00:00Z\"",
+                    day
+                ),
+                "Some content",
+            );
+        }
+        let config = SiteConfig {
+            blog_page_size: 2,
+            ..SiteConfig::default()
+        };
+        let site = site.build_with(&config).unwrap();
+
+        let index = site
+            .build_blog_index_context(NonZeroUsize::new(2).unwrap(), None)
+            .unwrap();
+        let tag = site
+            .build_blog_tag_context("stuff".to_string(), NonZeroUsize::new(3).unwrap())
+            .unwrap();
+
+        assert_eq!(2, index.pagination.current_page);
+        assert_eq!(3, index.pagination.total_pages);
+        assert_eq!(5, index.pagination.total_entries);
+        assert_eq!(3, tag.pagination.current_page);
+        assert_eq!(3, tag.pagination.total_pages);
+        assert_eq!(5, tag.pagination.total_entries);
+    }
+
+    #[test]
+    fn entries_use_their_own_author_or_the_default() {
+        let site = TestSite::new();
+        site.add_entry(
+            "guest-post",
+            "author = \"Some Guest\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "regular-post",
+            "created_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let config = SiteConfig {
+            default_author: "Some Author".to_string(),
+            ..SiteConfig::default()
+        };
+        let site = site.build_with(&config).unwrap();
+
+        let rss = site.build_rss_feed();
+        let guest_entry = site.build_blog_entry_context(&site.blog_entries[1]);
+
+        assert!(rss.contains("<dc:creator>Some Guest</dc:creator>"));
+        assert!(rss.contains("<dc:creator>Some Author</dc:creator>"));
+        assert_eq!("Some Guest", guest_entry.author);
+        assert!(guest_entry
+            .json_ld
+            .contains(r#""author":{"@type":"Person","name":"Some Guest"}"#));
+    }
+
+    #[test]
+    fn canonical_urls_leave_out_the_first_page_number() {
+        let site = TestSite::new();
+        for day in 1..=3 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!("created_at = \"2021-01-{:02}T00:00:00Z\"", day),
+                "Some content",
+            );
+        }
+        let config = SiteConfig {
+            blog_page_size: 2,
+            ..SiteConfig::default()
+        };
+        let site = site.build_with(&config).unwrap();
+
+        let first_page = site.build_blog_index_context(first_page(), None).unwrap();
+        let second_page = site
+            .build_blog_index_context(NonZeroUsize::new(2).unwrap(), None)
+            .unwrap();
+
+        assert_eq!(
+            Some("https://www.rotoclone.zone/blog".to_string()),
+            first_page.base.canonical_url
+        );
+        assert_eq!(
+            Some("https://www.rotoclone.zone/blog?page=2".to_string()),
+            second_page.base.canonical_url
+        );
+        assert_eq!(None, not_found_context().base.canonical_url);
+    }
+
+    #[test]
+    fn search_results_need_every_term_and_rank_title_matches_first() {
+        let site = TestSite::new();
+        site.add_entry(
+            "content-match",
+            "title = \"Dinner\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "I made some Rust-flavored soup",
+        );
+        site.add_entry(
+            "title-match",
+            "title = \"Rust soup\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "partial-match",
+            "title = \"Soup\"\ncreated_at = \"2021-01-03T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        let titles = |query: &str| {
+            site.build_search_context(query.to_string())
+                .entries
+                .into_iter()
+                .map(|entry| entry.title)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(vec!["Rust soup", "Dinner"], titles("RUST soup"));
+        assert!(titles("   ").is_empty());
+    }
+
+    #[test]
+    fn tags_are_displayed_the_way_the_newest_entry_writes_them() {
+        let site = TestSite::new();
+        site.add_entry(
+            "older-post",
+            "tags = [\"rust\", \"web dev\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "newer-post",
+            "tags = [\"  Rust \"]\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        let mut names = site
+            .build_blog_tags_context()
+            .tags
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect::<Vec<String>>();
+        names.sort();
+
+        assert_eq!(vec!["Rust", "web dev"], names);
+    }
+
+    #[test]
+    fn updated_feed_lists_only_edited_entries_by_update_time() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "edited-long-ago",
+            "title = \"Edited Long Ago\"\ncreated_at = \"2021-01-03T00:00:00Z\"\nupdated_at = \"2021-01-04T00:00:00Z\"",
+            "Content",
+        );
+        test_site.add_entry(
+            "never-edited",
+            "title = \"Never Edited\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Content",
+        );
+        test_site.add_entry(
+            "edited-recently",
+            "title = \"Edited Recently\"\ncreated_at = \"2021-01-01T00:00:00Z\"\nupdated_at = \"2021-01-05T00:00:00Z\"",
+            "Content",
+        );
+        let site = test_site.build();
+
+        let rss = site.build_blog_updated_feed_context().to_rss();
+
+        assert_eq!(
+            vec!["Edited Recently", "Edited Long Ago"],
+            rss_item_titles(&rss)
+        );
+        assert!(rss.contains(&format!(
+            "<guid isPermaLink=\"false\">{}/blog/posts/edited-recently#updated-2021-01-05T00:00:00Z</guid>",
+            site.config.site_base_url
+        )));
+    }
+
+    #[test]
+    fn entry_locales_come_from_lang_and_translations() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "hello",
+            "lang = \"en-US\"\ncreated_at = \"2021-01-01T00:00:00Z\"\n\
+            [[translations]]\nlang = \"fr-FR\"\nslug = \"bonjour\"",
+            "Hello",
+        );
+        test_site.add_entry(
+            "bonjour",
+            "lang = \"fr-FR\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Bonjour",
+        );
+        test_site.add_entry(
+            "untranslated",
+            "created_at = \"2021-01-01T00:00:00Z\"",
+            "Content",
+        );
+        let config = SiteConfig {
+            site_locale: "en_GB".to_string(),
+            ..SiteConfig::default()
+        };
+        let site = test_site.build_with(&config).unwrap();
+        let context = |slug: &str| {
+            let entry = site
+                .blog_entries
+                .iter()
+                .find(|entry| entry.metadata.slug == slug)
+                .unwrap();
+            site.build_blog_entry_context(entry)
+        };
+
+        let translated = context("hello");
+        assert_eq!("en_US", translated.locale);
+        assert_eq!(vec!["fr_FR"], translated.alternate_locales);
+
+        let untranslated = context("untranslated");
+        assert_eq!("en_GB", untranslated.locale);
+        assert!(untranslated.alternate_locales.is_empty());
+    }
+
+    #[test]
+    fn future_entries_are_left_out_of_the_index() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "published",
+            "title = \"Published\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Published",
+        );
+        test_site.add_entry(
+            "scheduled",
+            &format!(
+                "title = \"Scheduled\"\ncreated_at = \"{}\"",
+                (Utc::now() + chrono::Duration::days(1)).to_rfc3339()
+            ),
+            "Scheduled",
+        );
+        let site = test_site.build();
+
+        let context = site
+            .build_blog_index_context(NonZeroUsize::new(1).unwrap(), None)
+            .unwrap();
+
+        assert_eq!(
+            vec!["Published"],
+            context
+                .entries
+                .iter()
+                .map(|entry| entry.title.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert!(site.next_scheduled_publish.is_some());
+    }
+
+    #[test]
+    fn series_urls_are_percent_encoded() {
+        let site = TestSite::new();
+        site.add_entry(
+            "post-1",
+            "series = \"Rust & Me?\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        let series = site.build_series_index_context().series;
+
+        assert_eq!(1, series.len());
+        assert_eq!("Rust & Me?", series[0].name);
+        assert_eq!("/blog/series/Rust%20%26%20Me%3F", series[0].url);
+    }
+
+    #[test]
+    fn posts_this_month_counts_only_entries_from_the_current_month() {
+        let now = Utc::now();
+        let month_start = Utc.ymd(now.year(), now.month(), 1).and_hms(0, 0, 0);
+        let site = TestSite::new();
+        site.add_entry(
+            "this-month",
+            &format!("created_at = \"{}\"", month_start.to_rfc3339()),
+            "Some content",
+        );
+        site.add_entry(
+            "last-month",
+            &format!(
+                "created_at = \"{}\"",
+                (month_start - Duration::days(1)).to_rfc3339()
+            ),
+            "Some content",
+        );
+        let site = site.build();
+
+        let index_context = site.build_index_context();
+        assert_eq!(1, index_context.posts_this_month.count);
+        assert_eq!(
+            "/blog/posts/this-month",
+            index_context.posts_this_month.entries[0].url
+        );
+        let archive_context = site
+            .build_archive_context(now.year(), Some(now.month()))
+            .unwrap();
+        assert_eq!(1, archive_context.posts_this_month.count);
+    }
+
+    #[test]
+    fn posts_this_month_uses_the_display_timezone() {
+        let site = TestSite::new();
+        site.add_entry(
+            "february",
+            "created_at = \"2021-02-15T00:00:00Z\"",
+            "Some content",
+        );
+        // still February two hours behind UTC
+        site.add_entry(
+            "late-february",
+            "created_at = \"2021-03-01T01:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "march",
+            "created_at = \"2021-03-01T03:00:00Z\"",
+            "Some content",
+        );
+        let site = site
+            .build_with(&SiteConfig {
+                display_utc_offset_minutes: -120,
+                ..SiteConfig::default()
+            })
+            .unwrap();
+
+        let context = site.build_posts_in_month_context(Utc.ymd(2021, 2, 28).and_hms(23, 0, 0));
+
+        let urls = context
+            .entries
+            .iter()
+            .map(|entry| entry.url.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec!["/blog/posts/late-february", "/blog/posts/february"],
+            urls
+        );
+        assert_eq!(2, context.count);
+    }
+}
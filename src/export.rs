@@ -0,0 +1,101 @@
+use anyhow::Context;
+use chrono::Utc;
+use rocket::serde::json::serde_json;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::site::{BlogEntry, ExternalDiscussion, Site, Translation};
+
+/// A complete snapshot of the site's content, for backing it up or migrating it elsewhere.
+#[derive(Serialize)]
+pub struct SiteExport<'a> {
+    version: &'static str,
+    exported_at: String,
+    entries: Vec<EntryExport<'a>>,
+}
+
+/// A snapshot of a single blog entry's metadata.
+/// Paths are relative to the site content directory.
+#[derive(Serialize)]
+pub struct EntryExport<'a> {
+    slug: &'a str,
+    title: &'a str,
+    description: &'a str,
+    source_file: String,
+    associated_files: Vec<String>,
+    template_name: &'a str,
+    tags: &'a [String],
+    created_at: String,
+    updated_at: Option<String>,
+    comments_enabled: bool,
+    external_discussions: &'a [ExternalDiscussion],
+    password: Option<&'a str>,
+    lang: Option<&'a str>,
+    translations: &'a [Translation],
+    draft: bool,
+    read_more: Option<&'a str>,
+    author: Option<&'a str>,
+    image: Option<&'a str>,
+}
+
+impl Site {
+    /// Serializes a snapshot of all the blog entries on the site as JSON.
+    ///
+    /// # Errors
+    /// Returns any errors encountered while serializing the snapshot.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        let export = SiteExport {
+            version: env!("CARGO_PKG_VERSION"),
+            exported_at: Utc::now().to_rfc3339(),
+            entries: self
+                .blog_entries
+                .iter()
+                .map(|entry| entry.to_export(&self.source_dir))
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&export).context("error serializing site export")
+    }
+}
+
+impl BlogEntry {
+    /// Builds an `EntryExport` for this entry, with paths relative to `source_dir`.
+    fn to_export(&self, source_dir: &Path) -> EntryExport<'_> {
+        let source_file = self.metadata.source_file();
+        let entry_dir = source_file.parent().unwrap_or(source_file);
+
+        EntryExport {
+            slug: &self.metadata.slug,
+            title: &self.title,
+            description: &self.description,
+            source_file: relative_path_string(source_file, source_dir),
+            associated_files: self
+                .metadata
+                .associated_files
+                .iter()
+                .map(|file| relative_path_string(&entry_dir.join(&file.relative_path), source_dir))
+                .collect(),
+            template_name: &self.metadata.template_name,
+            tags: &self.tags,
+            created_at: self.created_at.to_rfc3339(),
+            updated_at: self.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+            comments_enabled: self.comments_enabled,
+            external_discussions: &self.external_discussions,
+            password: self.password.as_deref(),
+            lang: self.lang.as_deref(),
+            translations: &self.translations,
+            draft: self.draft,
+            read_more: self.read_more.as_deref(),
+            author: self.author.as_deref(),
+            image: self.image.as_deref(),
+        }
+    }
+}
+
+/// Converts the provided path to a string relative to `base`, or the whole path if it isn't under `base`.
+fn relative_path_string(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,83 @@
+use anyhow::anyhow;
+use std::sync::OnceLock;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+/// The syntax definitions code blocks can be highlighted with, loaded the first time they're needed.
+static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+
+/// The themes code blocks can be highlighted with, loaded the first time they're needed.
+static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+
+/// Finds the syntax highlighting theme with the provided name.
+///
+/// # Errors
+/// Returns an error listing the available themes if there isn't one with the provided name.
+pub fn find_theme(name: &str) -> anyhow::Result<&'static Theme> {
+    let themes = &THEME_SET.get_or_init(ThemeSet::load_defaults).themes;
+    themes.get(name).ok_or_else(|| {
+        anyhow!(
+            "unknown syntax theme \"{}\", available themes are: {}",
+            name,
+            themes.keys().cloned().collect::<Vec<String>>().join(", ")
+        )
+    })
+}
+
+/// Highlights the provided code as HTML with inline styles.
+/// Returns `None` if the language isn't recognized, so the code can be rendered without highlighting instead.
+///
+/// # Arguments
+/// * `code` - The code to highlight.
+/// * `lang` - The info string of the code block the code is from, like `rust` or `rust,ignore`.
+/// * `theme_name` - The name of the theme to highlight the code with.
+///
+/// # Errors
+/// Returns an error if there's no theme with the provided name, or the code can't be highlighted.
+pub fn highlight_code(code: &str, lang: &str, theme_name: &str) -> anyhow::Result<Option<String>> {
+    let token = match lang.split([',', ' ']).next() {
+        Some(token) if !token.is_empty() => token,
+        _ => return Ok(None),
+    };
+    let syntax_set = SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines);
+    let syntax = match syntax_set.find_syntax_by_token(token) {
+        Some(syntax) => syntax,
+        None => return Ok(None),
+    };
+
+    let html = highlighted_html_for_string(code, syntax_set, syntax, find_theme(theme_name)?)?;
+
+    Ok(Some(html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_is_highlighted_only_in_recognized_languages() {
+        let highlighted = highlight_code("fn main() {}", "rust,ignore", "InspiredGitHub")
+            .unwrap()
+            .unwrap();
+
+        assert!(highlighted.starts_with("<pre style="));
+        assert!(highlighted.contains("<span style="));
+        assert_eq!(
+            None,
+            highlight_code("some text", "not-a-language", "InspiredGitHub").unwrap()
+        );
+        assert_eq!(
+            None,
+            highlight_code("some text", "", "InspiredGitHub").unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_themes_are_rejected() {
+        let error = find_theme("Not A Theme").unwrap_err().to_string();
+
+        assert!(error.contains("unknown syntax theme \"Not A Theme\""));
+        assert!(error.contains("InspiredGitHub"));
+    }
+}
@@ -1,14 +1,50 @@
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
 use std::num::NonZeroUsize;
+use std::time::Duration;
 
-use rocket::fs::{FileServer, NamedFile, Options};
-use rocket::{response::Redirect, State};
+use rocket::figment::Figment;
+use rocket::form::Form;
+use rocket::fs::{FileServer, Options};
+use rocket::http::{ContentType, Method, Status};
+use rocket::request::{self, FromRequest};
+use rocket::response::content::Xml;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::{response::Redirect, Request, Route, State};
 use rocket_dyn_templates::Template;
-use std::path::PathBuf;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
 
 #[macro_use]
 extern crate rocket;
 
+mod admin;
+use admin::{AdminBodyLimit, AdminForm, AdminToken, CsrfKey, LocalOrAdminToken};
+
+mod asset_manifest;
+use asset_manifest::AssetManifest;
+
+mod build_cache;
+
+mod config;
+use config::SiteConfig;
+
+mod highlight;
+
+mod math;
+
+mod ranged_file;
+use ranged_file::RangedFile;
+
+mod shortcodes;
 mod site;
+use site::{
+    blog_entry_url, read_front_matter_and_content, write_front_matter_and_content, BlogEntry,
+    BuildReport, BLOG_ENTRY_URL_PREFIX,
+};
+
+mod toc;
 
 mod updating_site;
 use updating_site::*;
@@ -16,6 +52,16 @@ use updating_site::*;
 mod context;
 use context::*;
 
+mod export;
+
+mod xml_minifier;
+use xml_minifier::XmlMinifier;
+
+#[cfg(test)]
+mod test_util;
+
+const STATIC_FILES_DIR: &str = "static";
+
 const ADDITIONAL_STATIC_FILES_DIR_CONFIG_KEY: &str = "static_files_dir";
 
 const SITE_CONTENT_BASE_DIR_CONFIG_KEY: &str = "site_content_base_dir";
@@ -24,6 +70,9 @@ const DEFAULT_SITE_CONTENT_BASE_DIR: &str = "./site_content";
 const RENDERED_HTML_BASE_DIR_CONFIG_KEY: &str = "rendered_html_base_dir";
 const DEFAULT_RENDERED_HTML_BASE_DIR: &str = "./rendered_html";
 
+/// The log levels to use if `RUST_LOG` isn't set.
+const DEFAULT_LOG_FILTER: &str = "info";
+
 #[get("/")]
 fn index(updating_site: &State<UpdatingSite>) -> Template {
     let context = updating_site.site.read().unwrap().build_index_context();
@@ -36,14 +85,32 @@ fn about(updating_site: &State<UpdatingSite>) -> Template {
     Template::render("about", &context)
 }
 
-#[get("/blog?<page>")]
-fn get_blog_index(page: Option<NonZeroUsize>, updating_site: &State<UpdatingSite>) -> Template {
-    let context = updating_site
-        .site
-        .read()
-        .unwrap()
-        .build_blog_index_context(page.unwrap_or_else(|| NonZeroUsize::new(1).unwrap()));
-    Template::render("blog_index", &context)
+// ranked after the static files, so pages can't hide them
+#[get("/<slug>", rank = 11)]
+fn get_page(slug: String, updating_site: &State<UpdatingSite>) -> Option<Template> {
+    let site = &updating_site.site.read().unwrap();
+    let page = site.pages.iter().find(|page| page.slug == slug)?;
+
+    Some(Template::render(
+        page.template_name.clone(),
+        site.build_page_context(page),
+    ))
+}
+
+#[get("/blog?<page>&<before>")]
+fn get_blog_index(
+    page: Option<NonZeroUsize>,
+    before: Option<String>,
+    updating_site: &State<UpdatingSite>,
+) -> Option<Template> {
+    let before = before
+        .and_then(|before| DateTime::parse_from_rfc3339(&before).ok())
+        .map(|before| before.with_timezone(&Utc));
+    let context = updating_site.site.read().unwrap().build_blog_index_context(
+        page.unwrap_or_else(|| NonZeroUsize::new(1).unwrap()),
+        before,
+    );
+    context.map(|x| Template::render("blog_index", &x))
 }
 
 #[get("/blog/posts")]
@@ -51,42 +118,201 @@ fn get_blog_posts() -> Redirect {
     Redirect::permanent("/blog")
 }
 
-#[get("/blog/posts/<entry_name>")]
-fn get_blog_entry(entry_name: String, updating_site: &State<UpdatingSite>) -> Option<Template> {
+#[get("/blog/posts/<entry_name>?<tag>")]
+fn get_blog_entry(
+    entry_name: String,
+    tag: Option<String>,
+    updating_site: &State<UpdatingSite>,
+) -> Option<(Status, Template)> {
     let site = &updating_site.site.read().unwrap();
     let entry = site
         .blog_entries
         .iter()
         .find(|entry| entry.metadata.slug == entry_name);
 
-    entry.map(|x| {
-        Template::render(
-            x.metadata.template_name.clone(),
-            site.build_blog_entry_context(x)
-                .unwrap_or_else(|e| panic!("error rendering blog entry {}: {}", entry_name, e)),
-        )
-    })
+    let entry = match entry {
+        Some(entry) => entry,
+        None => {
+            return site
+                .gone_entries
+                .iter()
+                .find(|gone_entry| gone_entry.slug == entry_name)
+                .map(|gone_entry| {
+                    (
+                        Status::Gone,
+                        Template::render("error", gone_entry.to_error_context()),
+                    )
+                })
+        }
+    };
+
+    if entry.is_password_protected() {
+        return Some((
+            Status::Ok,
+            Template::render(
+                "blog_entry_password",
+                site.build_blog_entry_password_context(entry, false),
+            ),
+        ));
+    }
+
+    let context = match tag {
+        Some(tag) => site.build_blog_entry_context_within_tag(entry, &tag),
+        None => site.build_blog_entry_context(entry),
+    };
+
+    Some((
+        Status::Ok,
+        Template::render(entry.metadata.template_name.clone(), context),
+    ))
 }
 
-#[get("/blog/posts/<entry_name>/<path..>", rank = 0)]
-fn get_blog_entry_file(
+/// Request guard that forwards requests for paths that aren't blog entries, so the short entry route doesn't hide other routes when it's ranked before them.
+struct ExistingEntry;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ExistingEntry {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        // the slug is the segment after `/blog`
+        let slug = match request.param::<String>(1) {
+            Some(Ok(slug)) => slug,
+            _ => return request::Outcome::Forward(()),
+        };
+        let exists = request
+            .rocket()
+            .state::<UpdatingSite>()
+            .is_some_and(|updating_site| {
+                updating_site
+                    .site
+                    .read()
+                    .unwrap()
+                    .blog_entries
+                    .iter()
+                    .any(|entry| entry.metadata.slug == slug)
+            });
+
+        if exists {
+            request::Outcome::Success(ExistingEntry)
+        } else {
+            request::Outcome::Forward(())
+        }
+    }
+}
+
+// the rank is set from the config when the route is mounted
+#[get("/blog/<entry_name>")]
+fn get_blog_entry_short(entry_name: String, _entry: ExistingEntry) -> Redirect {
+    Redirect::to(blog_entry_url(&entry_name))
+}
+
+#[derive(FromForm)]
+struct EntryPasswordForm {
+    password: String,
+}
+
+#[post("/blog/posts/<entry_name>", data = "<form>")]
+fn post_blog_entry_password(
     entry_name: String,
-    path: PathBuf,
+    form: Form<EntryPasswordForm>,
     updating_site: &State<UpdatingSite>,
-) -> Option<NamedFile> {
+) -> Option<(Status, Template)> {
     let site = &updating_site.site.read().unwrap();
     let entry = site
         .blog_entries
         .iter()
         .find(|entry| entry.metadata.slug == entry_name)?;
-    let full_path = entry
-        .metadata
-        .associated_files
+
+    if !entry.check_password(&form.password) {
+        return Some((
+            Status::Unauthorized,
+            Template::render(
+                "blog_entry_password",
+                site.build_blog_entry_password_context(entry, true),
+            ),
+        ));
+    }
+
+    Some((
+        Status::Ok,
+        Template::render(
+            entry.metadata.template_name.clone(),
+            site.build_blog_entry_context(entry),
+        ),
+    ))
+}
+
+#[get("/blog/posts/<entry_name>/source.md")]
+async fn get_blog_entry_source(
+    entry_name: String,
+    updating_site: &State<UpdatingSite>,
+) -> Option<(ContentType, String)> {
+    // the site lock can't be held while waiting for the file to be read
+    let source_file = {
+        let site = &updating_site.site.read().unwrap();
+        let entry = site
+            .blog_entries
+            .iter()
+            .find(|entry| entry.metadata.slug == entry_name)?;
+        // the source would give away the content, and the password along with it
+        if entry.is_password_protected() {
+            return None;
+        }
+        entry.metadata.source_file().to_path_buf()
+    };
+
+    // the file may have been removed since the site was built
+    let source = rocket::tokio::fs::read_to_string(&source_file).await.ok()?;
+    Some((
+        ContentType::with_params("text", "markdown", ("charset", "utf-8")),
+        source,
+    ))
+}
+
+#[get("/blog/posts/<entry_name>/<path..>", rank = 0)]
+async fn get_blog_entry_file(
+    entry_name: String,
+    path: PathBuf,
+    updating_site: &State<UpdatingSite>,
+) -> Option<RangedFile> {
+    // the site lock can't be held while waiting for the file to open
+    let full_path = {
+        let site = &updating_site.site.read().unwrap();
+        let entry = site
+            .blog_entries
+            .iter()
+            .find(|entry| entry.metadata.slug == entry_name)?;
+        if entry.is_password_protected() {
+            return None;
+        }
+        entry
+            .metadata
+            .associated_files
+            .iter()
+            .find(|file| file.relative_path == path)
+            .map(|file| file.full_path.clone())?
+    };
+
+    // the file may have been removed since the site was built
+    RangedFile::open(&full_path).await.ok()
+}
+
+#[get("/<path..>")]
+async fn get_shared_asset(
+    path: PathBuf,
+    updating_site: &State<UpdatingSite>,
+) -> Option<RangedFile> {
+    let full_path = updating_site
+        .site
+        .read()
+        .unwrap()
+        .shared_assets
         .iter()
         .find(|file| file.relative_path == path)
-        .map(|file| &file.full_path)?;
+        .map(|file| file.full_path.clone())?;
 
-    futures::executor::block_on(NamedFile::open(full_path)).ok()
+    RangedFile::open(&full_path).await.ok()
 }
 
 #[get("/blog/tags")]
@@ -101,54 +327,488 @@ fn get_blog_tag(
     page: Option<NonZeroUsize>,
     updating_site: &State<UpdatingSite>,
 ) -> Option<Template> {
+    let site = updating_site.site.read().unwrap();
+    let template_name = site
+        .config
+        .tag_template_name(&site.tag_for_slug(&tag))
+        .to_string();
+    let context =
+        site.build_blog_tag_context(tag, page.unwrap_or_else(|| NonZeroUsize::new(1).unwrap()));
+
+    context.map(|x| Template::render(template_name, &x))
+}
+
+#[get("/blog/tags/<tag>/feed")]
+fn get_blog_tag_feed(tag: String, updating_site: &State<UpdatingSite>) -> Option<Xml<String>> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_blog_tag_feed_context(tag);
+    context.map(|x| Xml(x.to_rss()))
+}
+
+#[get("/blog/book.html?<tag>")]
+fn get_blog_book(tag: Option<String>, updating_site: &State<UpdatingSite>) -> Option<Template> {
+    let context = updating_site.site.read().unwrap().build_book_context(tag);
+    context.map(|x| Template::render("book", &x))
+}
+
+#[get("/blog/series")]
+fn get_blog_series_index(updating_site: &State<UpdatingSite>) -> Template {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_series_index_context();
+    Template::render("blog_series_index", &context)
+}
+
+#[get("/blog/series/<name>")]
+fn get_blog_series(name: String, updating_site: &State<UpdatingSite>) -> Option<Template> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_blog_series_context(name);
+    context.map(|x| Template::render("blog_series", &x))
+}
+
+#[get("/blog/search?<q>")]
+fn get_blog_search(q: Option<String>, updating_site: &State<UpdatingSite>) -> Template {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_search_context(q.unwrap_or_default());
+    Template::render("blog_search", &context)
+}
+
+#[get("/blog/archive/<year>")]
+fn get_blog_archive_year(year: i32, updating_site: &State<UpdatingSite>) -> Option<Template> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_archive_context(year, None);
+    context.map(|x| Template::render("blog_archive", &x))
+}
+
+#[get("/blog/archive/<year>/<month>")]
+fn get_blog_archive_month(
+    year: i32,
+    month: u32,
+    updating_site: &State<UpdatingSite>,
+) -> Option<Template> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_archive_context(year, Some(month));
+    context.map(|x| Template::render("blog_archive", &x))
+}
+
+// ranked after the static files, so custom taxonomies can't hide them
+#[get("/blog/<taxonomy>", rank = 11)]
+fn get_taxonomy(taxonomy: String, updating_site: &State<UpdatingSite>) -> Option<Template> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_taxonomy_context(taxonomy);
+    context.map(|x| Template::render("taxonomy", &x))
+}
+
+#[get("/blog/<taxonomy>/<term>?<page>", rank = 11)]
+fn get_taxonomy_term(
+    taxonomy: String,
+    term: String,
+    page: Option<NonZeroUsize>,
+    updating_site: &State<UpdatingSite>,
+) -> Option<Template> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_taxonomy_term_context(
+            taxonomy,
+            term,
+            page.unwrap_or_else(|| NonZeroUsize::new(1).unwrap()),
+        );
+    context.map(|x| Template::render("taxonomy_term", &x))
+}
+
+#[get("/blog/discussions")]
+fn get_blog_discussions(updating_site: &State<UpdatingSite>) -> Template {
     let context = updating_site
         .site
         .read()
         .unwrap()
-        .build_blog_tag_context(tag, page.unwrap_or_else(|| NonZeroUsize::new(1).unwrap()));
+        .build_discussions_context();
+    Template::render("blog_discussions", &context)
+}
 
-    context.map(|x| Template::render("blog_tag", &x))
+#[get("/changelog")]
+fn get_changelog(updating_site: &State<UpdatingSite>) -> Template {
+    let context = updating_site.site.read().unwrap().build_changelog_context();
+    Template::render("changelog", &context)
 }
 
 #[get("/blog/feed")]
-fn get_blog_feed(updating_site: &State<UpdatingSite>) -> Template {
-    let context = updating_site.site.read().unwrap().build_blog_feed_context();
-    Template::render("feed", &context)
+fn get_blog_feed(updating_site: &State<UpdatingSite>) -> Xml<String> {
+    Xml(updating_site.site.read().unwrap().build_rss_feed())
+}
+
+#[get("/blog/feed.xml?<page>")]
+fn get_blog_feed_page(
+    page: Option<NonZeroUsize>,
+    updating_site: &State<UpdatingSite>,
+) -> Option<Xml<String>> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_blog_feed_context(page.unwrap_or_else(|| NonZeroUsize::new(1).unwrap()));
+    context.map(|x| Xml(x.to_rss()))
+}
+
+#[get("/feed")]
+fn get_feed_redirect(updating_site: &State<UpdatingSite>) -> Redirect {
+    redirect_to_default_feed(updating_site)
+}
+
+#[get("/rss")]
+fn get_rss_redirect(updating_site: &State<UpdatingSite>) -> Redirect {
+    redirect_to_default_feed(updating_site)
+}
+
+#[get("/atom")]
+fn get_atom_redirect(updating_site: &State<UpdatingSite>) -> Redirect {
+    redirect_to_default_feed(updating_site)
+}
+
+/// Builds a temporary redirect to the feed in the configured default format.
+fn redirect_to_default_feed(updating_site: &UpdatingSite) -> Redirect {
+    Redirect::found(updating_site.config.feed_default_format.url())
+}
+
+#[get("/blog/feed.atom")]
+fn get_blog_atom_feed(updating_site: &State<UpdatingSite>) -> (ContentType, Template) {
+    let context = updating_site.site.read().unwrap().build_atom_feed_context();
+    (
+        ContentType::new("application", "atom+xml"),
+        Template::render("atom", &context),
+    )
+}
+
+#[get("/blog/feed.json")]
+fn get_blog_json_feed(updating_site: &State<UpdatingSite>) -> (ContentType, Json<JsonFeedContext>) {
+    let feed = updating_site.site.read().unwrap().build_json_feed();
+    (ContentType::new("application", "feed+json"), Json(feed))
+}
+
+#[get("/blog/updated.xml")]
+fn get_blog_updated_feed(updating_site: &State<UpdatingSite>) -> Xml<String> {
+    let context = updating_site
+        .site
+        .read()
+        .unwrap()
+        .build_blog_updated_feed_context();
+    Xml(context.to_rss())
+}
+
+#[get("/sitemap.xml")]
+fn get_sitemap(updating_site: &State<UpdatingSite>) -> Template {
+    let context = updating_site.site.read().unwrap().build_sitemap_context();
+    Template::render("sitemap", &context)
+}
+
+#[get("/robots.txt")]
+fn get_robots(updating_site: &State<UpdatingSite>) -> Template {
+    let context = updating_site.site.read().unwrap().build_robots_context();
+    Template::render("robots", &context)
+}
+
+#[get("/api/site")]
+fn get_site_info(updating_site: &State<UpdatingSite>) -> Json<SiteInfoContext> {
+    Json(updating_site.site.read().unwrap().build_site_info_context())
+}
+
+#[get("/api/posts/<entry_name>")]
+fn get_api_post(
+    entry_name: String,
+    updating_site: &State<UpdatingSite>,
+) -> Option<Json<ApiPostContext>> {
+    let site = &updating_site.site.read().unwrap();
+    site.blog_entries
+        .iter()
+        .find(|entry| entry.metadata.slug == entry_name)
+        .map(|entry| Json(entry.to_api_context()))
+}
+
+#[get("/api/nav")]
+fn get_nav(updating_site: &State<UpdatingSite>) -> Json<Vec<NavNodeContext>> {
+    Json(updating_site.site.read().unwrap().build_nav_context())
+}
+
+#[get("/debug/entries")]
+fn get_debug_entries(updating_site: &State<UpdatingSite>) -> Option<Json<Vec<DebugEntryContext>>> {
+    if !updating_site.config.debug_endpoints {
+        return None;
+    }
+
+    Some(Json(
+        updating_site
+            .site
+            .read()
+            .unwrap()
+            .build_debug_entries_context(),
+    ))
+}
+
+#[get("/debug/config")]
+fn get_debug_config(updating_site: &State<UpdatingSite>) -> Option<Json<SiteConfig>> {
+    if !updating_site.config.debug_endpoints {
+        return None;
+    }
+
+    Some(Json(
+        updating_site
+            .site
+            .read()
+            .unwrap()
+            .build_debug_config_context(),
+    ))
+}
+
+#[post("/admin/rebuild")]
+async fn post_admin_rebuild(
+    _token: AdminToken,
+    _body_limit: AdminBodyLimit,
+    updating_site: &State<UpdatingSite>,
+) -> Result<Json<BuildReport>, status::Custom<String>> {
+    info!("Rebuild requested, rebuilding site...");
+    let timeout = Duration::from_secs(updating_site.config.admin_timeout_secs);
+    match updating_site.rebuild_within(timeout).await {
+        None => {
+            error!("Site rebuild didn't finish within {:?}.", timeout);
+            Err(status::Custom(
+                Status::RequestTimeout,
+                format!("site rebuild didn't finish within {:?}", timeout),
+            ))
+        }
+        Some(Ok(build_report)) => {
+            info!("Site rebuilt successfully.");
+            Ok(Json(build_report))
+        }
+        Some(Err(e)) => {
+            error!("Error rebuilding site: {:?}", e);
+            Err(status::Custom(
+                Status::InternalServerError,
+                format!("error rebuilding site: {:?}", e),
+            ))
+        }
+    }
+}
+
+#[get("/admin/edit/<entry_name>")]
+fn get_admin_edit(
+    entry_name: String,
+    _access: LocalOrAdminToken,
+    csrf_key: &State<CsrfKey>,
+    updating_site: &State<UpdatingSite>,
+) -> Result<Option<Template>, Status> {
+    let site = &updating_site.site.read().unwrap();
+    let entry = match site
+        .blog_entries
+        .iter()
+        .find(|entry| entry.metadata.slug == entry_name)
+    {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    let (_, front_matter, content) = read_front_matter_and_content(entry.metadata.source_file())
+        .map_err(|e| {
+            error!("Error reading blog entry {}: {:?}", entry_name, e);
+            Status::InternalServerError
+        })?;
+
+    Ok(Some(Template::render(
+        "admin_edit",
+        site.build_admin_edit_context(
+            entry,
+            csrf_key.token_for(&entry_name),
+            front_matter,
+            content,
+            None,
+        ),
+    )))
+}
+
+#[derive(FromForm)]
+struct EntryEditForm {
+    csrf_token: String,
+    front_matter: String,
+    content: String,
+}
+
+#[post("/admin/edit/<entry_name>", data = "<form>")]
+fn post_admin_edit(
+    entry_name: String,
+    _access: LocalOrAdminToken,
+    form: AdminForm<EntryEditForm>,
+    csrf_key: &State<CsrfKey>,
+    updating_site: &State<UpdatingSite>,
+) -> Option<Result<Redirect, (Status, Template)>> {
+    let site = &updating_site.site.read().unwrap();
+    let entry = site
+        .blog_entries
+        .iter()
+        .find(|entry| entry.metadata.slug == entry_name)?;
+
+    // a local admin's browser can be made to submit the form by any site they visit, so it has to prove it was loaded from here
+    if !csrf_key.verify(&entry_name, &form.csrf_token) {
+        return Some(Err((
+            Status::Forbidden,
+            Template::render("error", forbidden_context()),
+        )));
+    }
+
+    // the site will be rebuilt with the changes once the file watcher notices them
+    match write_front_matter_and_content(
+        entry.metadata.source_file(),
+        &form.front_matter,
+        &form.content,
+        &updating_site.config,
+    ) {
+        Ok(()) => Some(Ok(Redirect::to(format!("/admin/edit/{}", entry_name)))),
+        Err(e) => Some(Err((
+            Status::BadRequest,
+            Template::render(
+                "admin_edit",
+                site.build_admin_edit_context(
+                    entry,
+                    form.csrf_token.clone(),
+                    form.front_matter.clone(),
+                    form.content.clone(),
+                    Some(format!("{:#}", e)),
+                ),
+            ),
+        ))),
+    }
+}
+
+#[get("/admin/export.json")]
+fn get_admin_export(
+    _token: AdminToken,
+    updating_site: &State<UpdatingSite>,
+) -> Result<(ContentType, String), status::Custom<String>> {
+    match updating_site.site.read().unwrap().to_json() {
+        Ok(json) => Ok((ContentType::JSON, json)),
+        Err(e) => Err(status::Custom(
+            Status::InternalServerError,
+            format!("error exporting site: {:?}", e),
+        )),
+    }
 }
 
 #[catch(404)]
-fn not_found() -> Template {
-    let context = ErrorContext {
-        base: BaseContext {
-            title: "404".to_string(),
-            meta_description: "Not a page".to_string(),
-        },
-        header: "404".to_string(),
-        message: "That's not a page".to_string(),
+fn not_found(request: &Request) -> Template {
+    let missing_slug = request
+        .uri()
+        .path()
+        .as_str()
+        .strip_prefix(BLOG_ENTRY_URL_PREFIX)
+        .filter(|slug| !slug.is_empty() && !slug.contains('/'));
+    let updating_site = request.rocket().state::<UpdatingSite>();
+    let context = match (missing_slug, updating_site) {
+        (Some(missing_slug), Some(updating_site)) => updating_site
+            .site
+            .read()
+            .unwrap()
+            .build_entry_not_found_context(missing_slug),
+        _ => not_found_context(),
     };
     Template::render("error", &context)
 }
 
+#[catch(500)]
+fn internal_server_error() -> Template {
+    Template::render("error", internal_server_error_context())
+}
+
 #[launch]
 fn rocket() -> rocket::Rocket<rocket::Build> {
-    let mut rocket = rocket::build()
+    // Rocket only installs its own logger when it launches, which is too late to see the messages from building the site,
+    // and it leaves this one alone since a logger can only be set once
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(DEFAULT_LOG_FILTER))
+        .init();
+
+    build_rocket(rocket::Config::figment())
+}
+
+/// Builds the server with the provided configuration, building the site it serves first.
+///
+/// # Panics
+/// Panics if the site can't be built, since there's nothing to serve without a site.
+fn build_rocket(config: Figment) -> rocket::Rocket<rocket::Build> {
+    let asset_manifest = AssetManifest::from_dir(Path::new(STATIC_FILES_DIR))
+        .unwrap_or_else(|e| panic!("error hashing static files: {:?}", e));
+
+    let mut rocket = rocket::custom(config)
         .mount(
             "/",
             routes![
                 index,
                 about,
+                get_page,
                 get_blog_index,
                 get_blog_posts,
                 get_blog_entry,
+                post_blog_entry_password,
+                get_blog_entry_source,
                 get_blog_entry_file,
                 get_blog_tags,
                 get_blog_tag,
+                get_blog_tag_feed,
+                get_blog_book,
+                get_blog_series_index,
+                get_blog_series,
+                get_blog_search,
+                get_blog_archive_year,
+                get_blog_archive_month,
+                get_taxonomy,
+                get_taxonomy_term,
+                get_blog_discussions,
+                get_changelog,
                 get_blog_feed,
+                get_blog_feed_page,
+                get_blog_atom_feed,
+                get_blog_json_feed,
+                get_sitemap,
+                get_robots,
+                get_feed_redirect,
+                get_rss_redirect,
+                get_atom_redirect,
+                get_blog_updated_feed,
+                get_site_info,
+                get_api_post,
+                get_nav,
+                post_admin_rebuild,
+                get_admin_export,
+                get_debug_entries,
+                get_debug_config,
             ],
         )
-        .mount("/", FileServer::from("static").rank(10))
-        .register("/", catchers![not_found])
-        .attach(Template::fairing());
+        .mount("/", FileServer::from(STATIC_FILES_DIR).rank(10))
+        .register("/", catchers![not_found, internal_server_error])
+        .manage(CsrfKey::random())
+        .attach(Template::custom(move |engines| {
+            engines
+                .tera
+                .register_function("asset", asset_manifest.clone().into_tera_function());
+        }));
 
     let config = rocket.figment();
     let additional_static_files_dir =
@@ -159,23 +819,61 @@ fn rocket() -> rocket::Rocket<rocket::Build> {
     let html_base_dir = config
         .extract_inner::<String>(RENDERED_HTML_BASE_DIR_CONFIG_KEY)
         .unwrap_or_else(|_| DEFAULT_RENDERED_HTML_BASE_DIR.to_string());
+    let site_config = config
+        .extract::<SiteConfig>()
+        .unwrap_or_else(|e| panic!("error reading site config: {}", e));
+    if site_config.smart_typography.is_some() {
+        warn!("smart_typography is deprecated, add or remove smart_punctuation in markdown_options instead");
+    }
+    let address = config
+        .extract_inner::<IpAddr>("address")
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    if address.is_loopback() || site_config.admin_token.is_some() {
+        rocket = rocket.mount("/", routes![get_admin_edit, post_admin_edit]);
+    } else {
+        warn!("Not serving the post editor, since the server isn't bound to localhost and no admin token is set.");
+    }
 
-    println!("Building site...");
-    match std::fs::remove_dir_all(&html_base_dir) {
-        Ok(()) => (),
-        Err(e) => match e.kind() {
-            std::io::ErrorKind::NotFound => (),
-            _ => panic!("error deleting {}: {}", html_base_dir, e),
-        },
-    };
-    let updating_site =
-        UpdatingSite::from_dir(PathBuf::from(site_base_dir), PathBuf::from(html_base_dir))
-            .unwrap_or_else(|e| panic!("error building site: {:?}", e));
-    println!("Site built successfully.");
+    if site_config.minify_xml {
+        rocket = rocket.attach(XmlMinifier);
+    }
+
+    info!("Building site...");
+    if !site_config.build_cache {
+        match std::fs::remove_dir_all(&html_base_dir) {
+            Ok(()) => (),
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => (),
+                _ => panic!("error deleting {}: {}", html_base_dir, e),
+            },
+        };
+    }
+    let updating_site = UpdatingSite::from_dir(
+        PathBuf::from(site_base_dir),
+        PathBuf::from(html_base_dir),
+        site_config,
+    )
+    .unwrap_or_else(|e| panic!("error building site: {:?}", e));
+    info!("Site built successfully.");
+    let shared_assets_url_prefix = updating_site.config.shared_assets_url_prefix.clone();
+    let mut blog_entry_routes = routes![get_blog_entry_short];
+    for route in &mut blog_entry_routes {
+        route.rank = updating_site.config.blog_entry_route_rank;
+    }
+    rocket = rocket
+        .mount(shared_assets_url_prefix, routes![get_shared_asset])
+        .mount("/", blog_entry_routes);
+    for warning in blog_entry_route_conflicts(
+        rocket.routes(),
+        &updating_site.site.read().unwrap().blog_entries,
+        updating_site.config.blog_entry_route_rank,
+    ) {
+        warn!("{}", warning);
+    }
     rocket = rocket.manage(updating_site);
 
     if let Ok(dir) = additional_static_files_dir {
-        println!("Serving static files from {}", dir);
+        info!("Serving static files from {}", dir);
         rocket = rocket.mount(
             "/",
             FileServer::new(dir, Options::Index | Options::DotFiles).rank(9),
@@ -184,3 +882,1550 @@ fn rocket() -> rocket::Rocket<rocket::Build> {
 
     rocket
 }
+
+/// Describes the blog entries whose short URLs are the same as the paths of other routes, which hide whichever of the two is ranked later.
+fn blog_entry_route_conflicts<'a>(
+    routes: impl Iterator<Item = &'a Route>,
+    blog_entries: &[BlogEntry],
+    blog_entry_route_rank: isize,
+) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    for route in routes.filter(|route| route.method == Method::Get) {
+        for entry in blog_entries
+            .iter()
+            .filter(|entry| route.uri.path() == format!("/blog/{}", entry.metadata.slug))
+        {
+            if route.rank < blog_entry_route_rank {
+                conflicts.push(format!(
+                    "Blog entry {} is hidden by the built-in route for {}, set blog_entry_route_rank below {} to serve the entry there instead",
+                    entry.metadata.slug,
+                    route.uri.path(),
+                    route.rank
+                ));
+            } else {
+                conflicts.push(format!(
+                    "Blog entry {} hides the built-in route for {}",
+                    entry.metadata.slug,
+                    route.uri.path()
+                ));
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{form_body, TestSite};
+    use rocket::http::{ContentType, Header};
+    use rocket::serde::json::serde_json;
+    use std::collections::HashMap;
+    use std::fs::read_to_string;
+    use std::net::SocketAddr;
+
+    fn protected_site() -> TestSite {
+        let site = TestSite::new();
+        site.add_entry(
+            "secret",
+            "title = \"Secret Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"\npassword = \"open sesame\"",
+            "The secret content",
+        );
+        site.add_file("blog/secret/pic.png", b"secret picture");
+        site
+    }
+
+    #[test]
+    fn password_protected_entry_shows_prompt() {
+        let site = protected_site();
+        let client = site.client();
+
+        let response = client.get("/blog/posts/secret").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let html = response.into_string().unwrap();
+        assert!(html.contains("This post is password protected."));
+        assert!(!html.contains("The secret content"));
+    }
+
+    #[test]
+    fn password_protected_entry_rejects_wrong_password() {
+        let site = protected_site();
+        let client = site.client();
+
+        let response = client
+            .post("/blog/posts/secret")
+            .header(ContentType::Form)
+            .body(form_body(&[("password", "open says me")]))
+            .dispatch();
+
+        assert_eq!(Status::Unauthorized, response.status());
+        let html = response.into_string().unwrap();
+        assert!(html.contains("That's not the right password."));
+        assert!(!html.contains("The secret content"));
+    }
+
+    #[test]
+    fn password_protected_entry_shows_content_for_correct_password() {
+        let site = protected_site();
+        let client = site.client();
+
+        let response = client
+            .post("/blog/posts/secret")
+            .header(ContentType::Form)
+            .body(form_body(&[("password", "open sesame")]))
+            .dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("The secret content"));
+    }
+
+    #[test]
+    fn password_protected_entry_files_are_not_served() {
+        let site = protected_site();
+        let client = site.client();
+
+        let response = client.get("/blog/posts/secret/pic.png").dispatch();
+
+        assert_eq!(Status::NotFound, response.status());
+    }
+
+    #[test]
+    fn associated_files_are_served() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_file("blog/some-post/pic.png", b"some picture");
+        let client = site.client();
+
+        let response = client.get("/blog/posts/some-post/pic.png").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert_eq!(b"some picture".to_vec(), response.into_bytes().unwrap());
+    }
+
+    #[test]
+    fn discussions_page_lists_only_discussed_entries() {
+        let site = TestSite::new();
+        site.add_entry(
+            "discussed-post",
+            "title = \"Discussed Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"\n\
+            [[external_discussions]]\nname = \"Some Forum\"\nurl = \"https://forum.example.com/thread/1\"",
+            "Some content",
+        );
+        site.add_entry(
+            "quiet-post",
+            "title = \"Quiet Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/discussions").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let html = response.into_string().unwrap();
+        assert!(html.contains("Discussed Post"));
+        // tera escapes slashes in URLs
+        assert!(html.contains(
+            "<a href=\"https:&#x2F;&#x2F;forum.example.com&#x2F;thread&#x2F;1\">Discuss this post on Some Forum</a>"
+        ));
+        assert!(!html.contains("Quiet Post"));
+    }
+
+    #[test]
+    fn nav_includes_top_level_pages_and_tags() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ntags = [\"rust\", \"cooking\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let response = client.get("/api/nav").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let nav: serde_json::Value =
+            serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        let titles: Vec<&str> = nav
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(vec!["Home", "About", "Blog"], titles);
+        let tags = &nav[2]["children"][0];
+        assert_eq!("/blog/tags", tags["url"]);
+        assert_eq!("cooking", tags["children"][0]["title"]);
+        assert_eq!("/blog/tags/rust", tags["children"][1]["url"]);
+        assert_eq!("Some Post", nav[2]["children"][1]["children"][0]["title"]);
+    }
+
+    #[test]
+    fn feed_includes_configured_ttl_and_skip_times() {
+        let site = TestSite::new();
+        let client = site.client_with(
+            Figment::new()
+                .merge(("feed_ttl", 60))
+                .merge(("feed_skip_hours", vec![0, 23]))
+                .merge(("feed_skip_days", vec!["Saturday"])),
+        );
+
+        let response = client.get("/blog/feed").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let xml: String = response.into_string().unwrap().split_whitespace().collect();
+        assert!(xml.contains("<ttl>60</ttl>"));
+        assert!(xml.contains("<skipHours><hour>0</hour><hour>23</hour></skipHours>"));
+        assert!(xml.contains("<skipDays><day>Saturday</day></skipDays>"));
+    }
+
+    #[test]
+    fn feed_leaves_out_unconfigured_ttl_and_skip_times() {
+        let site = TestSite::new();
+        let client = site.client();
+
+        let xml = client.get("/blog/feed").dispatch().into_string().unwrap();
+
+        assert!(!xml.contains("<ttl>"));
+        assert!(!xml.contains("<skipHours>"));
+        assert!(!xml.contains("<skipDays>"));
+    }
+
+    #[test]
+    fn index_source_is_rendered_on_homepage() {
+        let site = TestSite::new();
+        site.add_file("index.md", "# Welcome\n\nThis is *my* homepage.");
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(Figment::new().merge(("index_source", "index.md")));
+
+        let response = client.get("/").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let html = response.into_string().unwrap();
+        assert!(html.contains("<p>This is <em>my</em> homepage.</p>"));
+        assert!(html.contains("Some Post"));
+    }
+
+    #[test]
+    fn shared_assets_are_served_and_referenced_from_entries() {
+        let site = TestSite::new();
+        site.add_file("shared_assets/images/logo.png", b"some logo");
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "![the logo](shared:images/logo.png)",
+        );
+        let client = site.client();
+
+        let asset_response = client.get("/shared/images/logo.png").dispatch();
+        let entry_html = client
+            .get("/blog/posts/some-post")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert_eq!(Status::Ok, asset_response.status());
+        assert_eq!(b"some logo".to_vec(), asset_response.into_bytes().unwrap());
+        assert!(entry_html.contains("<img src=\"/shared/images/logo.png\" alt=\"the logo\" />"));
+    }
+
+    #[test]
+    fn only_draft_previews_show_draft_banner() {
+        let site = TestSite::new();
+        site.add_entry(
+            "draft-post",
+            "draft = true\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "published-post",
+            "created_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(Figment::new().merge(("include_drafts", true)));
+
+        let draft_html = client
+            .get("/blog/posts/draft-post")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let published_html = client
+            .get("/blog/posts/published-post")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(draft_html.contains("<div class=\"draft-banner\">DRAFT</div>"));
+        assert!(!published_html.contains("draft-banner"));
+    }
+
+    #[test]
+    fn drafts_are_not_served_without_include_drafts() {
+        let site = TestSite::new();
+        site.add_entry(
+            "draft-post",
+            "draft = true\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/posts/draft-post").dispatch();
+
+        assert_eq!(Status::NotFound, response.status());
+    }
+
+    #[test]
+    fn debug_endpoints_are_not_found_when_disabled() {
+        let site = TestSite::new();
+        let client = site.client();
+
+        assert_eq!(
+            Status::NotFound,
+            client.get("/debug/entries").dispatch().status()
+        );
+        assert_eq!(
+            Status::NotFound,
+            client.get("/debug/config").dispatch().status()
+        );
+    }
+
+    #[test]
+    fn debug_endpoints_describe_site_when_enabled() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(
+            Figment::new()
+                .merge(("debug_endpoints", true))
+                .merge(("admin_token", "hunter2")),
+        );
+
+        let entries: serde_json::Value = serde_json::from_str(
+            &client
+                .get("/debug/entries")
+                .dispatch()
+                .into_string()
+                .unwrap(),
+        )
+        .unwrap();
+        let config: serde_json::Value = serde_json::from_str(
+            &client
+                .get("/debug/config")
+                .dispatch()
+                .into_string()
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!("some-post", entries[0]["slug"]);
+        assert!(entries[0]["source_file"]
+            .as_str()
+            .unwrap()
+            .ends_with("content.md"));
+        assert_eq!(true, config["debug_endpoints"]);
+        assert_eq!("<redacted>", config["admin_token"]);
+    }
+
+    #[test]
+    fn post_footer_is_shown_after_every_entry() {
+        let site = TestSite::new();
+        site.add_file("footer.md", "Licensed under *CC BY 4.0*.");
+        site.add_entry(
+            "first-post",
+            "created_at = \"2021-01-01T00:00:00Z\"",
+            "First",
+        );
+        site.add_entry(
+            "second-post",
+            "created_at = \"2021-01-02T00:00:00Z\"",
+            "Second",
+        );
+        let client = site.client_with(Figment::new().merge(("post_footer", "footer.md")));
+
+        for slug in ["first-post", "second-post"] {
+            let html = client
+                .get(format!("/blog/posts/{}", slug))
+                .dispatch()
+                .into_string()
+                .unwrap();
+
+            assert!(html.contains("<p>Licensed under <em>CC BY 4.0</em>.</p>"));
+        }
+    }
+
+    #[test]
+    fn gone_entries_return_410() {
+        let site = TestSite::new();
+        site.add_file(
+            "gone.toml",
+            "[[gone]]\nslug = \"old-post\"\nmessage = \"I changed my mind about this one\"",
+        );
+        let client = site.client();
+
+        let gone_response = client.get("/blog/posts/old-post").dispatch();
+        let unknown_response = client.get("/blog/posts/never-existed").dispatch();
+
+        assert_eq!(Status::Gone, gone_response.status());
+        assert!(gone_response
+            .into_string()
+            .unwrap()
+            .contains("I changed my mind about this one"));
+        assert_eq!(Status::NotFound, unknown_response.status());
+    }
+
+    #[test]
+    fn tags_are_rendered_with_their_configured_templates() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "tags = [\"projects\", \"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(
+            Figment::new().merge(("tag_templates", HashMap::from([("projects", "blog_index")]))),
+        );
+
+        let mapped_html = client
+            .get("/blog/tags/projects")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let unmapped_html = client
+            .get("/blog/tags/rust")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(mapped_html.contains("<h1>The Rotoclone Zone Blog</h1>"));
+        assert!(!mapped_html.contains("Posts tagged"));
+        assert!(unmapped_html.contains("Posts tagged"));
+    }
+
+    #[test]
+    fn export_round_trips_entries() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ntags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_file("blog/some-post/pic.png", b"some picture");
+        site.add_entry(
+            "other-post",
+            "title = \"Other Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Other content",
+        );
+        let client = site.client_with(Figment::new().merge(("admin_token", "hunter2")));
+
+        let unauthorized_response = client.get("/admin/export.json").dispatch();
+        let response = client
+            .get("/admin/export.json")
+            .header(Header::new("Authorization", "Bearer hunter2"))
+            .dispatch();
+
+        assert_eq!(Status::Unauthorized, unauthorized_response.status());
+        assert_eq!(Status::Ok, response.status());
+        let export: serde_json::Value =
+            serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        let entries = export["entries"].as_array().unwrap();
+        assert_eq!(2, entries.len());
+        let entry = entries
+            .iter()
+            .find(|entry| entry["slug"] == "some-post")
+            .unwrap();
+        assert_eq!("Some Post", entry["title"]);
+        assert_eq!("blog/some-post/content.md", entry["source_file"]);
+        assert_eq!("blog/some-post/pic.png", entry["associated_files"][0]);
+        assert_eq!("rust", entry["tags"][0]);
+        assert_eq!("2021-01-01T00:00:00+00:00", entry["created_at"]);
+    }
+
+    const ADMIN_TOKEN: &str = "hunter2";
+
+    fn local_address() -> SocketAddr {
+        "127.0.0.1:50000".parse().unwrap()
+    }
+
+    fn remote_address() -> SocketAddr {
+        "203.0.113.7:50000".parse().unwrap()
+    }
+
+    fn admin_site() -> TestSite {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site
+    }
+
+    fn admin_client(site: &TestSite) -> rocket::local::blocking::Client {
+        site.client_with(Figment::new().merge(("admin_token", ADMIN_TOKEN)))
+    }
+
+    /// Gets the CSRF token out of the provided edit form HTML.
+    fn csrf_token(html: &str) -> String {
+        let start = html
+            .find("name=\"csrf_token\" value=\"")
+            .expect("form should have a CSRF token")
+            + "name=\"csrf_token\" value=\"".len();
+        let end = start + html[start..].find('"').unwrap();
+        html[start..end].to_string()
+    }
+
+    #[test]
+    fn admin_edit_rejects_spoofed_local_address() {
+        let site = admin_site();
+        let client = admin_client(&site);
+
+        let response = client
+            .get("/admin/edit/some-post")
+            .remote(remote_address())
+            .header(Header::new("X-Real-IP", "127.0.0.1"))
+            .dispatch();
+
+        assert_eq!(Status::Unauthorized, response.status());
+    }
+
+    #[test]
+    fn admin_edit_allows_loopback() {
+        let site = admin_site();
+        let client = admin_client(&site);
+
+        let response = client
+            .get("/admin/edit/some-post")
+            .remote(local_address())
+            .dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert!(response.into_string().unwrap().contains("Some content"));
+    }
+
+    #[test]
+    fn admin_edit_allows_admin_token() {
+        let site = admin_site();
+        let client = admin_client(&site);
+
+        let response = client
+            .get("/admin/edit/some-post")
+            .remote(remote_address())
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {}", ADMIN_TOKEN),
+            ))
+            .dispatch();
+        assert_eq!(Status::Ok, response.status());
+
+        let response = client
+            .get("/admin/edit/some-post")
+            .remote(remote_address())
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .dispatch();
+        assert_eq!(Status::Unauthorized, response.status());
+    }
+
+    #[test]
+    fn admin_edit_writes_changes_to_disk() {
+        let site = admin_site();
+        let client = admin_client(&site);
+        let html = client
+            .get("/admin/edit/some-post")
+            .remote(local_address())
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        let response = client
+            .post("/admin/edit/some-post")
+            .remote(local_address())
+            .header(ContentType::Form)
+            .body(form_body(&[
+                ("csrf_token", &csrf_token(&html)),
+                (
+                    "front_matter",
+                    "title = \"Edited Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+                ),
+                ("content", "Edited content"),
+            ]))
+            .dispatch();
+
+        assert_eq!(Status::SeeOther, response.status());
+        let source = read_to_string(site.source_dir().join("blog/some-post/content.md")).unwrap();
+        assert!(source.contains("title = \"Edited Post\""));
+        assert!(source.contains("Edited content"));
+        assert!(!source.contains("Some content"));
+    }
+
+    #[test]
+    fn admin_edit_rejects_missing_csrf_token() {
+        let site = admin_site();
+        let client = admin_client(&site);
+
+        let response = client
+            .post("/admin/edit/some-post")
+            .remote(local_address())
+            .header(ContentType::Form)
+            .body(form_body(&[
+                ("csrf_token", "forged"),
+                ("front_matter", "title = \"Forged\""),
+                ("content", "Forged content"),
+            ]))
+            .dispatch();
+
+        assert_eq!(Status::Forbidden, response.status());
+        let source = read_to_string(site.source_dir().join("blog/some-post/content.md")).unwrap();
+        assert!(source.contains("Some content"));
+    }
+
+    #[test]
+    fn common_feed_urls_redirect_to_default_feed() {
+        let site = TestSite::new();
+        let client = site.client();
+
+        for url in ["/feed", "/rss", "/atom"] {
+            let response = client.get(url).dispatch();
+
+            assert_eq!(Status::Found, response.status());
+            assert_eq!(Some("/blog/feed"), response.headers().get_one("Location"));
+        }
+    }
+
+    #[test]
+    fn feed_front_matter_controls_which_feeds_entries_appear_in() {
+        let site = TestSite::new();
+        site.add_entry(
+            "everywhere-post",
+            "title = \"Everywhere Post\"\ntags = [\"rust\", \"cooking\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "excluded-post",
+            "title = \"Excluded Post\"\ntags = [\"rust\"]\nfeed_exclude = true\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "cooking-only-post",
+            "title = \"Cooking Only Post\"\ntags = [\"rust\", \"cooking\"]\nfeed_only_tags = [\"Cooking\"]\ncreated_at = \"2021-01-03T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let main_feed = client.get("/blog/feed").dispatch().into_string().unwrap();
+        let rust_response = client.get("/blog/tags/rust/feed").dispatch();
+        assert_eq!(Status::Ok, rust_response.status());
+        let rust_feed = rust_response.into_string().unwrap();
+        let cooking_feed = client
+            .get("/blog/tags/cooking/feed")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(main_feed.contains("Everywhere Post"));
+        assert!(!main_feed.contains("Excluded Post"));
+        assert!(rust_feed.contains("Everywhere Post"));
+        assert!(rust_feed.contains("Excluded Post"));
+        assert!(!rust_feed.contains("Cooking Only Post"));
+        assert!(cooking_feed.contains("Cooking Only Post"));
+        assert_eq!(
+            Status::NotFound,
+            client
+                .get("/blog/tags/nonexistent/feed")
+                .dispatch()
+                .status()
+        );
+    }
+
+    #[test]
+    fn book_contains_published_entries_oldest_first() {
+        let site = TestSite::new();
+        site.add_entry(
+            "first-post",
+            "title = \"First Post\"\ntags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "First content",
+        );
+        site.add_entry(
+            "second-post",
+            "title = \"Second Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Second content",
+        );
+        site.add_entry(
+            "secret-post",
+            "title = \"Secret Post\"\ncreated_at = \"2021-01-03T00:00:00Z\"\npassword = \"open sesame\"",
+            "Secret content",
+        );
+        let client = site.client();
+
+        let book = client
+            .get("/blog/book.html")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let tag_book = client
+            .get("/blog/book.html?tag=rust")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        let first = book.find("First content").unwrap();
+        let second = book.find("Second content").unwrap();
+        assert!(first < second);
+        assert!(!book.contains("Secret content"));
+        assert!(tag_book.contains("First content"));
+        assert!(!tag_book.contains("Second content"));
+        assert_eq!(
+            Status::NotFound,
+            client
+                .get("/blog/book.html?tag=nonexistent")
+                .dispatch()
+                .status()
+        );
+    }
+
+    #[test]
+    fn empty_blog_shows_configured_message() {
+        let site = TestSite::new();
+        let client =
+            site.client_with(Figment::new().merge(("empty_blog_message", "Nothing here yet")));
+
+        for url in ["/", "/blog"] {
+            let response = client.get(url).dispatch();
+
+            assert_eq!(Status::Ok, response.status());
+            assert!(response
+                .into_string()
+                .unwrap()
+                .contains("<p class=\"empty-blog\">Nothing here yet</p>"));
+        }
+    }
+
+    #[test]
+    fn entries_include_open_graph_article_metadata() {
+        let site = TestSite::new();
+        site.add_entry(
+            "tagged-post",
+            "tags = [\"rust\", \"cooking\"]\ncreated_at = \"2021-01-01T00:00:00Z\"\nupdated_at = \"2021-02-01T12:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "untagged-post",
+            "created_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(Figment::new().merge(("default_article_section", "Misc")));
+
+        let tagged_html = client
+            .get("/blog/posts/tagged-post")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let untagged_html = client
+            .get("/blog/posts/untagged-post")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(tagged_html.contains("<meta property=\"og:type\" content=\"article\">"));
+        assert!(tagged_html.contains(
+            "<meta property=\"article:published_time\" content=\"2021-01-01T00:00:00Z\">"
+        ));
+        assert!(tagged_html.contains(
+            "<meta property=\"article:modified_time\" content=\"2021-02-01T12:00:00Z\">"
+        ));
+        assert!(tagged_html.contains("<meta property=\"article:section\" content=\"rust\">"));
+        assert!(tagged_html.contains("<meta property=\"article:tag\" content=\"cooking\">"));
+        assert!(untagged_html.contains("<meta property=\"article:section\" content=\"Misc\">"));
+        assert!(!untagged_html.contains("article:modified_time"));
+    }
+
+    #[test]
+    fn feeds_are_minified_when_configured() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(Figment::new().merge(("minify_xml", true)));
+
+        let xml = client.get("/blog/feed").dispatch().into_string().unwrap();
+
+        assert!(xml.contains("<title>Some Post</title>"));
+        assert!(!xml.trim_end().contains(">\n"));
+    }
+
+    #[test]
+    fn missing_entries_suggest_similar_slugs() {
+        let site = TestSite::new();
+        site.add_entry(
+            "learning-rust",
+            "title = \"Learning Rust\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "baking-bread",
+            "title = \"Baking Bread\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/posts/learning-rsut").dispatch();
+
+        assert_eq!(Status::NotFound, response.status());
+        let html = response.into_string().unwrap();
+        assert!(html.contains("Did you mean:"));
+        assert!(html.contains("Learning Rust"));
+        assert!(!html.contains("Baking Bread"));
+        let unrelated_html = client
+            .get("/blog/posts/something-else-entirely")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(!unrelated_html.contains("Did you mean:"));
+    }
+
+    #[test]
+    fn range_requests_return_partial_content() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_file("blog/some-post/clip.mp3", b"0123456789");
+        let client = site.client();
+
+        let response = client
+            .get("/blog/posts/some-post/clip.mp3")
+            .header(Header::new("Range", "bytes=2-5"))
+            .dispatch();
+        assert_eq!(Status::PartialContent, response.status());
+        assert_eq!(
+            Some("bytes 2-5/10"),
+            response.headers().get_one("Content-Range")
+        );
+        assert_eq!(b"2345".to_vec(), response.into_bytes().unwrap());
+
+        let response = client
+            .get("/blog/posts/some-post/clip.mp3")
+            .header(Header::new("Range", "bytes=-3"))
+            .dispatch();
+        assert_eq!(Status::PartialContent, response.status());
+        assert_eq!(b"789".to_vec(), response.into_bytes().unwrap());
+
+        let response = client
+            .get("/blog/posts/some-post/clip.mp3")
+            .header(Header::new("Range", "bytes=20-"))
+            .dispatch();
+        assert_eq!(Status::RangeNotSatisfiable, response.status());
+        assert_eq!(
+            Some("bytes */10"),
+            response.headers().get_one("Content-Range")
+        );
+
+        let response = client.get("/blog/posts/some-post/clip.mp3").dispatch();
+        assert_eq!(Status::Ok, response.status());
+        assert_eq!(Some("bytes"), response.headers().get_one("Accept-Ranges"));
+        assert_eq!(b"0123456789".to_vec(), response.into_bytes().unwrap());
+    }
+
+    #[test]
+    fn paged_feed_links_to_self_previous_and_next_pages() {
+        let site = TestSite::new();
+        for day in 1..=5 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!(
+                    "title = \"Post {}\"\ncreated_at = \"2021-01-0{}T00:00:00Z\"",
+                    day, day
+                ),
+                "Some content",
+            );
+        }
+        let client = site.client_with(Figment::new().merge(("feed_page_size", 2)));
+
+        let xml = client
+            .get("/blog/feed.xml?page=2")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let base_url = "https://www.rotoclone.zone/blog";
+
+        assert!(xml.contains(&format!(
+            "<atom:link href=\"{}/feed.xml?page=2\" rel=\"self\"",
+            base_url
+        )));
+        assert!(xml.contains(&format!(
+            "<atom:link href=\"{}/feed\" rel=\"previous\"",
+            base_url
+        )));
+        assert!(xml.contains(&format!(
+            "<atom:link href=\"{}/feed.xml?page=3\" rel=\"next\"",
+            base_url
+        )));
+        assert!(xml.contains("<title>Post 3</title>"));
+        assert!(xml.contains("<title>Post 2</title>"));
+        assert!(!xml.contains("<title>Post 4</title>"));
+        assert_eq!(
+            Status::NotFound,
+            client.get("/blog/feed.xml?page=4").dispatch().status()
+        );
+    }
+
+    fn site_with_tags_entry() -> TestSite {
+        let site = TestSite::new();
+        site.add_entry(
+            "tags",
+            "title = \"All About Tags\"\ntags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site
+    }
+
+    fn entry_conflicts(client: &rocket::local::blocking::Client) -> Vec<String> {
+        let updating_site = client.rocket().state::<UpdatingSite>().unwrap();
+        blog_entry_route_conflicts(
+            client.rocket().routes(),
+            &updating_site.site.read().unwrap().blog_entries,
+            updating_site.config.blog_entry_route_rank,
+        )
+    }
+
+    #[test]
+    fn fixed_routes_win_over_blog_entry_short_urls_by_default() {
+        let site = site_with_tags_entry();
+        let client = site.client();
+
+        let response = client.get("/blog/tags").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert!(response.into_string().unwrap().contains("rust"));
+        let conflicts = entry_conflicts(&client);
+        assert_eq!(1, conflicts.len());
+        assert!(
+            conflicts[0].contains("Blog entry tags is hidden by the built-in route for /blog/tags")
+        );
+    }
+
+    #[test]
+    fn blog_entry_short_urls_override_fixed_routes_with_lower_rank() {
+        let site = site_with_tags_entry();
+        let client = site.client_with(Figment::new().merge(("blog_entry_route_rank", -20)));
+
+        let response = client.get("/blog/tags").dispatch();
+
+        assert_eq!(Status::SeeOther, response.status());
+        assert_eq!(
+            Some("/blog/posts/tags"),
+            response.headers().get_one("Location")
+        );
+        assert_eq!(
+            Status::Ok,
+            client.get("/blog/discussions").dispatch().status()
+        );
+        assert_eq!(
+            Status::NotFound,
+            client.get("/blog/nope").dispatch().status()
+        );
+        let conflicts = entry_conflicts(&client);
+        assert_eq!(1, conflicts.len());
+        assert!(conflicts[0].contains("Blog entry tags hides the built-in route for /blog/tags"));
+    }
+
+    #[test]
+    fn blog_feed_is_served_as_xml() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/feed").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert_eq!(Some(ContentType::XML), response.content_type());
+        let body = response.into_string().unwrap();
+        assert!(body.starts_with("<?xml"));
+        assert!(body.contains("<title>Some Post</title>"));
+    }
+
+    #[test]
+    fn atom_feed_lists_entries() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some *content*",
+        );
+        site.add_entry(
+            "secret-post",
+            "title = \"Secret Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"\npassword = \"open sesame\"",
+            "The secret content",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/feed.atom").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert_eq!(
+            Some(ContentType::new("application", "atom+xml")),
+            response.content_type()
+        );
+        let xml = response.into_string().unwrap();
+        assert!(xml.contains("<updated>2021-01-02T00:00:00Z</updated>"));
+        assert!(xml.contains("<id>https://www.rotoclone.zone/blog/posts/some-post</id>"));
+        assert!(xml.contains("<published>2021-01-01T00:00:00Z</published>"));
+        assert!(xml.contains("&lt;em&gt;content&lt;"));
+        assert!(xml.contains("<title>Secret Post</title>"));
+        assert!(!xml.contains("The secret content"));
+    }
+
+    #[test]
+    fn json_feed_lists_entries() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ntags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "secret-post",
+            "title = \"Secret Post\"\ndescription = \"A secret\"\ncreated_at = \"2021-01-02T00:00:00Z\"\npassword = \"open sesame\"",
+            "The secret content",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/feed.json").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert_eq!(
+            Some(ContentType::new("application", "feed+json")),
+            response.content_type()
+        );
+        let feed: serde_json::Value =
+            serde_json::from_str(&response.into_string().unwrap()).unwrap();
+        assert_eq!("https://jsonfeed.org/version/1.1", feed["version"]);
+        let items = feed["items"].as_array().unwrap();
+        assert_eq!(2, items.len());
+        assert_eq!("Secret Post", items[0]["title"]);
+        assert_eq!("A secret", items[0]["content_text"]);
+        assert!(items[0].get("content_html").is_none());
+        assert_eq!(
+            "https://www.rotoclone.zone/blog/posts/some-post",
+            items[1]["url"]
+        );
+        assert_eq!("2021-01-01T00:00:00Z", items[1]["date_published"]);
+        assert_eq!("rust", items[1]["tags"][0]);
+        assert!(items[1]["content_html"]
+            .as_str()
+            .unwrap()
+            .contains("Some content"));
+    }
+
+    #[test]
+    fn sitemap_lists_pages_tags_and_published_entries() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "tags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"\nupdated_at = \"2021-02-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "draft-post",
+            "draft = true\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client_with(
+            Figment::new()
+                .merge(("include_drafts", true))
+                .merge(("site_base_url", "https://example.com")),
+        );
+
+        let response = client.get("/sitemap.xml").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        // tera escapes slashes in URLs
+        let xml: String = response
+            .into_string()
+            .unwrap()
+            .replace("&#x2F;", "/")
+            .split_whitespace()
+            .collect();
+        assert!(
+            xml.contains("<loc>https://example.com/</loc><lastmod>2021-02-01T00:00:00Z</lastmod>")
+        );
+        assert!(xml.contains("<loc>https://example.com/about</loc>"));
+        assert!(xml.contains("<loc>https://example.com/blog/tags/rust</loc>"));
+        assert!(xml.contains("<loc>https://example.com/blog/posts/some-post</loc>"));
+        assert!(!xml.contains("draft-post"));
+    }
+
+    #[test]
+    fn robots_txt_references_sitemap_unless_disallowing_all() {
+        let site = TestSite::new();
+        let client =
+            site.client_with(Figment::new().merge(("site_base_url", "https://example.com")));
+        let disallowing_client = site.client_with(Figment::new().merge(("disallow_all", true)));
+
+        let robots = client.get("/robots.txt").dispatch().into_string().unwrap();
+        let disallowing_robots = disallowing_client
+            .get("/robots.txt")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(robots.contains("Allow: /"));
+        assert!(robots.contains("Sitemap: https://example.com/sitemap.xml"));
+        assert!(disallowing_robots.contains("Disallow: /"));
+        assert!(!disallowing_robots.contains("Sitemap:"));
+    }
+
+    #[test]
+    fn toc_sidebar_is_shown_only_for_entries_with_headings() {
+        let site = TestSite::new();
+        site.add_entry(
+            "with-headings",
+            "created_at = \"2021-01-01T00:00:00Z\"",
+            "# First\n\nText\n\n## Nested\n\nMore text",
+        );
+        site.add_entry(
+            "without-headings",
+            "created_at = \"2021-01-02T00:00:00Z\"",
+            "Just some text",
+        );
+        let client = site.client();
+
+        let with_headings = client
+            .get("/blog/posts/with-headings")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let without_headings = client
+            .get("/blog/posts/without-headings")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(with_headings.contains("<aside class=\"blog-toc-sidebar\">"));
+        assert!(with_headings.contains("<a href=\"#first\">First</a>"));
+        assert!(with_headings.contains("<a href=\"#nested\">Nested</a>"));
+        assert!(!without_headings.contains("blog-toc-sidebar"));
+    }
+
+    #[test]
+    fn request_errors_render_an_error_page() {
+        let site = admin_site();
+        let client = admin_client(&site);
+        site.add_file("blog/some-post/content.md", [0xff, 0xfe]);
+
+        let response = client
+            .get("/admin/edit/some-post")
+            .remote(local_address())
+            .dispatch();
+
+        assert_eq!(Status::InternalServerError, response.status());
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("<p>Something went wrong</p>"));
+    }
+
+    #[test]
+    fn archive_pages_list_entries_from_their_period() {
+        let site = TestSite::new();
+        site.add_entry(
+            "january-post",
+            "title = \"January Post\"\ncreated_at = \"2021-01-15T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "march-post",
+            "title = \"March Post\"\ncreated_at = \"2021-03-15T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "next-year-post",
+            "title = \"Next Year Post\"\ncreated_at = \"2022-01-15T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let year = client
+            .get("/blog/archive/2021")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let month = client
+            .get("/blog/archive/2021/3")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(year.contains("Posts from 2021"));
+        assert!(year.contains("January Post"));
+        assert!(year.contains("March Post"));
+        assert!(!year.contains("Next Year Post"));
+        assert!(month.contains("Posts from March 2021"));
+        assert!(month.contains("March Post"));
+        assert!(!month.contains("January Post"));
+        assert_eq!(
+            Status::NotFound,
+            client.get("/blog/archive/2021/2").dispatch().status()
+        );
+        assert_eq!(
+            Status::NotFound,
+            client.get("/blog/archive/2021/13").dispatch().status()
+        );
+    }
+
+    #[test]
+    fn entries_link_to_neighbors_within_the_tag_they_came_from() {
+        let site = TestSite::new();
+        site.add_entry(
+            "first",
+            "title = \"First\"\ntags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "second",
+            "title = \"Second\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "third",
+            "title = \"Third\"\ntags = [\"rust\"]\ncreated_at = \"2021-01-03T00:00:00Z\"",
+            "Some content",
+        );
+        let client = site.client();
+
+        let untagged = client
+            .get("/blog/posts/third")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let tagged = client
+            .get("/blog/posts/third?tag=rust")
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        assert!(untagged.contains("Previous: Second"));
+        assert!(tagged.contains("Previous in \"rust\": First"));
+        assert!(tagged.contains("?tag=rust"));
+    }
+
+    #[test]
+    fn listing_pages_past_the_last_page_are_not_found() {
+        let site = TestSite::new();
+        for day in 1..=3 {
+            site.add_entry(
+                &format!("post-{}", day),
+                &format!(
+                    "tags = [\"stuff\"]\ncreated_at = \"2021-01-{:02}T00:00:00Z\"",
+                    day
+                ),
+                "Some content",
+            );
+        }
+        let client = site.client_with(Figment::new().merge(("blog_page_size", 2)));
+
+        assert_eq!(Status::Ok, client.get("/blog?page=2").dispatch().status());
+        assert_eq!(
+            Status::NotFound,
+            client.get("/blog?page=3").dispatch().status()
+        );
+        assert_eq!(
+            Status::Ok,
+            client.get("/blog/tags/stuff?page=2").dispatch().status()
+        );
+        assert_eq!(
+            Status::NotFound,
+            client.get("/blog/tags/stuff?page=3").dispatch().status()
+        );
+    }
+
+    #[test]
+    fn first_listing_page_exists_without_entries() {
+        let client = TestSite::new().client();
+
+        assert_eq!(Status::Ok, client.get("/blog").dispatch().status());
+    }
+
+    #[test]
+    fn pages_are_served_at_their_slugs() {
+        let site = TestSite::new();
+        site.add_file(
+            "pages/contact.md",
+            "+++\ntitle = \"Contact Me\"\n+++\nSend a *letter*",
+        );
+        site.add_file(
+            "pages/other.md",
+            "+++\nslug = \"elsewhere\"\ntitle = \"Elsewhere\"\n+++\nSomething else",
+        );
+        let client = site.client();
+
+        let contact = client.get("/contact").dispatch().into_string().unwrap();
+
+        assert!(contact.contains("<h1>Contact Me</h1>"));
+        assert!(contact.contains("Send a <em>letter</em>"));
+        assert_eq!(Status::Ok, client.get("/elsewhere").dispatch().status());
+        assert_eq!(Status::NotFound, client.get("/other").dispatch().status());
+    }
+
+    #[test]
+    fn pages_include_link_preview_metadata() {
+        let site = TestSite::new();
+        site.add_entry(
+            "pictured-post",
+            "title = \"Pictured Post\"\nimage = \"cover.png\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_file("blog/pictured-post/cover.png", "not really an image");
+        let client = site.client();
+
+        let about = client.get("/about").dispatch().into_string().unwrap();
+        let entry = client
+            .get("/blog/posts/pictured-post")
+            .dispatch()
+            .into_string()
+            .unwrap()
+            // tera escapes slashes in URLs
+            .replace("&#x2F;", "/");
+
+        assert!(about.contains("<meta property=\"og:type\" content=\"website\">"));
+        assert!(about.contains("<meta name=\"twitter:card\" content=\"summary\">"));
+        assert!(entry.contains("<meta property=\"og:title\" content=\"Pictured Post\">"));
+        assert!(entry.contains(
+            "<meta property=\"og:image\" content=\"https://www.rotoclone.zone/blog/posts/pictured-post/cover.png\">"
+        ));
+        assert!(entry.contains("<meta name=\"twitter:card\" content=\"summary_large_image\">"));
+    }
+
+    #[test]
+    fn removed_associated_files_are_not_found() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let file = site.add_file("blog/some-post/pic.png", b"some picture");
+        let client = site.client();
+        std::fs::remove_file(file).unwrap();
+
+        let response = client.get("/blog/posts/some-post/pic.png").dispatch();
+
+        assert_eq!(Status::NotFound, response.status());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_of_other_entries_are_not_served_through_symlinks() {
+        let site = protected_site();
+        let dir = site.add_entry(
+            "loop",
+            "title = \"Loop\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Loop content",
+        );
+        std::os::unix::fs::symlink("..", dir.join("up")).unwrap();
+        let client = site.client();
+
+        let response = client
+            .get("/blog/posts/loop/up/secret/content.md")
+            .dispatch();
+
+        assert_eq!(Status::NotFound, response.status());
+    }
+
+    #[test]
+    fn entry_markdown_source_is_served_unless_password_protected() {
+        let site = protected_site();
+        site.add_entry(
+            "open-post",
+            "title = \"Open Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some *content*",
+        );
+        let client = site.client();
+
+        let response = client.get("/blog/posts/open-post/source.md").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        assert_eq!(
+            Some(ContentType::new("text", "markdown")),
+            response.content_type()
+        );
+        assert!(response.into_string().unwrap().ends_with("Some *content*"));
+        assert_eq!(
+            Status::NotFound,
+            client
+                .get("/blog/posts/secret/source.md")
+                .dispatch()
+                .status()
+        );
+    }
+
+    fn post_edit(client: &rocket::local::blocking::Client, content: &str) -> Status {
+        let html = client
+            .get("/admin/edit/some-post")
+            .remote(local_address())
+            .dispatch()
+            .into_string()
+            .unwrap();
+
+        client
+            .post("/admin/edit/some-post")
+            .remote(local_address())
+            .header(ContentType::Form)
+            .body(form_body(&[
+                ("csrf_token", &csrf_token(&html)),
+                (
+                    "front_matter",
+                    "title = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+                ),
+                ("content", content),
+            ]))
+            .dispatch()
+            .status()
+    }
+
+    #[test]
+    fn admin_edit_rejects_content_that_does_not_render() {
+        let site = admin_site();
+        let client = admin_client(&site);
+
+        assert_eq!(Status::BadRequest, post_edit(&client, "{{< nope >}}"));
+        let source = read_to_string(site.source_dir().join("blog/some-post/content.md")).unwrap();
+        assert!(source.contains("Some content"));
+    }
+
+    #[test]
+    fn site_info_counts_published_posts() {
+        let site = admin_site();
+        site.add_entry(
+            "draft-post",
+            "title = \"Draft Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"\ndraft = true",
+            "Draft content",
+        );
+        let client = site.client_with(
+            Figment::new()
+                .merge(("include_drafts", true))
+                .merge(("admin_token", ADMIN_TOKEN)),
+        );
+        assert_eq!(
+            Status::Ok,
+            client.get("/blog/posts/draft-post").dispatch().status()
+        );
+
+        let response = client.get("/api/site").dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let info = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(env!("CARGO_PKG_VERSION"), info["version"]);
+        assert_eq!(1, info["total_posts"]);
+        assert!(!info.to_string().contains(ADMIN_TOKEN));
+    }
+
+    #[test]
+    fn authorized_rebuild_picks_up_new_entries() {
+        let site = admin_site();
+        let client = admin_client(&site);
+        site.add_entry(
+            "new-post",
+            "title = \"New Post\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "New content",
+        );
+
+        let response = client
+            .post("/admin/rebuild")
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {}", ADMIN_TOKEN),
+            ))
+            .dispatch();
+
+        assert_eq!(Status::Ok, response.status());
+        let report = response.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(2, report["blog_entries"]);
+        assert_eq!(
+            Status::Ok,
+            client.get("/blog/posts/new-post").dispatch().status()
+        );
+    }
+
+    #[test]
+    fn unauthorized_rebuild_is_rejected() {
+        let site = admin_site();
+        let client = admin_client(&site);
+
+        let without_token = client.post("/admin/rebuild").dispatch();
+        let with_wrong_token = client
+            .post("/admin/rebuild")
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .dispatch();
+
+        assert_eq!(Status::Unauthorized, without_token.status());
+        assert_eq!(Status::Unauthorized, with_wrong_token.status());
+    }
+
+    #[test]
+    fn admin_edit_accepts_entries_bigger_than_default_form_limit() {
+        let site = admin_site();
+        let client = admin_client(&site);
+        let content = "word ".repeat(8 * 1024);
+
+        assert_eq!(Status::SeeOther, post_edit(&client, &content));
+        let source = read_to_string(site.source_dir().join("blog/some-post/content.md")).unwrap();
+        assert!(source.contains(&content));
+    }
+
+    #[test]
+    fn admin_edit_rejects_bodies_over_limit() {
+        let site = admin_site();
+        let client = site.client_with(
+            Figment::new()
+                .merge(("admin_token", ADMIN_TOKEN))
+                .merge(("admin_body_limit", 16 * 1024)),
+        );
+
+        // the local client doesn't send a Content-Length header, so the limit has to be enforced on the body itself
+        // smaller than Rocket's default form limit, so only the admin body limit rejects it
+        assert_eq!(
+            Status::PayloadTooLarge,
+            post_edit(&client, &"word ".repeat(4 * 1024))
+        );
+        let source = read_to_string(site.source_dir().join("blog/some-post/content.md")).unwrap();
+        assert!(source.contains("Some content"));
+    }
+
+    #[test]
+    fn admin_body_limit_does_not_change_other_form_limits() {
+        let site = admin_site();
+        let client = site.client_with(
+            Figment::new()
+                .merge(("admin_token", ADMIN_TOKEN))
+                .merge(("admin_body_limit", 16 * 1024 * 1024)),
+        );
+
+        assert_eq!(
+            rocket::data::Limits::default().get("form"),
+            client.rocket().config().limits.get("form")
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::bail;
+
+use crate::shortcodes::map_lines_outside_code_blocks;
+
+/// The string that starts and ends a block of display math.
+const DISPLAY_MATH_DELIMITER: &str = "$$";
+
+/// The character that starts and ends a span of inline math.
+const INLINE_MATH_DELIMITER: u8 = b'$';
+
+/// Wraps the math in the provided markdown in elements KaTeX's auto-render extension can find.
+/// Inline `$...$` math becomes a `<span class="math">` and `$$...$$` display math becomes a `<div class="math">`,
+/// with the TeX inside using KaTeX's default `\(...\)` and `\[...\]` delimiters.
+/// Math inside code blocks and code spans is left alone, and so are dollar signs that don't look like they surround math, like in `$5 and $10`.
+///
+/// # Errors
+/// Returns an error if a block of display math is never closed.
+pub fn expand_math(markdown: &str) -> anyhow::Result<String> {
+    let mut display_math: Option<String> = None;
+    let expanded = map_lines_outside_code_blocks(markdown, |line| {
+        let trimmed_line = line.trim();
+        if let Some(tex) = &mut display_math {
+            return Ok(match trimmed_line.strip_suffix(DISPLAY_MATH_DELIMITER) {
+                Some(last_line) => {
+                    tex.push_str(last_line);
+                    let html = display_math_html(tex);
+                    display_math = None;
+                    html
+                }
+                None => {
+                    tex.push_str(line);
+                    tex.push('\n');
+                    String::new()
+                }
+            });
+        }
+
+        if let Some(rest) = trimmed_line.strip_prefix(DISPLAY_MATH_DELIMITER) {
+            return Ok(match rest.strip_suffix(DISPLAY_MATH_DELIMITER) {
+                Some(tex) => display_math_html(tex),
+                None => {
+                    display_math = Some(format!("{}\n", rest));
+                    String::new()
+                }
+            });
+        }
+
+        Ok(expand_inline_math(line))
+    })?;
+
+    if display_math.is_some() {
+        bail!("display math is missing closing {}", DISPLAY_MATH_DELIMITER);
+    }
+
+    Ok(expanded)
+}
+
+/// Builds the HTML for a block of display math.
+/// It's followed by a blank line so the markdown after it isn't treated as part of the same HTML block.
+fn display_math_html(tex: &str) -> String {
+    format!(
+        "<div class=\"math\">{}</div>\n",
+        encode_tex(&format!("\\[{}\\]", tex.trim()))
+    )
+}
+
+/// Wraps the inline math in the provided line in `<span class="math">` elements.
+/// An opening `$` has to be followed by a non-space character, and a closing `$` has to follow a non-space character and not be followed by a digit.
+fn expand_inline_math(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut expanded = String::with_capacity(line.len());
+    let mut copied_until = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // skip over escaped characters, like `\$`
+            b'\\' => i += 2,
+            b'`' => {
+                let run_length = count_run(bytes, i, b'`');
+                i = find_closing_backticks(bytes, i + run_length, run_length)
+                    .unwrap_or(i + run_length);
+            }
+            INLINE_MATH_DELIMITER if opens_inline_math(bytes, i) => {
+                match find_unescaped_dollar(bytes, i + 1) {
+                    Some(end) if closes_inline_math(bytes, end) => {
+                        expanded.push_str(&line[copied_until..i]);
+                        expanded.push_str(&format!(
+                            "<span class=\"math\">{}</span>",
+                            encode_tex(&format!("\\({}\\)", &line[i + 1..end]))
+                        ));
+                        i = end + 1;
+                        copied_until = i;
+                    }
+                    // TeX can't contain an unescaped `$`, so if the next one can't close the math, neither of them are math delimiters
+                    Some(end) => i = end + 1,
+                    None => i = bytes.len(),
+                }
+            }
+            INLINE_MATH_DELIMITER => i += count_run(bytes, i, INLINE_MATH_DELIMITER),
+            _ => i += 1,
+        }
+    }
+    expanded.push_str(&line[copied_until.min(line.len())..]);
+
+    expanded
+}
+
+/// Counts how many times `byte` appears in a row in `bytes`, starting at `start`.
+fn count_run(bytes: &[u8], start: usize, byte: u8) -> usize {
+    bytes[start..].iter().take_while(|b| **b == byte).count()
+}
+
+/// Finds the index just past the run of exactly `run_length` backticks that closes a code span, starting the search at `start`.
+fn find_closing_backticks(bytes: &[u8], start: usize, run_length: usize) -> Option<usize> {
+    let mut i = start;
+    while i < bytes.len() {
+        if bytes[i] == b'`' {
+            let length = count_run(bytes, i, b'`');
+            if length == run_length {
+                return Some(i + length);
+            }
+            i += length;
+        } else {
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Finds the index of the first `$` in `bytes` at or after `start` that isn't escaped with a backslash.
+fn find_unescaped_dollar(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            INLINE_MATH_DELIMITER => return Some(i),
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+/// Determines whether the `$` at index `i` of `bytes` can open inline math.
+fn opens_inline_math(bytes: &[u8], i: usize) -> bool {
+    bytes
+        .get(i + 1)
+        .is_some_and(|next| !next.is_ascii_whitespace() && *next != INLINE_MATH_DELIMITER)
+}
+
+/// Determines whether the `$` at index `i` of `bytes` can close inline math.
+fn closes_inline_math(bytes: &[u8], i: usize) -> bool {
+    !bytes[i - 1].is_ascii_whitespace() && !bytes.get(i + 1).is_some_and(u8::is_ascii_digit)
+}
+
+/// Encodes all the ASCII punctuation in the provided TeX as HTML character references,
+/// so markdown doesn't interpret any of it while the browser still sees the original TeX.
+fn encode_tex(tex: &str) -> String {
+    let mut encoded = String::with_capacity(tex.len() * 2);
+    for c in tex.chars() {
+        if c.is_ascii_punctuation() {
+            encoded.push_str(&format!("&#{};", c as u32));
+        } else {
+            encoded.push(c);
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_math_is_wrapped_in_spans() {
+        let expanded = expand_math("Euler: $e^x$ costs $5 and $10").unwrap();
+
+        assert_eq!(
+            format!(
+                "Euler: <span class=\"math\">{}</span> costs $5 and $10",
+                encode_tex("\\(e^x\\)")
+            ),
+            expanded.trim_end()
+        );
+    }
+
+    #[test]
+    fn display_math_is_wrapped_in_divs() {
+        let expanded = expand_math("$$\na + b\n$$").unwrap();
+
+        assert!(expanded.contains(&format!(
+            "<div class=\"math\">{}</div>",
+            encode_tex("\\[a + b\\]")
+        )));
+    }
+
+    #[test]
+    fn math_in_code_is_left_alone() {
+        let markdown = "Some `$x$` code\n\n```\n$$\n```";
+
+        assert_eq!(markdown, expand_math(markdown).unwrap().trim_end());
+    }
+
+    #[test]
+    fn unclosed_display_math_is_an_error() {
+        assert!(expand_math("$$\na + b").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use rocket::http::{ContentType, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket::tokio::{fs::File, io::AsyncReadExt};
+use std::{
+    io::{Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// A file response that supports single-range `Range` requests, so media can be seeked without downloading all of it.
+/// The file is streamed from disk rather than read into memory.
+pub struct RangedFile {
+    /// The location of the file.
+    path: PathBuf,
+    /// The open file.
+    file: std::fs::File,
+    /// The size of the file in bytes.
+    len: u64,
+}
+
+/// The part of a file requested by a `Range` header.
+#[derive(Debug)]
+enum ByteRange {
+    /// The bytes from `start` to `end`, inclusive.
+    Satisfiable { start: u64, end: u64 },
+    /// A range that doesn't overlap the file at all.
+    Unsatisfiable,
+}
+
+impl RangedFile {
+    /// Opens the file at the provided location.
+    ///
+    /// # Errors
+    /// Returns any errors that occur while opening the file or reading its metadata.
+    pub async fn open(path: &Path) -> std::io::Result<RangedFile> {
+        let file = File::open(path).await?;
+        let len = file.metadata().await?.len();
+
+        Ok(RangedFile {
+            path: path.to_path_buf(),
+            file: file.into_std().await,
+            len,
+        })
+    }
+}
+
+impl<'r> Responder<'r, 'static> for RangedFile {
+    fn respond_to(mut self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+        response.raw_header("Accept-Ranges", "bytes");
+        if let Some(content_type) = self
+            .path
+            .extension()
+            .and_then(|extension| ContentType::from_extension(&extension.to_string_lossy()))
+        {
+            response.header(content_type);
+        }
+
+        // ranges that can't be parsed are ignored, and the whole file is sent instead
+        let range = request
+            .headers()
+            .get_one("Range")
+            .and_then(|header| parse_range(header, self.len));
+
+        match range {
+            None => {
+                response.sized_body(self.len as usize, File::from_std(self.file));
+            }
+            Some(ByteRange::Unsatisfiable) => {
+                response
+                    .status(Status::RangeNotSatisfiable)
+                    .raw_header("Content-Range", format!("bytes */{}", self.len));
+            }
+            Some(ByteRange::Satisfiable { start, end }) => {
+                self.file
+                    .seek(SeekFrom::Start(start))
+                    .map_err(|_| Status::InternalServerError)?;
+                let range_len = end - start + 1;
+                response
+                    .status(Status::PartialContent)
+                    .raw_header(
+                        "Content-Range",
+                        format!("bytes {}-{}/{}", start, end, self.len),
+                    )
+                    .raw_header("Content-Length", range_len.to_string())
+                    .streamed_body(File::from_std(self.file).take(range_len));
+            }
+        }
+
+        response.ok()
+    }
+}
+
+/// Parses the value of a `Range` header for a file of the provided length.
+/// Returns `None` if the header isn't a single byte range, since those aren't supported.
+fn parse_range(header: &str, len: u64) -> Option<ByteRange> {
+    let range = header.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let (start, end) = if start.is_empty() {
+        // a suffix range, like `-500` for the last 500 bytes
+        let suffix_len = end.parse::<u64>().ok()?;
+        if suffix_len == 0 {
+            return Some(ByteRange::Unsatisfiable);
+        }
+        (len.saturating_sub(suffix_len), len.checked_sub(1)?)
+    } else {
+        let start = start.parse::<u64>().ok()?;
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            end.parse::<u64>().ok()?.min(len.saturating_sub(1))
+        };
+        (start, end)
+    };
+
+    if start >= len || start > end {
+        return Some(ByteRange::Unsatisfiable);
+    }
+
+    Some(ByteRange::Satisfiable { start, end })
+}
@@ -0,0 +1,427 @@
+use anyhow::{anyhow, bail, Context};
+use pulldown_cmark::escape::{escape_href, escape_html};
+use pulldown_cmark::{Event, Parser, Tag};
+use std::{collections::HashMap, fs::read_to_string, ops::Range, path::Path};
+
+use crate::site::AssociatedFile;
+
+/// The string that marks the beginning of a shortcode.
+const SHORTCODE_START: &str = "{{<";
+
+/// The string that marks the end of a shortcode.
+const SHORTCODE_END: &str = ">}}";
+
+/// The string that marks the beginning of an embed.
+const EMBED_START: &str = "{{embed ";
+
+/// The string that marks the end of an embed.
+const EMBED_END: &str = "}}";
+
+/// The prefixes of lines that start or end a fenced code block, inside of which shortcodes are not expanded.
+const CODE_FENCES: [&str; 2] = ["```", "~~~"];
+
+/// A function that expands a shortcode with the provided arguments into HTML.
+type ShortcodeFn = fn(&ShortcodeArgs) -> anyhow::Result<String>;
+
+/// The shortcodes available for use in content, and the functions that expand them.
+const BUILT_IN_SHORTCODES: [(&str, ShortcodeFn); 3] = [
+    ("youtube", expand_youtube),
+    ("figure", expand_figure),
+    ("gist", expand_gist),
+];
+
+/// The arguments provided to a shortcode.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ShortcodeArgs {
+    /// Arguments provided without a name, in the order they were provided.
+    positional: Vec<String>,
+    /// Arguments provided as `name=value` pairs.
+    named: HashMap<String, String>,
+}
+
+impl ShortcodeArgs {
+    /// Gets the positional argument at the provided index, or returns an error naming the argument if there isn't one.
+    fn positional(&self, index: usize, description: &str) -> anyhow::Result<&str> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("missing {} argument", description))
+    }
+
+    /// Gets the named argument with the provided name, if it was provided.
+    fn named(&self, name: &str) -> Option<&str> {
+        self.named.get(name).map(String::as_str)
+    }
+}
+
+/// Replaces all the shortcodes in the provided markdown with the HTML they expand to.
+/// Shortcodes inside code blocks and code spans are left alone.
+///
+/// # Errors
+/// Returns an error if an unknown shortcode is used, or a shortcode is malformed or used with invalid arguments.
+pub fn expand_shortcodes(markdown: &str) -> anyhow::Result<String> {
+    map_lines_outside_code(markdown, |line| {
+        expand_shortcodes_in_line(line).context("error expanding shortcodes")
+    })
+}
+
+/// Replaces all the `{{embed "path"}}` markers in the provided markdown with the contents of the associated file at that path.
+/// Markers inside code blocks and code spans are left alone.
+///
+/// # Errors
+/// Returns an error if an embedded file isn't one of the provided associated files, or can't be read.
+pub fn expand_embeds(
+    markdown: &str,
+    associated_files: &[AssociatedFile],
+) -> anyhow::Result<String> {
+    map_lines_outside_code(markdown, |line| {
+        expand_embeds_in_line(line, associated_files).context("error expanding embeds")
+    })
+}
+
+/// Applies `transform` to each line of the provided markdown, skipping over code blocks and code spans.
+/// Lines that contain code spans have `transform` applied to the parts before and after each span separately.
+///
+/// # Errors
+/// Returns the first error returned from `transform`, with the line number it occurred on.
+fn map_lines_outside_code(
+    markdown: &str,
+    mut transform: impl FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let mut transformed = String::with_capacity(markdown.len());
+    let mut position = 0;
+    for code_range in code_ranges(markdown) {
+        push_transformed_lines(
+            &markdown[position..code_range.start],
+            line_number(markdown, position),
+            &mut transform,
+            &mut transformed,
+        )?;
+        transformed.push_str(&markdown[code_range.clone()]);
+        position = code_range.end;
+    }
+    push_transformed_lines(
+        &markdown[position..],
+        line_number(markdown, position),
+        &mut transform,
+        &mut transformed,
+    )?;
+
+    Ok(transformed)
+}
+
+/// Finds the byte ranges of the code blocks and code spans in the provided markdown, in the order they appear.
+fn code_ranges(markdown: &str) -> Vec<Range<usize>> {
+    Parser::new(markdown)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Gets the number of the line the provided byte offset into `markdown` is on, starting from 1.
+fn line_number(markdown: &str, offset: usize) -> usize {
+    markdown[..offset].matches('\n').count() + 1
+}
+
+/// Applies `transform` to each line of `text` and appends the results to `transformed`.
+/// `first_line_number` is the number of the line `text` starts on, for error messages.
+fn push_transformed_lines(
+    text: &str,
+    first_line_number: usize,
+    transform: &mut impl FnMut(&str) -> anyhow::Result<String>,
+    transformed: &mut String,
+) -> anyhow::Result<()> {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            transformed.push('\n');
+        }
+        transformed.push_str(
+            &transform(line).with_context(|| format!("on line {}", first_line_number + i))?,
+        );
+    }
+
+    Ok(())
+}
+
+/// Applies `transform` to each line of the provided markdown that isn't inside a fenced code block.
+///
+/// # Errors
+/// Returns the first error returned from `transform`, with the line number it occurred on.
+pub fn map_lines_outside_code_blocks(
+    markdown: &str,
+    mut transform: impl FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let mut transformed = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    for (i, line) in markdown.lines().enumerate() {
+        if i > 0 {
+            transformed.push('\n');
+        }
+
+        let trimmed_line = line.trim_start();
+        if CODE_FENCES
+            .iter()
+            .any(|fence| trimmed_line.starts_with(fence))
+        {
+            in_code_block = !in_code_block;
+        }
+
+        if in_code_block {
+            transformed.push_str(line);
+        } else {
+            transformed.push_str(&transform(line).with_context(|| format!("on line {}", i + 1))?);
+        }
+    }
+
+    Ok(transformed)
+}
+
+/// Replaces all the embed markers in the provided line with the contents of the files they refer to.
+fn expand_embeds_in_line(
+    line: &str,
+    associated_files: &[AssociatedFile],
+) -> anyhow::Result<String> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut remaining = line;
+    while let Some(start_index) = remaining.find(EMBED_START) {
+        expanded.push_str(&remaining[..start_index]);
+        let after_start = &remaining[start_index + EMBED_START.len()..];
+        let end_index = after_start
+            .find(EMBED_END)
+            .ok_or_else(|| anyhow!("embed is missing closing {}", EMBED_END))?;
+
+        let path = after_start[..end_index].trim().trim_matches('"');
+        let file = associated_files
+            .iter()
+            .find(|file| file.relative_path == Path::new(path))
+            .ok_or_else(|| anyhow!("embedded file {} is not in the entry's directory", path))?;
+        expanded.push_str(
+            &read_to_string(&file.full_path)
+                .with_context(|| format!("error reading embedded file {}", path))?,
+        );
+        remaining = &after_start[end_index + EMBED_END.len()..];
+    }
+    expanded.push_str(remaining);
+
+    Ok(expanded)
+}
+
+/// Replaces all the shortcodes in the provided line with the HTML they expand to.
+fn expand_shortcodes_in_line(line: &str) -> anyhow::Result<String> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut remaining = line;
+    while let Some(start_index) = remaining.find(SHORTCODE_START) {
+        expanded.push_str(&remaining[..start_index]);
+        let after_start = &remaining[start_index + SHORTCODE_START.len()..];
+        let end_index = after_start
+            .find(SHORTCODE_END)
+            .ok_or_else(|| anyhow!("shortcode is missing closing {}", SHORTCODE_END))?;
+
+        expanded.push_str(&expand_shortcode(&after_start[..end_index])?);
+        remaining = &after_start[end_index + SHORTCODE_END.len()..];
+    }
+    expanded.push_str(remaining);
+
+    Ok(expanded)
+}
+
+/// Expands the provided shortcode (the part between the delimiters) into HTML.
+fn expand_shortcode(shortcode: &str) -> anyhow::Result<String> {
+    let mut tokens = tokenize(shortcode)?.into_iter();
+    let name = tokens.next().ok_or_else(|| anyhow!("empty shortcode"))?;
+
+    let mut args = ShortcodeArgs::default();
+    for token in tokens {
+        match token.split_once('=') {
+            Some((key, value)) => {
+                args.named
+                    .insert(key.to_string(), value.trim_matches('"').to_string());
+            }
+            None => args.positional.push(token),
+        }
+    }
+
+    let expand = BUILT_IN_SHORTCODES
+        .iter()
+        .find(|(shortcode_name, _)| *shortcode_name == name)
+        .map(|(_, expand)| expand)
+        .ok_or_else(|| anyhow!("unknown shortcode: {}", name))?;
+
+    expand(&args).with_context(|| format!("error expanding {} shortcode", name))
+}
+
+/// Splits the provided shortcode into whitespace-separated tokens, keeping quoted strings together.
+fn tokenize(shortcode: &str) -> anyhow::Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current_token = String::new();
+    let mut in_quotes = false;
+    for c in shortcode.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current_token.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current_token.is_empty() {
+                    tokens.push(std::mem::take(&mut current_token));
+                }
+            }
+            _ => current_token.push(c),
+        }
+    }
+
+    if in_quotes {
+        bail!("unterminated quoted string in shortcode");
+    }
+
+    if !current_token.is_empty() {
+        tokens.push(current_token);
+    }
+
+    Ok(tokens
+        .into_iter()
+        .map(|token| {
+            if token.contains('=') {
+                token
+            } else {
+                token.trim_matches('"').to_string()
+            }
+        })
+        .collect())
+}
+
+/// Expands `{{< youtube ID >}}` into an embedded YouTube video.
+fn expand_youtube(args: &ShortcodeArgs) -> anyhow::Result<String> {
+    let id = args.positional(0, "video ID")?;
+    if !id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("invalid YouTube video ID: {}", id);
+    }
+
+    Ok(format!(
+        "<div class=\"video-embed\"><iframe src=\"https://www.youtube-nocookie.com/embed/{}\" title=\"YouTube video\" frameborder=\"0\" allow=\"accelerometer; autoplay; clipboard-write; encrypted-media; gyroscope; picture-in-picture\" allowfullscreen></iframe></div>",
+        id
+    ))
+}
+
+/// Expands `{{< figure src="..." alt="..." caption="..." >}}` into an image with an optional caption.
+fn expand_figure(args: &ShortcodeArgs) -> anyhow::Result<String> {
+    let src = args
+        .named("src")
+        .ok_or_else(|| anyhow!("missing src argument"))?;
+
+    let mut html = "<figure><img src=\"".to_string();
+    escape_href(&mut html, src)?;
+    html.push_str("\" alt=\"");
+    escape_html(&mut html, args.named("alt").unwrap_or_default())?;
+    html.push_str("\" />");
+    if let Some(caption) = args.named("caption") {
+        html.push_str("<figcaption>");
+        escape_html(&mut html, caption)?;
+        html.push_str("</figcaption>");
+    }
+    html.push_str("</figure>");
+
+    Ok(html)
+}
+
+/// Expands `{{< gist USER ID >}}` or `{{< gist USER ID FILE >}}` into an embedded GitHub gist.
+fn expand_gist(args: &ShortcodeArgs) -> anyhow::Result<String> {
+    let user = args.positional(0, "user")?;
+    let id = args.positional(1, "gist ID")?;
+
+    let mut src = format!("https://gist.github.com/{}/{}.js", user, id);
+    if let Some(file) = args.positional.get(2) {
+        src.push_str("?file=");
+        src.push_str(file);
+    }
+
+    let mut html = "<script src=\"".to_string();
+    escape_href(&mut html, &src)?;
+    html.push_str("\"></script>");
+
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestSite;
+
+    #[test]
+    fn youtube_expands_to_embedded_video() {
+        let html = expand_shortcodes("{{< youtube dQw4w9WgXcQ >}}").unwrap();
+
+        assert!(html.starts_with("<div class=\"video-embed\"><iframe src=\"https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ\""));
+    }
+
+    #[test]
+    fn youtube_rejects_invalid_ids() {
+        assert!(expand_shortcodes("{{< youtube \"a\\\"b\" >}}").is_err());
+    }
+
+    #[test]
+    fn figure_expands_to_escaped_image_with_caption() {
+        let html = expand_shortcodes(
+            "{{< figure src=\"pic.png\" alt=\"A <pic>\" caption=\"Fish & chips\" >}}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "<figure><img src=\"pic.png\" alt=\"A &lt;pic&gt;\" /><figcaption>Fish &amp; chips</figcaption></figure>",
+            html
+        );
+    }
+
+    #[test]
+    fn gist_expands_to_script() {
+        let html = expand_shortcodes("{{< gist someone abc123 notes.md >}}").unwrap();
+
+        assert_eq!(
+            "<script src=\"https://gist.github.com/someone/abc123.js?file=notes.md\"></script>",
+            html
+        );
+    }
+
+    #[test]
+    fn unknown_shortcodes_are_errors() {
+        let error = expand_shortcodes("Some text\n\n{{< nope >}}").unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.contains("unknown shortcode: nope"));
+        assert!(message.contains("on line 3"));
+    }
+
+    #[test]
+    fn unknown_shortcodes_fail_the_build_with_the_file_path() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "some-post",
+            "created_at = \"2021-01-01T00:00:00Z\"",
+            "{{< nope >}}",
+        );
+
+        let error = test_site
+            .build_with(&crate::config::SiteConfig::default())
+            .unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.contains("unknown shortcode: nope"));
+        assert!(message.contains(&format!("some-post{}content.md", std::path::MAIN_SEPARATOR)));
+    }
+
+    #[test]
+    fn shortcodes_in_code_are_left_alone() {
+        let markdown = "Use `{{< youtube ID >}}` like {{< youtube abc >}}\n\n```\n{{< nope >}}\n```\n\n    {{< nope >}}\n";
+
+        let html = expand_shortcodes(markdown).unwrap();
+
+        assert!(html.starts_with("Use `{{< youtube ID >}}` like <div class=\"video-embed\">"));
+        assert!(html.ends_with("\n\n```\n{{< nope >}}\n```\n\n    {{< nope >}}\n"));
+    }
+}
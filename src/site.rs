@@ -1,18 +1,45 @@
 use anyhow::{bail, Context};
-use chrono::{DateTime, Utc};
-use pulldown_cmark::{html, Options, Parser};
-use serde::Deserialize;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use log::warn;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag};
+use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fmt::Write as _;
 use std::{
-    ffi::OsString,
-    fs::{create_dir_all, DirEntry, File, OpenOptions},
-    io::{BufRead, BufReader, ErrorKind, Write},
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    fs::{create_dir_all, read_to_string, DirEntry, OpenOptions},
+    io::{ErrorKind, Write},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
+    time::Instant,
 };
 
+use crate::build_cache::{hash_entry_source, BuildCache, CachedRender};
+use crate::config::SiteConfig;
+use crate::highlight::{find_theme, highlight_code};
+use crate::math::expand_math;
+use crate::shortcodes::{expand_embeds, expand_shortcodes};
+use crate::toc::{add_heading_ids_and_toc, TocEntry};
+
 /// The name of the directory blog entry files are stored under.
 const BLOG_ENTRIES_DIR_NAME: &str = "blog";
 
+/// The name of the directory files that can be used by any blog entry are stored under.
+const SHARED_ASSETS_DIR_NAME: &str = "shared_assets";
+
+/// The name of the file in the site source directory that lists the slugs of blog entries that have been permanently removed.
+const GONE_FILE_NAME: &str = "gone.toml";
+
+/// The name of the directory in the site content directory that standalone pages are in.
+const PAGES_DIR_NAME: &str = "pages";
+
+/// The extension of the markdown files standalone pages are written in.
+const PAGE_FILE_EXTENSION: &str = "md";
+
+/// The template standalone pages are rendered with, if they don't specify one.
+const DEFAULT_PAGE_TEMPLATE_NAME: &str = "page";
+
 /// The name of the file a blog entry's content is in.
 const BLOG_CONTENT_FILE_NAME: &str = "content.md";
 
@@ -22,15 +49,91 @@ const DEFAULT_BLOG_ENTRY_TEMPLATE_NAME: &str = "blog_entry";
 /// Whether comments should be enabled on blog entries by default.
 const DEFAULT_COMMENTS_ENABLED: bool = true;
 
-/// The string used to delimit the beginning and end of the front matter
-const FRONT_MATTER_DELIMITER: &str = "+++";
+/// The names that can't be used for custom taxonomies, because they're already used by other pages and feeds.
+/// Tags are set with their own front matter field, so `tags` is reserved too.
+const RESERVED_TAXONOMY_NAMES: [&str; 11] = [
+    "tags",
+    "posts",
+    "series",
+    "feed",
+    "discussions",
+    "search",
+    "archive",
+    "feed.xml",
+    "feed.atom",
+    "feed.json",
+    "updated.xml",
+];
+
+/// The URL path blog entries are served under, which has to match the blog entry routes.
+pub const BLOG_ENTRY_URL_PREFIX: &str = "/blog/posts/";
+
+/// The lowest heading level HTML has, `<h6>`.
+const MAX_HEADING_LEVEL: u32 = 6;
+
+/// The string used to delimit the beginning and end of TOML front matter
+const TOML_FRONT_MATTER_DELIMITER: &str = "+++";
+
+/// The string used to delimit the beginning and end of YAML front matter
+const YAML_FRONT_MATTER_DELIMITER: &str = "---";
+
+/// The marker that can be put in a blog entry's markdown to end its excerpt.
+const EXCERPT_MARKER: &str = "<!-- more -->";
+
+/// The number of words excerpts are cut off at if their entry doesn't have an excerpt marker.
+const EXCERPT_WORD_LIMIT: usize = 40;
+
+/// The scheme used in links to refer to other blog entries by their slug, e.g. `post:some-slug`.
+const POST_LINK_SCHEME: &str = "post:";
+
+/// The scheme used in links and images to refer to shared assets by their path, e.g. `shared:images/logo.png`.
+const SHARED_ASSET_SCHEME: &str = "shared:";
 
 #[derive(Debug)]
 pub struct Site {
     pub blog_entries: Vec<BlogEntry>,
+    pub shared_assets: Vec<AssociatedFile>,
+    pub build_report: BuildReport,
+    pub config: SiteConfig,
+    pub index_content_html: Option<String>,
+    pub post_footer_html: Option<String>,
+    pub gone_entries: Vec<GoneEntry>,
+    pub source_dir: PathBuf,
+    /// When the next blog entry scheduled for the future should be published.
+    /// The site has to be rebuilt then for the entry to appear.
+    pub next_scheduled_publish: Option<DateTime<Utc>>,
+    /// The indices in `blog_entries` of the entries with each canonical tag, newest first.
+    pub tag_index: HashMap<String, Vec<usize>>,
+    /// How each canonical tag is written by the newest blog entry that uses it, for display.
+    pub tag_names: HashMap<String, String>,
+    /// The slug used for each canonical tag in URLs.
+    pub tag_slugs: HashMap<String, String>,
+    /// The canonical tag each tag slug is for.
+    pub tags_by_slug: HashMap<String, String>,
+    /// The standalone pages that aren't part of the blog, like a contact page, in order of their slugs.
+    pub pages: Vec<Page>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildReport {
+    pub built_at: DateTime<Utc>,
+    pub duration_ms: u128,
+    pub blog_entries: usize,
+    pub rendered_blog_entries: usize,
+    pub failed_blog_entries: Vec<FailedBlogEntry>,
+}
+
+/// A blog entry that was skipped because it couldn't be parsed.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedBlogEntry {
+    /// The path to the entry's source directory.
+    pub path: String,
+    /// A description of the error that occurred while parsing the entry.
+    pub error: String,
 }
 
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct FrontMatter {
     slug: Option<String>,
     title: Option<String>,
@@ -41,6 +144,39 @@ pub struct FrontMatter {
     updated_at: Option<DateTime<Utc>>,
     comments_enabled: Option<bool>,
     external_discussions: Option<Vec<ExternalDiscussion>>,
+    password: Option<String>,
+    lang: Option<String>,
+    translations: Option<Vec<Translation>>,
+    draft: Option<bool>,
+    read_more: Option<String>,
+    author: Option<String>,
+    image: Option<String>,
+    feed_exclude: Option<bool>,
+    feed_only_tags: Option<Vec<String>>,
+    series: Option<String>,
+    taxonomies: Option<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PageFrontMatter {
+    slug: Option<String>,
+    title: String,
+    description: Option<String>,
+    template: Option<String>,
+}
+
+/// A standalone page that isn't part of the blog, served at `/<slug>`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Page {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub template_name: String,
+    /// The path to the markdown file the page was built from.
+    pub source_file: PathBuf,
+    /// The page's content, rendered as HTML.
+    pub html_content: String,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -48,11 +184,14 @@ pub struct PageMetadata {
     source_file: PathBuf,
     pub associated_files: Vec<AssociatedFile>,
     pub html_content_file: PathBuf,
+    pub linked_slugs: Vec<String>,
+    pub outbound_links: Vec<String>,
+    pub toc: Vec<TocEntry>,
     pub slug: String,
     pub template_name: String,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AssociatedFile {
     pub relative_path: PathBuf,
     pub full_path: PathBuf,
@@ -64,28 +203,121 @@ pub struct BlogEntry {
     pub description: String,
     pub metadata: PageMetadata,
     pub tags: Vec<String>,
+    /// How each of this entry's tags is written in its front matter, keyed by the tag's canonical form.
+    pub tag_names: HashMap<String, String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub comments_enabled: bool,
     pub external_discussions: Vec<ExternalDiscussion>,
+    pub password: Option<String>,
+    pub lang: Option<String>,
+    pub translations: Vec<Translation>,
+    pub draft: bool,
+    pub read_more: Option<String>,
+    /// The name of the person who wrote this entry, if it's not the site's default author.
+    pub author: Option<String>,
+    /// The image to show in link previews of this entry, as a URL, a path on the site, or a path relative to the entry.
+    pub image: Option<String>,
+    /// Whether this entry should be left out of the site-wide feeds.
+    pub feed_exclude: bool,
+    /// The tags whose feeds this entry should appear in. If not set, it appears in the feeds of all its tags.
+    pub feed_only_tags: Option<Vec<String>>,
+    /// The name of the series of blog entries this entry is part of, if any.
+    pub series: Option<String>,
+    /// The canonical terms of each custom taxonomy this entry is classified under, keyed by the taxonomy's canonical name.
+    pub taxonomies: HashMap<String, Vec<String>>,
+    /// The number of words in the entry's markdown content.
+    pub word_count: usize,
+    /// A plain text preview of the entry's content.
+    pub excerpt: String,
+    /// The entry's content as lowercase plain text, for searching.
+    pub search_text: String,
+    /// The entry's content, rendered as HTML.
+    pub html_content: String,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ExternalDiscussion {
     pub name: String,
     pub url: String,
 }
 
+/// A blog entry that has been permanently removed from the site.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+pub struct GoneEntry {
+    pub slug: String,
+    /// A message explaining why the entry was removed.
+    pub message: Option<String>,
+}
+
+/// The contents of the file listing permanently removed blog entries.
+#[derive(Deserialize)]
+struct GoneFile {
+    #[serde(default)]
+    gone: Vec<GoneEntry>,
+}
+
+/// A version of a blog entry in another language.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Translation {
+    pub lang: String,
+    pub slug: String,
+}
+
 impl Site {
     /// Builds the site model from the provided source directory, and puts rendered HTML in the provided HTML directory.
     ///
     /// # Errors
     /// Returns any errors that occur while reading from the file system or parsing file contents.
-    pub fn from_dir(source_dir: &Path, html_dir: &Path) -> anyhow::Result<Site> {
+    pub fn from_dir(
+        source_dir: &Path,
+        html_dir: &Path,
+        config: &SiteConfig,
+    ) -> anyhow::Result<Site> {
+        let build_start = Instant::now();
+        let build_time = Utc::now();
+        find_theme(&config.syntax_theme)?;
+        if config.display_timezone().is_none() {
+            bail!(
+                "display_utc_offset_minutes must be less than a day, but is {}",
+                config.display_utc_offset_minutes
+            );
+        }
+        let template_names = find_template_names(&config.template_dir.relative())?;
+        for (tag, template_name) in &config.tag_templates {
+            if !template_names.contains(template_name) {
+                bail!(
+                    "Tag {} is configured to use nonexistent template: {}",
+                    tag,
+                    template_name
+                );
+            }
+        }
         let blog_entries_source_dir = source_dir.join(BLOG_ENTRIES_DIR_NAME);
         let blog_entries_html_dir = html_dir.join(BLOG_ENTRIES_DIR_NAME);
 
+        let shared_assets_dir = source_dir.join(SHARED_ASSETS_DIR_NAME);
+        let shared_assets = if shared_assets_dir.is_dir() {
+            find_associated_files(&shared_assets_dir, &shared_assets_dir, None, config)?
+        } else {
+            Vec::new()
+        };
+
+        let mut build_cache = if config.build_cache {
+            BuildCache::load(html_dir)
+        } else {
+            BuildCache::default()
+        };
+        let mut rendered_blog_entries = 0;
+        let mut failed_blog_entries = Vec::new();
+        let mut html_content_files = Vec::new();
+        let mut next_scheduled_publish: Option<DateTime<Utc>> = None;
+        let mut scheduled_slugs = Vec::new();
+        // the directory of the entry with each slug, so conflicting entries can both be named
+        // drafts and scheduled entries are included, since they'd conflict once they're published
+        let mut slug_dirs: HashMap<String, PathBuf> = HashMap::new();
         let mut blog_entries: Vec<BlogEntry> = Vec::new();
+        let mut entry_dirs = Vec::new();
         for file in blog_entries_source_dir.read_dir().with_context(|| {
             format!(
                 "error reading from {}",
@@ -100,24 +332,607 @@ impl Site {
             })?;
 
             if is_dir(&file)? {
-                let entry = parse_entry_dir(&file, &blog_entries_html_dir)?;
-                if blog_entries
+                entry_dirs.push(file.path());
+            }
+        }
+        // the directories are handled in a consistent order so errors and ties in the entry order don't depend on the filesystem
+        entry_dirs.sort();
+
+        let parsed_entries = entry_dirs
+            .par_iter()
+            .map(|dir| {
+                parse_entry_dir(
+                    dir,
+                    &blog_entries_html_dir,
+                    config,
+                    &shared_assets,
+                    &build_cache,
+                )
+            })
+            .collect::<Vec<anyhow::Result<(BlogEntry, CachedRender, bool)>>>();
+
+        for (file, parsed) in entry_dirs.iter().zip(parsed_entries) {
+            let (entry, cached_render, rendered) = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) if config.continue_on_error => {
+                    warn!(
+                        "Error parsing blog entry in {}, skipping it: {:?}",
+                        file.to_string_lossy(),
+                        e
+                    );
+                    failed_blog_entries.push(FailedBlogEntry {
+                        path: file.to_string_lossy().to_string(),
+                        error: format!("{:#}", e),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            build_cache.insert(build_cache_key(file), cached_render);
+            if rendered {
+                rendered_blog_entries += 1;
+            }
+            if !template_names.contains(&entry.metadata.template_name) {
+                bail!(
+                    "Blog entry in {} uses nonexistent template: {}",
+                    file.to_string_lossy(),
+                    entry.metadata.template_name
+                );
+            }
+            if let Some(existing_dir) = slug_dirs.get(&entry.metadata.slug) {
+                bail!(
+                    "Blog entries in {} and {} have the same slug: {}",
+                    existing_dir.to_string_lossy(),
+                    file.to_string_lossy(),
+                    entry.metadata.slug
+                );
+            }
+            slug_dirs.insert(entry.metadata.slug.clone(), file.clone());
+            html_content_files.push(entry.metadata.html_content_file.clone());
+            // entries created in the future are hidden until then
+            let is_scheduled = entry.created_at > build_time;
+            if is_scheduled && !entry.draft {
+                scheduled_slugs.push(entry.metadata.slug.clone());
+                next_scheduled_publish = Some(
+                    next_scheduled_publish
+                        .map_or(entry.created_at, |publish| publish.min(entry.created_at)),
+                );
+            }
+            if !is_scheduled && (!entry.draft || config.include_drafts) {
+                blog_entries.push(entry);
+            }
+        }
+
+        for entry in &blog_entries {
+            for linked_slug in &entry.metadata.linked_slugs {
+                if scheduled_slugs.contains(linked_slug) {
+                    warn_or_bail(
+                        config.strict,
+                        format!(
+                            "Blog entry in {} links to blog entry {}, which isn't published yet",
+                            entry.metadata.source_file.to_string_lossy(),
+                            linked_slug
+                        ),
+                    )?;
+                } else if !blog_entries
                     .iter()
-                    .any(|existing_entry| entry.metadata.slug == existing_entry.metadata.slug)
+                    .any(|other| other.metadata.slug == *linked_slug)
                 {
                     bail!(
-                        "Blog entry in {} has non-unique slug: {}",
-                        file.path().to_string_lossy(),
-                        entry.metadata.slug
+                        "Blog entry in {} links to nonexistent blog entry: {}",
+                        entry.metadata.source_file.to_string_lossy(),
+                        linked_slug
                     );
                 }
-                blog_entries.push(entry);
+            }
+        }
+
+        let gone_entries = parse_gone_file(&source_dir.join(GONE_FILE_NAME))?;
+        for gone_entry in &gone_entries {
+            if blog_entries
+                .iter()
+                .any(|entry| entry.metadata.slug == gone_entry.slug)
+            {
+                bail!(
+                    "Blog entry with slug {} is listed in {} but still exists",
+                    gone_entry.slug,
+                    GONE_FILE_NAME
+                );
             }
         }
 
         blog_entries.sort_by(|a, b| a.created_at.cmp(&b.created_at).reverse());
-        Ok(Site { blog_entries })
+        let tag_index = build_tag_index(&blog_entries);
+        let tag_names = build_tag_names(&blog_entries);
+        let (tag_slugs, tags_by_slug) = build_tag_slugs(&tag_index);
+
+        if config.remove_orphaned_html {
+            remove_orphaned_html_files(&blog_entries_html_dir, &html_content_files)?;
+        }
+        build_cache.save()?;
+        let index_content_html = match &config.index_source {
+            Some(index_source) => Some(render_site_markdown_file(
+                &source_dir.join(index_source),
+                "/",
+                config,
+            )?),
+            None => None,
+        };
+        let pages = parse_pages(&source_dir.join(PAGES_DIR_NAME), config, &shared_assets)?;
+        for (page, linked_slugs) in &pages {
+            if !template_names.contains(&page.template_name) {
+                bail!(
+                    "Page in {} uses nonexistent template: {}",
+                    page.source_file.to_string_lossy(),
+                    page.template_name
+                );
+            }
+            for linked_slug in linked_slugs {
+                if !blog_entries
+                    .iter()
+                    .any(|entry| entry.metadata.slug == *linked_slug)
+                {
+                    bail!(
+                        "Page in {} links to nonexistent blog entry: {}",
+                        page.source_file.to_string_lossy(),
+                        linked_slug
+                    );
+                }
+            }
+        }
+        let pages = pages.into_iter().map(|(page, _)| page).collect();
+        let post_footer_html = match &config.post_footer {
+            Some(post_footer) => Some(render_site_markdown_file(
+                &source_dir.join(post_footer),
+                "",
+                config,
+            )?),
+            None => None,
+        };
+
+        let build_report = BuildReport {
+            built_at: Utc::now(),
+            duration_ms: build_start.elapsed().as_millis(),
+            blog_entries: blog_entries.len(),
+            rendered_blog_entries,
+            failed_blog_entries,
+        };
+
+        Ok(Site {
+            blog_entries,
+            shared_assets,
+            build_report,
+            config: config.clone(),
+            index_content_html,
+            post_footer_html,
+            gone_entries,
+            source_dir: source_dir.to_path_buf(),
+            next_scheduled_publish,
+            tag_index,
+            tag_names,
+            tag_slugs,
+            tags_by_slug,
+            pages,
+        })
+    }
+
+    /// Finds the directory of the published blog entry the provided changed file belongs to.
+    /// Returns `None` if the file isn't in a published entry's directory, in which case the change can't be handled without rebuilding the whole site.
+    ///
+    /// # Arguments
+    /// * `changed_path` - The absolute path of the file that changed.
+    pub fn changed_entry_dir(&self, changed_path: &Path) -> Option<PathBuf> {
+        let blog_entries_source_dir = self.source_dir.join(BLOG_ENTRIES_DIR_NAME);
+        let entry_dir_name = entry_dir_name_for_path(&blog_entries_source_dir, changed_path)?;
+        self.entry_index_for_dir_name(&entry_dir_name)?;
+
+        Some(blog_entries_source_dir.join(entry_dir_name))
+    }
+
+    /// Finds the index in `blog_entries` of the entry in the directory with the provided name.
+    fn entry_index_for_dir_name(&self, entry_dir_name: &OsStr) -> Option<usize> {
+        self.blog_entries.iter().position(|entry| {
+            entry
+                .metadata
+                .source_file
+                .parent()
+                .and_then(Path::file_name)
+                == Some(entry_dir_name)
+        })
+    }
+
+    /// Swaps the provided re-parsed blog entry in for the version of it in the site.
+    /// Returns `false` without changing anything if the change can't be handled without rebuilding the whole site,
+    /// like when the entry is no longer in the site, or when the change affects the entry's slug, whether it's published, or what its links resolve to.
+    ///
+    /// # Arguments
+    /// * `update` - The re-parsed entry, from `parse_entry_update`.
+    /// * `config` - The configuration for the site.
+    pub fn apply_entry_update(&mut self, update: EntryUpdate, config: &SiteConfig) -> bool {
+        let EntryUpdate {
+            entry_dir_name,
+            entry,
+            started_at,
+        } = update;
+        let index = match self.entry_index_for_dir_name(&entry_dir_name) {
+            Some(index) => index,
+            None => return false,
+        };
+        let existing_entry = &self.blog_entries[index];
+        let still_published =
+            entry.created_at <= Utc::now() && (!entry.draft || config.include_drafts);
+        let links_resolve = entry.metadata.linked_slugs.iter().all(|linked_slug| {
+            self.blog_entries
+                .iter()
+                .any(|other| other.metadata.slug == *linked_slug)
+        });
+        // the template is only checked when the whole site is built
+        let template_changed =
+            entry.metadata.template_name != existing_entry.metadata.template_name;
+        if entry.metadata.slug != existing_entry.metadata.slug
+            || !still_published
+            || !links_resolve
+            || template_changed
+        {
+            return false;
+        }
+
+        let created_at_changed = entry.created_at != existing_entry.created_at;
+        self.blog_entries[index] = entry;
+        if created_at_changed {
+            self.blog_entries
+                .sort_by(|a, b| a.created_at.cmp(&b.created_at).reverse());
+        }
+        self.tag_index = build_tag_index(&self.blog_entries);
+        self.tag_names = build_tag_names(&self.blog_entries);
+        (self.tag_slugs, self.tags_by_slug) = build_tag_slugs(&self.tag_index);
+        self.build_report = BuildReport {
+            built_at: Utc::now(),
+            duration_ms: started_at.elapsed().as_millis(),
+            blog_entries: self.blog_entries.len(),
+            rendered_blog_entries: 1,
+            failed_blog_entries: self.build_report.failed_blog_entries.clone(),
+        };
+
+        true
+    }
+
+    /// Finds the canonical tag the provided tag slug is for.
+    /// Tags written out in full are accepted too, in any case, for links made before tags had slugs.
+    pub fn tag_for_slug(&self, slug: &str) -> String {
+        let tag = canonicalize_tag(slug);
+        if let Some(tag) = self.tags_by_slug.get(&tag) {
+            return tag.clone();
+        }
+        if self.tag_index.contains_key(&tag) {
+            return tag;
+        }
+
+        self.tags_by_slug
+            .get(&tag_slug(&tag))
+            .cloned()
+            .unwrap_or(tag)
+    }
+
+    /// Gets the blog entries with the provided canonical tag, newest first.
+    pub fn entries_tagged(&self, tag: &str) -> Vec<&BlogEntry> {
+        self.tag_index
+            .get(tag)
+            .map(|indices| indices.iter().map(|i| &self.blog_entries[*i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Finds the slugs of the blog entries most similar to the provided one, most similar first.
+    /// At most `slug_suggestion_limit` slugs are returned, and only ones at least `slug_suggestion_min_similarity` similar to the provided slug.
+    pub fn suggest_slugs(&self, slug: &str) -> Vec<&str> {
+        let mut suggestions = self
+            .blog_entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.metadata.slug.as_str(),
+                    similarity(slug, &entry.metadata.slug),
+                )
+            })
+            .filter(|(_, similarity)| *similarity >= self.config.slug_suggestion_min_similarity)
+            .collect::<Vec<(&str, f64)>>();
+        suggestions.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        suggestions
+            .into_iter()
+            .take(self.config.slug_suggestion_limit)
+            .map(|(slug, _)| slug)
+            .collect()
+    }
+}
+
+/// Calculates how similar the provided strings are, from 0 (nothing in common) to 1 (identical), based on their edit distance.
+fn similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    1.0 - (edit_distance(a, b) as f64 / max_len as f64)
+}
+
+/// Calculates the Levenshtein distance between the provided strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+            current_row[j + 1] = (previous_row[j] + substitution_cost)
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        previous_row = current_row;
+    }
+
+    previous_row[b.len()]
+}
+
+impl PageMetadata {
+    /// The markdown file the page was built from.
+    pub fn source_file(&self) -> &Path {
+        &self.source_file
+    }
+}
+
+impl BlogEntry {
+    /// Estimates how many minutes it takes to read this entry at the provided reading speed, rounded up to at least 1.
+    pub fn reading_time_minutes(&self, words_per_minute: NonZeroUsize) -> usize {
+        self.word_count.div_ceil(words_per_minute.get()).max(1)
+    }
+
+    /// The name of the person who wrote this entry, or `default_author` if it doesn't specify one.
+    pub fn author<'a>(&'a self, default_author: &'a str) -> &'a str {
+        self.author.as_deref().unwrap_or(default_author)
+    }
+
+    /// Determines whether this entry should appear in the site-wide feeds.
+    /// Drafts never do, even when they're included in the site for previewing.
+    pub fn is_in_site_feeds(&self) -> bool {
+        !self.draft && !self.feed_exclude
+    }
+
+    /// Determines whether this entry should appear in the feed of the provided canonical tag.
+    /// Drafts never do, even when they're included in the site for previewing.
+    pub fn is_in_tag_feed(&self, tag: &str) -> bool {
+        let tag = tag.to_string();
+        !self.draft
+            && self.tags.contains(&tag)
+            && self
+                .feed_only_tags
+                .as_ref()
+                .is_none_or(|feed_only_tags| feed_only_tags.contains(&tag))
+    }
+
+    /// Determines whether a password is required to view this entry.
+    pub fn is_password_protected(&self) -> bool {
+        self.password.is_some()
     }
+
+    /// Determines whether the provided password grants access to this entry.
+    /// Entries without a password can be viewed with any password.
+    pub fn check_password(&self, password: &str) -> bool {
+        match &self.password {
+            Some(expected) => constant_time_eq(expected.as_bytes(), password.as_bytes()),
+            None => true,
+        }
+    }
+}
+
+/// Compares two byte slices in time that depends only on their lengths, not their contents.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Deletes rendered HTML files in `html_dir` that aren't one of the provided content files.
+/// Files that aren't HTML files and directories are left alone.
+fn remove_orphaned_html_files(
+    html_dir: &Path,
+    html_content_files: &[PathBuf],
+) -> anyhow::Result<()> {
+    let read_dir = match html_dir.read_dir() {
+        Ok(read_dir) => read_dir,
+        // nothing has been rendered, so there's nothing to remove
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("error reading from {}", html_dir.to_string_lossy()))
+        }
+    };
+
+    for file in read_dir {
+        let file =
+            file.with_context(|| format!("error reading from {}", html_dir.to_string_lossy()))?;
+        let path = file.path();
+        if is_dir(&file)? || path.extension().is_none_or(|extension| extension != "html") {
+            continue;
+        }
+
+        if !html_content_files.contains(&path) {
+            std::fs::remove_file(&path).with_context(|| {
+                format!("error removing orphaned file {}", path.to_string_lossy())
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the list of permanently removed blog entries from the file at the provided path.
+/// If the file doesn't exist, no entries have been removed.
+fn parse_gone_file(path: &Path) -> anyhow::Result<Vec<GoneEntry>> {
+    let contents = match read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("error reading from {}", path.to_string_lossy()))
+        }
+    };
+
+    let gone_file: GoneFile = toml::from_str(&contents)
+        .with_context(|| format!("error parsing {}", path.to_string_lossy()))?;
+
+    Ok(gone_file.gone)
+}
+
+/// Renders the markdown file at the provided path as HTML to be displayed on the page at `page_url`.
+/// Site-wide markdown files don't have associated files, so only shared assets can be referenced from them.
+fn render_site_markdown_file(
+    path: &Path,
+    page_url: &str,
+    config: &SiteConfig,
+) -> anyhow::Result<String> {
+    let markdown = read_to_string(path)
+        .with_context(|| format!("error reading from {}", path.to_string_lossy()))?;
+
+    let render_options = RenderOptions {
+        page_url: page_url.to_string(),
+        asset_base_url: None,
+        // site markdown files aren't displayed under a blog entry title, so their headings are left alone
+        heading_level_offset: 0,
+        ..RenderOptions::for_blog_entry("index", config, None, &[])
+    };
+
+    let rendered_content = render_content(&markdown, &render_options)
+        .with_context(|| format!("error rendering {}", path.to_string_lossy()))?;
+    for warning in &rendered_content.warnings {
+        warn!("{}", warning);
+    }
+
+    Ok(rendered_content.html)
+}
+
+/// Parses the markdown files in the provided directory into `Page`s, in order of their slugs.
+/// Each page is returned with the slugs of the blog entries it links to, so the links can be checked once the entries are known.
+/// There are no pages if the directory doesn't exist.
+///
+/// # Errors
+/// Returns any errors that occur while reading or rendering the pages, or if two pages have the same slug.
+fn parse_pages(
+    pages_dir: &Path,
+    config: &SiteConfig,
+    shared_assets: &[AssociatedFile],
+) -> anyhow::Result<Vec<(Page, Vec<String>)>> {
+    if !pages_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut pages: Vec<(Page, Vec<String>)> = Vec::new();
+    for file in pages_dir
+        .read_dir()
+        .with_context(|| format!("error reading from {}", pages_dir.to_string_lossy()))?
+    {
+        let path = file
+            .with_context(|| format!("error reading from {}", pages_dir.to_string_lossy()))?
+            .path();
+        if !path.is_file() || path.extension() != Some(PAGE_FILE_EXTENSION.as_ref()) {
+            continue;
+        }
+
+        let (page, linked_slugs) = parse_page(&path, config, shared_assets)
+            .with_context(|| format!("error parsing page {}", path.to_string_lossy()))?;
+        if pages
+            .iter()
+            .any(|(existing_page, _)| existing_page.slug == page.slug)
+        {
+            bail!(
+                "Page in {} has non-unique slug: {}",
+                path.to_string_lossy(),
+                page.slug
+            );
+        }
+        pages.push((page, linked_slugs));
+    }
+    pages.sort_by(|(a, _), (b, _)| a.slug.cmp(&b.slug));
+
+    Ok(pages)
+}
+
+/// Parses the markdown file at the provided path into a `Page`, along with the slugs of the blog entries it links to.
+/// The page's slug is the name of the file without its extension, unless its front matter specifies one.
+///
+/// # Errors
+/// Returns any errors that occur while reading the file, parsing its front matter, or rendering its content.
+fn parse_page(
+    path: &Path,
+    config: &SiteConfig,
+    shared_assets: &[AssociatedFile],
+) -> anyhow::Result<(Page, Vec<String>)> {
+    let (format, front_matter, markdown) = read_front_matter_and_content(path)?;
+    let front_matter: PageFrontMatter = format.parse(&front_matter)?;
+    let slug = match front_matter.slug {
+        Some(slug) => slug,
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+
+    let render_options = RenderOptions {
+        page_url: page_url(&slug),
+        asset_base_url: None,
+        ..RenderOptions::for_blog_entry(&slug, config, None, shared_assets)
+    };
+    let rendered = render_content(&markdown, &render_options)?;
+    for warning in &rendered.warnings {
+        warn!("{}", warning);
+    }
+
+    let page = Page {
+        title: front_matter.title,
+        description: front_matter.description.unwrap_or_default(),
+        template_name: front_matter
+            .template
+            .unwrap_or_else(|| DEFAULT_PAGE_TEMPLATE_NAME.to_string()),
+        source_file: path.to_path_buf(),
+        html_content: rendered.html,
+        slug,
+    };
+
+    Ok((page, rendered.linked_slugs))
+}
+
+/// Finds the names of all the templates in the provided directory and its subdirectories.
+/// Names are determined the same way Rocket does, so `blog_entry.html.tera` is named `blog_entry`, and `icons/rss.html.tera` is named `icons/rss`.
+///
+/// # Errors
+/// Returns any errors that occur while reading from the file system.
+fn find_template_names(dir: &Path) -> anyhow::Result<HashSet<String>> {
+    let mut template_names = HashSet::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current_dir) = dirs.pop() {
+        for file in current_dir
+            .read_dir()
+            .with_context(|| format!("error reading from {}", current_dir.to_string_lossy()))?
+        {
+            let file = file
+                .with_context(|| format!("error reading from {}", current_dir.to_string_lossy()))?;
+            let path = file.path();
+            if is_dir(&file)? {
+                dirs.push(path);
+            } else if path.extension() == Some(OsStr::new("tera")) {
+                let relative_path = path.strip_prefix(dir)?.with_extension("");
+                template_names.insert(
+                    relative_path
+                        .with_extension("")
+                        .to_string_lossy()
+                        .replace('\\', "/"),
+                );
+            }
+        }
+    }
+
+    Ok(template_names)
 }
 
 /// Determines whether the provided `DirEntry` is a directory.
@@ -134,12 +949,22 @@ fn is_dir(file: &DirEntry) -> anyhow::Result<bool> {
 }
 
 /// Parses a directory into a `BlogEntry`.
+/// Returns the entry, the render to record for it in the build cache, and whether its content had to be rendered because there was no up-to-date render of it in the build cache.
 ///
 /// # Arguments
 /// * `dir` - The directory to parse.
 /// * `html_dir` - The directory to store the rendered HTML in.
-fn parse_entry_dir(dir: &DirEntry, html_dir: &Path) -> anyhow::Result<BlogEntry> {
-    let content_file_path = dir.path().join(BLOG_CONTENT_FILE_NAME);
+/// * `config` - The configuration for the site.
+/// * `shared_assets` - The shared assets available to the entry.
+/// * `build_cache` - The cache of previously rendered entries.
+fn parse_entry_dir(
+    dir: &Path,
+    html_dir: &Path,
+    config: &SiteConfig,
+    shared_assets: &[AssociatedFile],
+    build_cache: &BuildCache,
+) -> anyhow::Result<(BlogEntry, CachedRender, bool)> {
+    let content_file_path = dir.join(BLOG_CONTENT_FILE_NAME);
 
     let (front_matter, content_markdown) = extract_front_matter_and_content(&content_file_path)
         .with_context(|| {
@@ -149,190 +974,2142 @@ fn parse_entry_dir(dir: &DirEntry, html_dir: &Path) -> anyhow::Result<BlogEntry>
             )
         })?;
 
-    let html_content_file = write_content_as_html(html_dir, dir.file_name(), &content_markdown)
-        .with_context(|| {
-            format!(
-                "error writing content of {} as HTML",
-                content_file_path.to_string_lossy()
-            )
-        })?;
+    let default_slug = default_slug_for_file(dir);
+    let date_prefix = if config.strip_date_prefix {
+        split_date_prefix(&default_slug)
+    } else {
+        None
+    };
+    let slug = front_matter.slug.unwrap_or_else(|| match date_prefix {
+        Some((_, rest)) => rest.to_string(),
+        None => default_slug.clone(),
+    });
 
-    let associated_files = find_associated_files(dir, &dir.path(), &content_file_path)?;
+    let associated_files = find_associated_files(dir, dir, Some(&content_file_path), config)?;
 
-    let created_at = front_matter.created_at.unwrap_or(
-        content_file_path
-            .metadata()
-            .with_context(|| {
+    let cache_key = build_cache_key(dir);
+    // hashing is skipped entirely when there's no cache to look the hash up in
+    let source_hash = if config.build_cache {
+        Some(hash_entry_source(
+            &slug,
+            &content_markdown,
+            &associated_files,
+            shared_assets,
+            config,
+        )?)
+    } else {
+        None
+    };
+    let html_content_file =
+        html_content_path(html_dir, dir.file_name().unwrap_or_default().to_os_string());
+    let cached_render = source_hash
+        .as_ref()
+        .and_then(|source_hash| build_cache.get(&cache_key, source_hash))
+        .filter(|_| html_content_file.is_file())
+        .cloned();
+    let rendered = cached_render.is_none();
+    let (render, html_content) = match cached_render {
+        Some(cached_render) => {
+            let html_content = read_to_string(&html_content_file).with_context(|| {
                 format!(
-                    "error getting metadata for {}",
+                    "error reading rendered content of {}",
                     content_file_path.to_string_lossy()
                 )
-            })?
-            .created()
-            .with_context(|| {
+            })?;
+            // the warnings still apply to the unchanged content, so they're repeated on every build
+            for warning in &cached_render.warnings {
+                warn!("{}", warning);
+            }
+            (cached_render, html_content)
+        }
+        None => {
+            let render_options = RenderOptions::for_blog_entry(
+                &slug,
+                config,
+                Some(&associated_files),
+                shared_assets,
+            );
+            let rendered_content = render_content(&content_markdown, &render_options)
+                .with_context(|| {
+                    format!(
+                        "error rendering content of {}",
+                        content_file_path.to_string_lossy()
+                    )
+                })?;
+            for warning in &rendered_content.warnings {
+                warn!("{}", warning);
+            }
+
+            write_html_content(&html_content_file, &rendered_content.html).with_context(|| {
                 format!(
-                    "error getting created at for {}",
+                    "error writing content of {} as HTML",
                     content_file_path.to_string_lossy()
                 )
-            })?
-            .into(),
-    );
+            })?;
+
+            let cached_render = CachedRender {
+                source_hash: source_hash.unwrap_or_default(),
+                linked_slugs: rendered_content.linked_slugs,
+                outbound_links: rendered_content.outbound_links,
+                toc: rendered_content.toc,
+                warnings: rendered_content.warnings,
+            };
+            (cached_render, rendered_content.html)
+        }
+    };
+    let cached_render = render.clone();
+
+    let prefix_created_at =
+        date_prefix.map(|(date, _)| Utc.from_utc_datetime(&date.and_hms(0, 0, 0)));
+    let created_at = match front_matter.created_at.or(prefix_created_at) {
+        Some(created_at) => created_at,
+        None => file_created_at(&content_file_path)?,
+    };
 
+    let taxonomies = canonicalize_taxonomies(front_matter.taxonomies.unwrap_or_default())
+        .with_context(|| {
+            format!(
+                "invalid taxonomies in {}",
+                content_file_path.to_string_lossy()
+            )
+        })?;
+    let excerpt = build_excerpt(&content_markdown).with_context(|| {
+        format!(
+            "error building excerpt of {}",
+            content_file_path.to_string_lossy()
+        )
+    })?;
+    let search_text = build_search_text(&content_markdown).with_context(|| {
+        format!(
+            "error building search text of {}",
+            content_file_path.to_string_lossy()
+        )
+    })?;
     let metadata = PageMetadata {
         source_file: content_file_path,
         associated_files,
         html_content_file,
-        slug: front_matter
-            .slug
-            .unwrap_or_else(|| default_slug_for_file(dir)),
+        linked_slugs: render.linked_slugs,
+        outbound_links: render.outbound_links,
+        toc: render.toc,
+        slug,
         template_name: front_matter
             .template
             .unwrap_or_else(|| DEFAULT_BLOG_ENTRY_TEMPLATE_NAME.to_string()),
     };
-    Ok(BlogEntry {
+    let written_tags = front_matter.tags.unwrap_or_default();
+    let entry = BlogEntry {
         metadata,
         title: front_matter.title.unwrap_or_default(),
         description: front_matter.description.unwrap_or_default(),
-        tags: front_matter.tags.unwrap_or_default(),
+        tag_names: written_tag_names(&written_tags),
+        tags: canonicalize_tags(written_tags),
         created_at,
         updated_at: front_matter.updated_at,
         comments_enabled: front_matter
             .comments_enabled
             .unwrap_or(DEFAULT_COMMENTS_ENABLED),
         external_discussions: front_matter.external_discussions.unwrap_or_default(),
-    })
-}
+        password: front_matter.password,
+        lang: front_matter.lang,
+        translations: front_matter.translations.unwrap_or_default(),
+        draft: front_matter.draft.unwrap_or(false),
+        read_more: front_matter.read_more,
+        author: front_matter.author,
+        image: front_matter.image,
+        feed_exclude: front_matter.feed_exclude.unwrap_or(false),
+        feed_only_tags: front_matter.feed_only_tags.map(canonicalize_tags),
+        series: front_matter
+            .series
+            .map(|series| series.trim().to_string())
+            .filter(|series| !series.is_empty()),
+        taxonomies,
+        word_count: content_markdown.split_whitespace().count(),
+        excerpt,
+        search_text,
+        html_content,
+    };
+    check_tag_count(&entry, config)?;
 
-/// Recursively finds all the files associated with a blog entry, starting in `dir`.
-/// Relative paths in the returned `AssociatedFile`s will be relative to `base_path`.
-/// Any file with a path matching `content_file_path` will be ignored.
-fn find_associated_files(
-    dir: &DirEntry,
-    base_path: &Path,
-    content_file_path: &Path,
-) -> anyhow::Result<Vec<AssociatedFile>> {
-    let mut associated_files = Vec::new();
-    for file in dir
-        .path()
-        .read_dir()
-        .with_context(|| format!("error reading from {}", dir.path().to_string_lossy()))?
-    {
-        let file =
-            file.with_context(|| format!("error reading from {}", dir.path().to_string_lossy()))?;
+    Ok((entry, cached_render, rendered))
+}
 
-        if file
-            .file_type()
-            .with_context(|| format!("error getting type of {}", file.path().to_string_lossy()))?
-            .is_dir()
-        {
-            associated_files.extend(find_associated_files(&file, base_path, content_file_path)?);
-        } else {
-            let path = file.path();
-            if path != *content_file_path {
-                associated_files.push(AssociatedFile {
-                    relative_path: path.strip_prefix(base_path)?.to_path_buf(),
-                    full_path: path,
-                });
-            }
-        }
-    }
+/// Finds the name of the blog entry directory the provided path is in, if any.
+/// The directory itself doesn't count as being in it, since creating or removing a whole entry needs a full rebuild.
+///
+/// # Arguments
+/// * `blog_entries_source_dir` - The directory blog entry directories are in.
+/// * `path` - The absolute path to find the entry directory for.
+fn entry_dir_name_for_path(blog_entries_source_dir: &Path, path: &Path) -> Option<OsString> {
+    // file watchers report absolute paths, but the source directory might be relative
+    let blog_entries_source_dir = blog_entries_source_dir.canonicalize().ok()?;
+    let mut components = path
+        .strip_prefix(blog_entries_source_dir)
+        .ok()?
+        .components();
+    let entry_dir_name = components.next()?.as_os_str().to_os_string();
+    components.next()?;
 
-    Ok(associated_files)
+    Some(entry_dir_name)
 }
 
-/// Determines the default slug for the provided file.
-fn default_slug_for_file(file: &DirEntry) -> String {
-    file.path()
-        .file_stem()
-        .unwrap_or(&file.file_name())
-        .to_string_lossy()
-        .to_string()
+/// A blog entry that was re-parsed after one of its files changed, ready to be swapped into the site with `Site::apply_entry_update`.
+pub struct EntryUpdate {
+    /// The name of the directory the entry is in.
+    entry_dir_name: OsString,
+    /// The re-parsed entry.
+    entry: BlogEntry,
+    /// When the update started.
+    started_at: Instant,
 }
 
-/// Parses the front matter and the content from the file at the provided location.
+/// Re-parses the blog entry in the provided directory, so it can be swapped into the site with `Site::apply_entry_update`.
+/// The site itself isn't needed, so this can be done without holding a lock on it.
+///
+/// # Arguments
+/// * `entry_dir` - The directory of the entry, from `Site::changed_entry_dir`.
+/// * `html_dir` - The directory to put rendered HTML in.
+/// * `config` - The configuration for the site.
+/// * `shared_assets` - The shared assets of the site.
+///
+/// # Errors
+/// Returns any errors that occur while parsing the blog entry.
+pub fn parse_entry_update(
+    entry_dir: &Path,
+    html_dir: &Path,
+    config: &SiteConfig,
+    shared_assets: &[AssociatedFile],
+) -> anyhow::Result<EntryUpdate> {
+    let started_at = Instant::now();
+    // the build cache is skipped since the entry is known to have changed
+    let (entry, _, _) = parse_entry_dir(
+        entry_dir,
+        &html_dir.join(BLOG_ENTRIES_DIR_NAME),
+        config,
+        shared_assets,
+        &BuildCache::default(),
+    )?;
+
+    Ok(EntryUpdate {
+        entry_dir_name: entry_dir.file_name().unwrap_or_default().to_os_string(),
+        entry,
+        started_at,
+    })
+}
+
+/// Gets the key the render of the blog entry in the provided directory is stored under in the build cache.
+fn build_cache_key(dir: &Path) -> String {
+    dir.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Maps each tag used by the provided blog entries to the indices of the entries that use it, in the same order as the entries.
+fn build_tag_index(blog_entries: &[BlogEntry]) -> HashMap<String, Vec<usize>> {
+    let mut tag_index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, entry) in blog_entries.iter().enumerate() {
+        for tag in &entry.tags {
+            tag_index.entry(tag.clone()).or_default().push(i);
+        }
+    }
+
+    tag_index
+}
+
+/// Assigns a slug to each of the tags in the provided tag index, returning a map from each tag to its slug, and a map from each slug to its tag.
+/// If more than one tag has the same slug, every tag but the first in alphabetical order gets a number added to the end of its slug.
+fn build_tag_slugs(
+    tag_index: &HashMap<String, Vec<usize>>,
+) -> (HashMap<String, String>, HashMap<String, String>) {
+    let mut tags = tag_index.keys().collect::<Vec<&String>>();
+    tags.sort_unstable();
+
+    let mut tag_slugs = HashMap::with_capacity(tags.len());
+    let mut tags_by_slug = HashMap::with_capacity(tags.len());
+    for tag in tags {
+        let base_slug = tag_slug(tag);
+        let mut slug = base_slug.clone();
+        let mut suffix = 2;
+        while tags_by_slug.contains_key(&slug) {
+            slug = format!("{}-{}", base_slug, suffix);
+            suffix += 1;
+        }
+        tags_by_slug.insert(slug.clone(), tag.clone());
+        tag_slugs.insert(tag.clone(), slug);
+    }
+
+    (tag_slugs, tags_by_slug)
+}
+
+/// Maps each tag used by the provided blog entries to how the first entry that uses it writes it.
+fn build_tag_names(blog_entries: &[BlogEntry]) -> HashMap<String, String> {
+    let mut tag_names = HashMap::new();
+    for entry in blog_entries {
+        for (tag, name) in &entry.tag_names {
+            tag_names.entry(tag.clone()).or_insert_with(|| name.clone());
+        }
+    }
+
+    tag_names
+}
+
+/// Builds a plain text excerpt of the provided blog entry markdown.
+/// The excerpt is everything before the excerpt marker if there is one, or the first few words otherwise.
+///
+/// # Errors
+/// Returns any errors encountered while expanding shortcodes in the markdown.
+fn build_excerpt(markdown: &str) -> anyhow::Result<String> {
+    let (excerpt_markdown, truncate) = match markdown.split_once(EXCERPT_MARKER) {
+        Some((before_marker, _)) => (before_marker, false),
+        None => (markdown, true),
+    };
+    // shortcodes expand to HTML, which is left out of the plain text
+    let text = markdown_to_plain_text(&expand_shortcodes(excerpt_markdown)?);
+
+    let words = text.split_whitespace().collect::<Vec<&str>>();
+    if truncate && words.len() > EXCERPT_WORD_LIMIT {
+        Ok(format!("{}…", words[..EXCERPT_WORD_LIMIT].join(" ")))
+    } else {
+        Ok(words.join(" "))
+    }
+}
+
+/// Determines when the file at the provided path was created, for entries that don't specify a creation time.
+/// Many filesystems don't record file creation times, so this falls back to the file's last modified time.
+///
+/// # Errors
+/// Returns an error if neither the creation time nor the modified time of the file can be read.
+fn file_created_at(path: &Path) -> anyhow::Result<DateTime<Utc>> {
+    let metadata = path
+        .metadata()
+        .with_context(|| format!("error getting metadata for {}", path.to_string_lossy()))?;
+
+    let time = metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .with_context(|| {
+            format!(
+                "unable to determine the creation time of {}; set `created_at` in its front matter",
+                path.to_string_lossy()
+            )
+        })?;
+
+    Ok(time.into())
+}
+
+/// Builds the lowercase plain text of the provided blog entry markdown that searches are matched against.
+///
+/// # Errors
+/// Returns any errors encountered while expanding shortcodes in the markdown.
+fn build_search_text(markdown: &str) -> anyhow::Result<String> {
+    let text = markdown_to_plain_text(&expand_shortcodes(markdown)?);
+
+    Ok(text
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase())
+}
+
+/// Converts the provided markdown to plain text, leaving out any markup and HTML.
+fn markdown_to_plain_text(markdown: &str) -> String {
+    let mut text = String::with_capacity(markdown.len());
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => (),
+        }
+    }
+
+    text
+}
+
+/// Checks that the provided blog entry has an allowed number of tags.
+///
+/// # Errors
+/// Returns an error if `config.strict` is set and the entry has fewer than `config.min_tags` or more than `config.max_tags` tags.
+fn check_tag_count(entry: &BlogEntry, config: &SiteConfig) -> anyhow::Result<()> {
+    let tag_count = entry.tags.len();
+    if let Some(min_tags) = config.min_tags {
+        if tag_count < min_tags {
+            warn_or_bail(
+                config.strict,
+                format!(
+                    "Blog entry {} has {} tags, but at least {} are required",
+                    entry.metadata.slug, tag_count, min_tags
+                ),
+            )?;
+        }
+    }
+    if let Some(max_tags) = config.max_tags {
+        if tag_count > max_tags {
+            warn_or_bail(
+                config.strict,
+                format!(
+                    "Blog entry {} has {} tags, but at most {} are allowed",
+                    entry.metadata.slug, tag_count, max_tags
+                ),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the provided tag into the canonical form used to match it against other tags.
+pub fn canonicalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Converts the provided tag into the form used for it in URLs.
+/// The slug is the canonical tag with each run of whitespace, hyphens, and underscores replaced by a single hyphen, and anything else that isn't a letter or number removed.
+/// Tags without any letters or numbers in them keep their canonical form.
+pub fn tag_slug(tag: &str) -> String {
+    let tag = canonicalize_tag(tag);
+    let mut slug = String::with_capacity(tag.len());
+    let mut separated = false;
+    for c in tag.chars() {
+        if c.is_alphanumeric() {
+            if separated && !slug.is_empty() {
+                slug.push('-');
+            }
+            slug.push(c);
+            separated = false;
+        } else if c.is_whitespace() || c == '-' || c == '_' {
+            separated = true;
+        }
+    }
+
+    if slug.is_empty() {
+        tag
+    } else {
+        slug
+    }
+}
+
+/// Canonicalizes the names and terms of the provided taxonomies the same way tags are.
+///
+/// # Errors
+/// Returns an error if any of the taxonomies has a reserved name.
+fn canonicalize_taxonomies(
+    taxonomies: HashMap<String, Vec<String>>,
+) -> anyhow::Result<HashMap<String, Vec<String>>> {
+    let mut canonical_taxonomies = HashMap::with_capacity(taxonomies.len());
+    for (name, terms) in taxonomies {
+        let name = canonicalize_tag(&name);
+        if RESERVED_TAXONOMY_NAMES.contains(&name.as_str()) {
+            bail!("{} is a reserved taxonomy name", name);
+        }
+        canonical_taxonomies
+            .entry(name)
+            .or_insert_with(Vec::new)
+            .extend(canonicalize_tags(terms));
+    }
+
+    Ok(canonical_taxonomies)
+}
+
+/// Maps the canonical form of each of the provided tags to how it's written, trimmed.
+/// If a tag is written more than one way, the first way is used.
+fn written_tag_names(tags: &[String]) -> HashMap<String, String> {
+    let mut tag_names = HashMap::with_capacity(tags.len());
+    for tag in tags {
+        tag_names
+            .entry(canonicalize_tag(tag))
+            .or_insert_with(|| tag.trim().to_string());
+    }
+
+    tag_names
+}
+
+/// Converts the provided tags into their canonical forms, removing any duplicates.
+fn canonicalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut canonical_tags: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = canonicalize_tag(&tag);
+        if !canonical_tags.contains(&tag) {
+            canonical_tags.push(tag);
+        }
+    }
+
+    canonical_tags
+}
+
+/// Recursively finds all the files associated with a blog entry, starting in `dir`.
+/// Relative paths in the returned `AssociatedFile`s will be relative to `base_path`.
+/// Any file with a path matching `content_file_path` will be ignored.
+/// Hidden files and files the config says to ignore are left out, along with the contents of directories like that.
+/// Symlinks are only followed if they point to somewhere inside `base_path`, so they can't expose other entries or the rest of the file system.
+/// Symlinks that can't be resolved, or that link back to a directory the search is already inside of, are skipped.
+fn find_associated_files(
+    dir: &Path,
+    base_path: &Path,
+    content_file_path: Option<&Path>,
+    config: &SiteConfig,
+) -> anyhow::Result<Vec<AssociatedFile>> {
+    let canonical_base_path = base_path
+        .canonicalize()
+        .with_context(|| format!("error resolving {}", base_path.to_string_lossy()))?;
+    let mut associated_files = Vec::new();
+    find_associated_files_within(
+        dir,
+        base_path,
+        &canonical_base_path,
+        content_file_path,
+        config,
+        &mut Vec::new(),
+        &mut associated_files,
+    )?;
+
+    Ok(associated_files)
+}
+
+/// Adds the files associated with a blog entry in `dir` to `associated_files`, recursing into subdirectories.
+/// `canonical_base_path` is the canonical form of `base_path`, which symlinks have to point inside of to be followed.
+/// `ancestor_dirs` holds the canonical paths of the directories the search is inside of, so symlinks back to them can be skipped instead of recursing forever.
+fn find_associated_files_within(
+    dir: &Path,
+    base_path: &Path,
+    canonical_base_path: &Path,
+    content_file_path: Option<&Path>,
+    config: &SiteConfig,
+    ancestor_dirs: &mut Vec<PathBuf>,
+    associated_files: &mut Vec<AssociatedFile>,
+) -> anyhow::Result<()> {
+    let canonical_dir = dir
+        .canonicalize()
+        .with_context(|| format!("error resolving {}", dir.to_string_lossy()))?;
+    if ancestor_dirs.contains(&canonical_dir) {
+        warn!(
+            "Skipping {} because it links back to a directory containing it",
+            dir.to_string_lossy()
+        );
+        return Ok(());
+    }
+    ancestor_dirs.push(canonical_dir);
+
+    for file in dir
+        .read_dir()
+        .with_context(|| format!("error reading from {}", dir.to_string_lossy()))?
+    {
+        let file = file.with_context(|| format!("error reading from {}", dir.to_string_lossy()))?;
+        if config.is_ignored_file(&file.file_name().to_string_lossy()) {
+            continue;
+        }
+
+        let path = file.path();
+        let file_type = file
+            .file_type()
+            .with_context(|| format!("error getting type of {}", path.to_string_lossy()))?;
+        let is_dir = if file_type.is_symlink() {
+            let target = match path.canonicalize() {
+                Ok(target) => target,
+                Err(e) => {
+                    warn!(
+                        "Skipping {} because it can't be resolved: {}",
+                        path.to_string_lossy(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            if !target.starts_with(canonical_base_path) {
+                warn!(
+                    "Skipping {} because it links to {}, which is outside of {}",
+                    path.to_string_lossy(),
+                    target.to_string_lossy(),
+                    base_path.to_string_lossy()
+                );
+                continue;
+            }
+            target.is_dir()
+        } else {
+            file_type.is_dir()
+        };
+
+        if is_dir {
+            find_associated_files_within(
+                &path,
+                base_path,
+                canonical_base_path,
+                content_file_path,
+                config,
+                ancestor_dirs,
+                associated_files,
+            )?;
+        } else if Some(path.as_path()) != content_file_path {
+            associated_files.push(AssociatedFile {
+                relative_path: path.strip_prefix(base_path)?.to_path_buf(),
+                full_path: path,
+            });
+        }
+    }
+
+    ancestor_dirs.pop();
+    Ok(())
+}
+
+/// Determines the default slug for the provided file.
+fn default_slug_for_file(file: &Path) -> String {
+    file.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Splits a leading `YYYY-MM-DD-` date prefix off of the provided name.
+/// Returns the date and the rest of the name, or `None` if the name doesn't start with a date prefix or has nothing after it.
+fn split_date_prefix(name: &str) -> Option<(NaiveDate, &str)> {
+    let date = name.get(..10)?;
+    let rest = name.get(10..)?.strip_prefix('-')?;
+    if rest.is_empty() {
+        return None;
+    }
+
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+
+    Some((date, rest))
+}
+
+/// The formats front matter can be written in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrontMatterFormat {
+    /// TOML, delimited by `+++`.
+    Toml,
+    /// YAML, delimited by `---`.
+    Yaml,
+}
+
+impl FrontMatterFormat {
+    /// Determines the format of front matter that starts with the provided delimiter line.
+    fn from_delimiter(line: &str) -> Option<FrontMatterFormat> {
+        match line {
+            TOML_FRONT_MATTER_DELIMITER => Some(FrontMatterFormat::Toml),
+            YAML_FRONT_MATTER_DELIMITER => Some(FrontMatterFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// The line that starts and ends front matter in this format.
+    fn delimiter(self) -> &'static str {
+        match self {
+            FrontMatterFormat::Toml => TOML_FRONT_MATTER_DELIMITER,
+            FrontMatterFormat::Yaml => YAML_FRONT_MATTER_DELIMITER,
+        }
+    }
+
+    /// Deserializes the provided front matter, which is in this format.
+    ///
+    /// # Errors
+    /// Returns an error if the front matter isn't valid in this format, or doesn't match `T`.
+    pub fn parse<T: DeserializeOwned>(self, front_matter: &str) -> anyhow::Result<T> {
+        Ok(match self {
+            FrontMatterFormat::Toml => toml::from_str(front_matter)?,
+            FrontMatterFormat::Yaml => serde_yaml::from_str(front_matter)?,
+        })
+    }
+
+    /// Re-serializes the provided front matter, which is in this format, so it's formatted consistently.
+    ///
+    /// # Errors
+    /// Returns an error if the front matter isn't valid in this format.
+    fn reformat(self, front_matter: &str) -> anyhow::Result<String> {
+        Ok(match self {
+            FrontMatterFormat::Toml => toml::to_string(&self.parse::<toml::Value>(front_matter)?)?,
+            FrontMatterFormat::Yaml => {
+                serde_yaml::to_string(&self.parse::<serde_yaml::Value>(front_matter)?)?
+            }
+        })
+    }
+}
+
+/// Parses the front matter and the content from the file at the provided location.
 ///
 /// # Errors
 /// Returns an error if there are any errors reading the file or parsing the front matter from it.
-fn extract_front_matter_and_content(
+fn extract_front_matter_and_content(file_path: &Path) -> anyhow::Result<(FrontMatter, String)> {
+    let (format, front_matter_string, content) = read_front_matter_and_content(file_path)?;
+
+    let front_matter = format.parse(&front_matter_string)?;
+    Ok((front_matter, content))
+}
+
+/// Reads the format of the front matter, the unparsed front matter, and the content from the file at the provided location.
+/// The first line of the file determines the format of the front matter.
+/// Windows (`\r\n`) and old Mac (`\r`) line endings are normalized to `\n`.
+///
+/// # Errors
+/// Returns an error if there are any errors reading the file, or it doesn't start with front matter.
+pub fn read_front_matter_and_content(
     file_path: &Path,
-) -> Result<(FrontMatter, String), std::io::Error> {
-    let file = File::open(file_path)?;
+) -> Result<(FrontMatterFormat, String, String), std::io::Error> {
+    let file_contents = normalize_line_endings(&read_to_string(file_path)?);
+    let mut format = FrontMatterFormat::Toml;
     let mut front_matter_string = "".to_string();
     let mut done_with_front_matter = false;
     let mut content_lines = Vec::new();
-    for (i, line) in BufReader::new(file).lines().enumerate() {
-        let line = line?;
-
+    for (i, line) in file_contents.lines().enumerate() {
         if i == 0 {
-            if line != FRONT_MATTER_DELIMITER {
-                return Err(std::io::Error::new(
+            format = FrontMatterFormat::from_delimiter(line).ok_or_else(|| {
+                std::io::Error::new(
                     ErrorKind::InvalidInput,
                     format!(
-                        "file at {:?} did not start with {}",
-                        file_path, FRONT_MATTER_DELIMITER
+                        "file at {:?} did not start with {} or {}",
+                        file_path, TOML_FRONT_MATTER_DELIMITER, YAML_FRONT_MATTER_DELIMITER
                     ),
-                ));
-            }
+                )
+            })?;
             continue;
         }
 
         if done_with_front_matter {
             content_lines.push(line);
-        } else if line == FRONT_MATTER_DELIMITER {
+        } else if line == format.delimiter() {
             done_with_front_matter = true;
         } else {
-            writeln!(front_matter_string, "{}", line)
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+            writeln!(front_matter_string, "{}", line).map_err(std::io::Error::other)?;
         }
     }
 
-    let front_matter = toml::from_str(&front_matter_string)?;
-    Ok((front_matter, content_lines.join("\n")))
+    Ok((format, front_matter_string, content_lines.join("\n")))
 }
 
-/// Converts the provided markdown to HTML and writes it to a file.
-/// Returns the path to the written file.
-///
-/// # Arguments
-/// * `output_dir` - The directory to write the HTML file to.
-/// * `file_name` - The name of the source file the markdown is from.
-/// * `markdown` - The markdown to convert to HTML.
+/// Converts all the line endings in the provided text to `\n`.
+fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Writes the provided front matter and content to the file at the provided location, replacing its current contents.
+/// The front matter is expected to be in the same format as the front matter already in the file, and is validated and re-serialized before it's written.
+/// The content is rendered with the provided configuration first, so content that would fail to build isn't written.
 ///
 /// # Errors
-/// Returns any errors encountered while writing the file.
-fn write_content_as_html(
-    output_dir: &Path,
-    mut file_name: OsString,
-    markdown: &str,
-) -> Result<PathBuf, std::io::Error> {
+/// Returns an error if the front matter isn't valid, the content can't be rendered, or there are any errors reading or writing the file.
+pub fn write_front_matter_and_content(
+    file_path: &Path,
+    front_matter: &str,
+    content: &str,
+    config: &SiteConfig,
+) -> anyhow::Result<()> {
+    let (format, _, _) = read_front_matter_and_content(file_path)
+        .with_context(|| format!("error reading {}", file_path.to_string_lossy()))?;
+    let parsed_front_matter = format
+        .parse::<FrontMatter>(front_matter)
+        .context("invalid front matter")?;
+    render_entry_html(config, &parsed_front_matter, content).context("invalid content")?;
+    let front_matter = format
+        .reformat(front_matter)
+        .context("invalid front matter")?;
+
+    std::fs::write(
+        file_path,
+        format!(
+            "{delimiter}\n{}{delimiter}\n{}\n",
+            front_matter,
+            content.replace("\r\n", "\n"),
+            delimiter = format.delimiter()
+        ),
+    )
+    .with_context(|| format!("error writing to {}", file_path.to_string_lossy()))
+}
+
+/// Builds the path of the file the HTML rendered from a source file should be written to.
+///
+/// # Arguments
+/// * `output_dir` - The directory the HTML file goes in.
+/// * `file_name` - The name of the source file the HTML was rendered from.
+fn html_content_path(output_dir: &Path, mut file_name: OsString) -> PathBuf {
     file_name.push(".html");
 
-    let mut output_path = output_dir.to_owned();
-    output_path.push(file_name);
+    output_dir.join(file_name)
+}
 
-    create_dir_all(output_dir)?;
+/// Writes the provided HTML to the file at the provided path, creating its parent directory if needed.
+///
+/// # Errors
+/// Returns any errors encountered while writing the file.
+fn write_html_content(output_path: &Path, html: &str) -> Result<(), std::io::Error> {
+    if let Some(output_dir) = output_path.parent() {
+        create_dir_all(output_dir)?;
+    }
 
     let mut output_file = OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(true)
-        .open(&output_path)?;
-    writeln!(output_file, "{}", markdown_to_html(markdown))?;
+        .open(output_path)?;
+    writeln!(output_file, "{}", html)?;
+
+    Ok(())
+}
+
+/// Options that control how a blog entry's markdown is converted to HTML.
+struct RenderOptions<'a> {
+    /// The slug of the blog entry being rendered.
+    slug: &'a str,
+    /// The URL of the page the HTML will be displayed on.
+    page_url: String,
+    /// The URL to resolve local image paths against, if they shouldn't be left relative.
+    asset_base_url: Option<String>,
+    /// The files associated with the blog entry being rendered.
+    /// If this is `None`, local images aren't checked for existence.
+    associated_files: Option<&'a [AssociatedFile]>,
+    /// The URL to resolve shared asset paths against.
+    shared_asset_base_url: String,
+    /// The shared assets available to the blog entry being rendered.
+    shared_assets: &'a [AssociatedFile],
+    /// Whether problems that would normally cause warnings should cause errors instead.
+    strict: bool,
+    /// The terms to warn about if they appear in prose.
+    forbidden_terms: &'a [String],
+    /// The options to parse the markdown with, which determine the markdown extensions to enable.
+    parser_options: Options,
+    /// The number of levels to shift headings down by, e.g. 1 to render `#` as `<h2>`.
+    heading_level_offset: u32,
+    /// The name of the theme to highlight fenced code blocks with.
+    syntax_theme: &'a str,
+    /// Whether `$...$` and `$$...$$` math should be wrapped in elements for KaTeX to render.
+    enable_math: bool,
+}
+
+impl<'a> RenderOptions<'a> {
+    /// Builds the options for rendering the content of the blog entry with the provided slug.
+    ///
+    /// # Arguments
+    /// * `slug` - The slug of the blog entry.
+    /// * `config` - The configuration for the site.
+    /// * `associated_files` - The files associated with the blog entry, or `None` if local images shouldn't be checked for existence.
+    /// * `shared_assets` - The shared assets available to the blog entry.
+    fn for_blog_entry(
+        slug: &'a str,
+        config: &'a SiteConfig,
+        associated_files: Option<&'a [AssociatedFile]>,
+        shared_assets: &'a [AssociatedFile],
+    ) -> RenderOptions<'a> {
+        RenderOptions {
+            slug,
+            page_url: blog_entry_url(slug),
+            asset_base_url: config.asset_cdn_base.as_ref().map(|cdn_base| {
+                format!(
+                    "{}{}/",
+                    cdn_base.trim_end_matches('/'),
+                    blog_entry_url(slug)
+                )
+            }),
+            associated_files,
+            shared_asset_base_url: config.shared_asset_base_url(),
+            shared_assets,
+            strict: config.strict,
+            forbidden_terms: config.active_prose_lint_forbidden_terms(),
+            parser_options: config.markdown_parser_options(),
+            heading_level_offset: config.heading_level_offset,
+            syntax_theme: &config.syntax_theme,
+            enable_math: config.enable_math,
+        }
+    }
+}
+
+/// Renders the provided blog entry markdown to HTML with the provided configuration, without reading from or writing to the filesystem.
+/// Local images aren't checked for existence, since there's no directory to look for them in.
+/// Front matter to render with can be parsed with `FrontMatterFormat::parse`.
+///
+/// # Errors
+/// Returns any errors encountered while expanding shortcodes or converting the markdown.
+pub fn render_entry_html(
+    config: &SiteConfig,
+    front_matter: &FrontMatter,
+    markdown: &str,
+) -> anyhow::Result<String> {
+    let slug = front_matter.slug.as_deref().unwrap_or_default();
+    let render_options = RenderOptions::for_blog_entry(slug, config, None, &[]);
+
+    Ok(render_content(markdown, &render_options)?.html)
+}
+
+/// Renders the provided markdown to HTML, expanding any embeds, shortcodes, and math in it first.
+/// Embeds are only expanded if `options` includes associated files to embed.
+/// Math is only expanded if `options.enable_math` is set.
+///
+/// # Errors
+/// Returns any errors encountered while expanding embeds or shortcodes or converting the markdown.
+fn render_content(markdown: &str, options: &RenderOptions) -> anyhow::Result<RenderedContent> {
+    let markdown = match options.associated_files {
+        Some(associated_files) => expand_embeds(markdown, associated_files)?,
+        None => markdown.to_string(),
+    };
+    let markdown = expand_shortcodes(&markdown)?;
+    let markdown = if options.enable_math {
+        expand_math(&markdown).context("error expanding math")?
+    } else {
+        markdown
+    };
+    markdown_to_html(&markdown, options).context("error converting markdown to HTML")
+}
 
-    Ok(output_path)
+/// The result of converting a blog entry's markdown to HTML.
+struct RenderedContent {
+    /// The rendered HTML.
+    html: String,
+    /// The slugs of the other blog entries linked to from the content.
+    linked_slugs: Vec<String>,
+    /// Problems found in the content that don't prevent it from being rendered.
+    warnings: Vec<String>,
+    /// The external URLs linked to or embedded in the content, without duplicates.
+    outbound_links: Vec<String>,
+    /// The table of contents built from the content's headings.
+    toc: Vec<TocEntry>,
 }
 
 /// Converts the provided markdown to HTML.
-fn markdown_to_html(markdown: &str) -> String {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_TABLES);
-    let parser = Parser::new_ext(markdown, options);
+/// Headings are given IDs, and the first table of contents marker, if any, is replaced with a table of contents.
+/// Fenced code blocks in recognized languages are highlighted with inline styles.
+/// Links to other blog entries via `post:<slug>` URLs are resolved to the URLs of those entries.
+///
+/// # Errors
+/// Returns an error if `options.strict` is set and the markdown references a local image that isn't associated with the entry.
+fn markdown_to_html(markdown: &str, options: &RenderOptions) -> anyhow::Result<RenderedContent> {
+    let mut events = Vec::new();
+    let mut linked_slugs = Vec::new();
+    let mut warnings = Vec::new();
+    let mut outbound_links: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    let mut code_block_start = 0;
+    for event in Parser::new_ext(markdown, options.parser_options) {
+        if let Event::Start(Tag::Link(_, url, _) | Tag::Image(_, url, _)) = &event {
+            if is_external_url(url) && !outbound_links.iter().any(|link| link == url.as_ref()) {
+                outbound_links.push(url.to_string());
+            }
+        }
+
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                code_block_start = events.len();
+                events.push(event);
+            }
+            Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(ref lang))) => {
+                in_code_block = false;
+                let code = events[code_block_start..]
+                    .iter()
+                    .filter_map(|event| match event {
+                        Event::Text(text) => Some(text.as_ref()),
+                        _ => None,
+                    })
+                    .collect::<String>();
+                match highlight_code(&code, lang, options.syntax_theme)? {
+                    Some(highlighted) => {
+                        events.truncate(code_block_start);
+                        events.push(Event::Html(highlighted.into()));
+                    }
+                    None => events.push(event),
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                in_code_block = false;
+                events.push(event);
+            }
+            Event::Start(Tag::Heading(level)) => events.push(Event::Start(Tag::Heading(
+                offset_heading_level(level, options.heading_level_offset),
+            ))),
+            Event::End(Tag::Heading(level)) => events.push(Event::End(Tag::Heading(
+                offset_heading_level(level, options.heading_level_offset),
+            ))),
+            Event::Text(ref text) if !in_code_block => {
+                for term in find_forbidden_terms(text, options.forbidden_terms) {
+                    warnings.push(format!(
+                        "blog entry {} contains forbidden term \"{}\"",
+                        options.slug, term
+                    ));
+                }
+                events.push(event);
+            }
+            Event::Start(Tag::Link(link_type, url, title)) if url.starts_with(POST_LINK_SCHEME) => {
+                let target = &url[POST_LINK_SCHEME.len()..];
+                let (slug, fragment) = match target.split_once('#') {
+                    Some((slug, fragment)) => (slug, Some(fragment)),
+                    None => (target, None),
+                };
+                let mut resolved_url = blog_entry_url(slug);
+                if let Some(fragment) = fragment {
+                    resolved_url.push('#');
+                    resolved_url.push_str(fragment);
+                }
+
+                if !linked_slugs.iter().any(|linked_slug| linked_slug == slug) {
+                    linked_slugs.push(slug.to_string());
+                }
+                events.push(Event::Start(Tag::Link(
+                    link_type,
+                    resolved_url.into(),
+                    title,
+                )));
+            }
+            Event::Start(Tag::Image(link_type, url, title))
+                if url.starts_with(SHARED_ASSET_SCHEME) =>
+            {
+                let url = resolve_shared_asset_url(&url, options, &mut warnings)?;
+                events.push(Event::Start(Tag::Image(link_type, url.into(), title)));
+            }
+            Event::Start(Tag::Link(link_type, url, title))
+                if url.starts_with(SHARED_ASSET_SCHEME) =>
+            {
+                let url = resolve_shared_asset_url(&url, options, &mut warnings)?;
+                events.push(Event::Start(Tag::Link(link_type, url.into(), title)));
+            }
+            Event::Start(Tag::Image(link_type, url, title)) => {
+                check_image_exists(&url, options, &mut warnings)?;
+                let url = match &options.asset_base_url {
+                    Some(base_url) => rewrite_asset_url(&url, base_url, options).into(),
+                    None => url,
+                };
+                events.push(Event::Start(Tag::Image(link_type, url, title)));
+            }
+            event => events.push(event),
+        }
+    }
+    let (events, toc) = add_heading_ids_and_toc(events, &options.page_url);
     //TODO add width and height attributes to img tags to reduce reflow
 
     let mut html: String = String::with_capacity(markdown.len() * 3 / 2);
-    html::push_html(&mut html, parser);
+    html::push_html(&mut html, events.into_iter());
+
+    Ok(RenderedContent {
+        html,
+        linked_slugs,
+        warnings,
+        outbound_links,
+        toc,
+    })
+}
+
+/// Builds the URL path of the standalone page with the provided slug.
+pub fn page_url(slug: &str) -> String {
+    format!("/{}", slug)
+}
+
+/// Builds the URL path of the blog entry with the provided slug.
+pub fn blog_entry_url(slug: &str) -> String {
+    format!("{}{}", BLOG_ENTRY_URL_PREFIX, slug)
+}
+
+/// Shifts the provided heading level down by `offset`, without going past the lowest level HTML has.
+fn offset_heading_level(level: u32, offset: u32) -> u32 {
+    level.saturating_add(offset).min(MAX_HEADING_LEVEL)
+}
+
+/// Finds the terms from `forbidden_terms` that appear as whole words in the provided text, ignoring case.
+fn find_forbidden_terms<'a>(text: &str, forbidden_terms: &'a [String]) -> Vec<&'a str> {
+    let text = text.to_lowercase();
+    forbidden_terms
+        .iter()
+        .filter(|term| {
+            let term = term.to_lowercase();
+            !term.is_empty()
+                && text.match_indices(&term).any(|(start, matched)| {
+                    let before = text[..start].chars().next_back();
+                    let after = text[start + matched.len()..].chars().next();
+                    !before.is_some_and(char::is_alphanumeric)
+                        && !after.is_some_and(char::is_alphanumeric)
+                })
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+/// Adds a warning to `warnings` about the provided image URL if it refers to a local file that isn't associated with the entry being rendered.
+///
+/// # Errors
+/// Returns an error instead of warning if `options.strict` is set.
+fn check_image_exists(
+    url: &str,
+    options: &RenderOptions,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let path = match entry_relative_path(url, options.slug) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let associated_files = match options.associated_files {
+        Some(associated_files) => associated_files,
+        None => return Ok(()),
+    };
+
+    if associated_files
+        .iter()
+        .any(|file| file.relative_path == path)
+    {
+        return Ok(());
+    }
+
+    push_warning_or_bail(
+        options.strict,
+        format!(
+            "blog entry {} references missing image {}",
+            options.slug, url
+        ),
+        warnings,
+    )
+}
+
+/// Resolves the provided `shared:<path>` URL to the URL the shared asset at that path is served from.
+///
+/// # Errors
+/// Returns an error if `options.strict` is set and there is no shared asset at the path.
+/// Otherwise, a missing shared asset is added to `warnings`.
+fn resolve_shared_asset_url(
+    url: &str,
+    options: &RenderOptions,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<String> {
+    let path = url[SHARED_ASSET_SCHEME.len()..].trim_start_matches('/');
+    if !options
+        .shared_assets
+        .iter()
+        .any(|asset| asset.relative_path == Path::new(path))
+    {
+        push_warning_or_bail(
+            options.strict,
+            format!(
+                "blog entry {} references missing shared asset {}",
+                options.slug, path
+            ),
+            warnings,
+        )?;
+    }
+
+    Ok(format!("{}{}", options.shared_asset_base_url, path))
+}
 
-    html
+/// Logs the provided message as a warning, or returns it as an error if `strict` is set.
+fn warn_or_bail(strict: bool, message: String) -> anyhow::Result<()> {
+    if strict {
+        bail!(message);
+    }
+    warn!("{}", message);
+
+    Ok(())
+}
+
+/// Adds the provided message to `warnings`, or returns it as an error if `strict` is set.
+fn push_warning_or_bail(
+    strict: bool,
+    message: String,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    if strict {
+        bail!(message);
+    }
+    warnings.push(message);
+
+    Ok(())
+}
+
+/// Resolves the provided image URL against `base_url` if it refers to one of the files associated with the blog entry.
+/// Both relative URLs and absolute ones under the entry's URL are resolved.
+/// All other URLs, including ones to local files that aren't associated with the entry, are returned unchanged.
+fn rewrite_asset_url(url: &str, base_url: &str, options: &RenderOptions) -> String {
+    let associated_files = options.associated_files.unwrap_or_default();
+    let path = match entry_relative_path(url, options.slug) {
+        Some(path)
+            if associated_files
+                .iter()
+                .any(|file| file.relative_path == path) =>
+        {
+            path
+        }
+        _ => return url.to_string(),
+    };
+
+    format!("{}{}", base_url, path.to_string_lossy())
+}
+
+/// Gets the path relative to the directory of the blog entry with the provided slug that the provided URL points to.
+/// Returns `None` if the URL points somewhere other than inside the entry, like another site or another page.
+fn entry_relative_path<'u>(url: &'u str, slug: &str) -> Option<&'u Path> {
+    if is_external_url(url) {
+        return None;
+    }
+
+    let path = if url.starts_with('/') {
+        url.strip_prefix(&blog_entry_url(slug))?.strip_prefix('/')?
+    } else {
+        url.trim_start_matches("./")
+    };
+
+    Some(Path::new(path))
+}
+
+/// Determines whether the provided URL points somewhere other than this site.
+/// The `post:` and `shared:` schemes refer to things on this site, so URLs using them aren't external.
+fn is_external_url(url: &str) -> bool {
+    if url.starts_with("//") {
+        return true;
+    }
+    if url.starts_with(POST_LINK_SCHEME) || url.starts_with(SHARED_ASSET_SCHEME) {
+        return false;
+    }
+
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MarkdownOption;
+    use crate::test_util::TestSite;
+
+    const CREATED_AT: &str = "created_at = \"2021-01-01T00:00:00Z\"";
+
+    fn entry<'a>(site: &'a Site, slug: &str) -> &'a BlogEntry {
+        site.blog_entries
+            .iter()
+            .find(|entry| entry.metadata.slug == slug)
+            .unwrap_or_else(|| panic!("no entry with slug {}", slug))
+    }
+
+    fn associated_paths(entry: &BlogEntry) -> Vec<PathBuf> {
+        let mut paths = entry
+            .metadata
+            .associated_files
+            .iter()
+            .map(|file| file.relative_path.clone())
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn deleted_entry_html_is_removed_on_next_build() {
+        let site = TestSite::new();
+        site.add_entry("kept-post", CREATED_AT, "Kept content");
+        let deleted_dir = site.add_entry("deleted-post", CREATED_AT, "Deleted content");
+        let unrelated_file = site.html_dir().join("blog").join("notes.txt");
+        let built = site.build();
+        let deleted_html = built
+            .blog_entries
+            .iter()
+            .find(|entry| entry.metadata.slug == "deleted-post")
+            .unwrap()
+            .metadata
+            .html_content_file
+            .clone();
+        std::fs::write(&unrelated_file, "some notes").unwrap();
+        assert!(deleted_html.exists());
+
+        std::fs::remove_dir_all(deleted_dir).unwrap();
+        let rebuilt = site.build();
+
+        assert!(!deleted_html.exists());
+        assert!(rebuilt.blog_entries[0].metadata.html_content_file.exists());
+        assert!(unrelated_file.exists());
+    }
+
+    #[test]
+    fn orphaned_html_is_kept_when_removal_is_disabled() {
+        let site = TestSite::new();
+        let deleted_dir = site.add_entry("deleted-post", CREATED_AT, "Deleted content");
+        let deleted_html = site.build().blog_entries[0]
+            .metadata
+            .html_content_file
+            .clone();
+
+        std::fs::remove_dir_all(deleted_dir).unwrap();
+        let config = SiteConfig {
+            remove_orphaned_html: false,
+            ..SiteConfig::default()
+        };
+        site.build_with(&config).unwrap();
+
+        assert!(deleted_html.exists());
+    }
+
+    fn strict_config() -> SiteConfig {
+        SiteConfig {
+            strict: true,
+            ..SiteConfig::default()
+        }
+    }
+
+    #[test]
+    fn missing_local_image_fails_strict_build() {
+        let site = TestSite::new();
+        site.add_entry("some-post", CREATED_AT, "![a picture](missing.png)");
+
+        let error = site.build_with(&strict_config()).unwrap_err();
+
+        assert!(format!("{:?}", error)
+            .contains("blog entry some-post references missing image missing.png"));
+        assert!(site.build_with(&SiteConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn external_and_associated_images_pass_strict_build() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            CREATED_AT,
+            "![external](https://example.com/pic.png)\n\n![local](./images/pic.png)",
+        );
+        site.add_file("blog/some-post/images/pic.png", b"some picture");
+
+        assert!(site.build_with(&strict_config()).is_ok());
+    }
+
+    #[test]
+    fn post_links_resolve_to_entry_urls() {
+        let site = TestSite::new();
+        site.add_entry("target-post", CREATED_AT, "Some content");
+        site.add_entry(
+            "linking-post",
+            CREATED_AT,
+            "See [the other post](post:target-post#details).",
+        );
+
+        let site = site.build();
+        let linking_entry = site
+            .blog_entries
+            .iter()
+            .find(|entry| entry.metadata.slug == "linking-post")
+            .unwrap();
+        let html = std::fs::read_to_string(&linking_entry.metadata.html_content_file).unwrap();
+
+        assert!(html.contains("<a href=\"/blog/posts/target-post#details\">the other post</a>"));
+        assert_eq!(vec!["target-post"], linking_entry.metadata.linked_slugs);
+    }
+
+    #[test]
+    fn post_links_to_nonexistent_entries_fail_build() {
+        let site = TestSite::new();
+        site.add_entry("linking-post", CREATED_AT, "See [this](post:nowhere).");
+
+        let error = site.build_with(&SiteConfig::default()).unwrap_err();
+
+        assert!(format!("{:?}", error).contains("links to nonexistent blog entry: nowhere"));
+    }
+
+    fn lint_config(forbidden_terms: &[&str]) -> SiteConfig {
+        SiteConfig {
+            prose_lint: true,
+            prose_lint_forbidden_terms: forbidden_terms
+                .iter()
+                .map(|term| term.to_string())
+                .collect(),
+            ..SiteConfig::default()
+        }
+    }
+
+    #[test]
+    fn forbidden_terms_in_prose_cause_warnings() {
+        let config = lint_config(&["Obviously", "just"]);
+
+        let rendered = markdown_to_html(
+            "This is obviously fine.\n\nIt's justified.",
+            &RenderOptions::for_blog_entry("some-post", &config, None, &[]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["blog entry some-post contains forbidden term \"Obviously\""],
+            rendered.warnings
+        );
+    }
+
+    #[test]
+    fn forbidden_terms_in_code_blocks_are_ignored() {
+        let config = lint_config(&["obviously"]);
+
+        let rendered = markdown_to_html(
+            "```\nlet obviously = true;\n```\n\n    obviously indented\n",
+            &RenderOptions::for_blog_entry("some-post", &config, None, &[]),
+        )
+        .unwrap();
+
+        assert!(rendered.warnings.is_empty());
+    }
+
+    #[test]
+    fn embeds_include_associated_file_contents() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            CREATED_AT,
+            "Before\n\n{{embed \"demo/widget.html\"}}\n\nAfter",
+        );
+        site.add_file(
+            "blog/some-post/demo/widget.html",
+            "<div id=\"widget\"></div>",
+        );
+
+        let site = site.build();
+        let html =
+            std::fs::read_to_string(&site.blog_entries[0].metadata.html_content_file).unwrap();
+
+        assert!(html.contains("<p>Before</p>\n<div id=\"widget\"></div>\n<p>After</p>"));
+    }
+
+    #[test]
+    fn embeds_of_missing_files_fail_build() {
+        let site = TestSite::new();
+        site.add_entry("some-post", CREATED_AT, "{{embed \"widget.html\"}}");
+
+        let error = site.build_with(&SiteConfig::default()).unwrap_err();
+
+        assert!(format!("{:?}", error)
+            .contains("embedded file widget.html is not in the entry's directory"));
+    }
+
+    #[test]
+    fn date_prefixes_are_stripped_from_default_slugs() {
+        let site = TestSite::new();
+        site.add_entry("2023-05-01-my-post", "title = \"Mine\"", "Some content");
+        site.add_entry(
+            "2023-05-02-explicit-post",
+            "slug = \"chosen-slug\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let config = SiteConfig {
+            strip_date_prefix: true,
+            ..SiteConfig::default()
+        };
+
+        let site = site.build_with(&config).unwrap();
+
+        let inferred = site
+            .blog_entries
+            .iter()
+            .find(|entry| entry.title == "Mine")
+            .unwrap();
+        assert_eq!("my-post", inferred.metadata.slug);
+        assert_eq!(
+            "2023-05-01T00:00:00+00:00",
+            inferred.created_at.to_rfc3339()
+        );
+        let explicit = site
+            .blog_entries
+            .iter()
+            .find(|entry| entry.title != "Mine")
+            .unwrap();
+        assert_eq!("chosen-slug", explicit.metadata.slug);
+        assert_eq!(
+            "2021-01-01T00:00:00+00:00",
+            explicit.created_at.to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn date_prefixes_are_kept_by_default() {
+        let site = TestSite::new();
+        site.add_entry("2023-05-01-my-post", CREATED_AT, "Some content");
+
+        let site = site.build();
+
+        assert_eq!("2023-05-01-my-post", site.blog_entries[0].metadata.slug);
+    }
+
+    fn tag_count_config(strict: bool) -> SiteConfig {
+        SiteConfig {
+            min_tags: Some(1),
+            max_tags: Some(2),
+            strict,
+            ..SiteConfig::default()
+        }
+    }
+
+    #[test]
+    fn too_few_tags_fail_strict_build() {
+        let site = TestSite::new();
+        site.add_entry("untagged-post", CREATED_AT, "Some content");
+
+        let error = site.build_with(&tag_count_config(true)).unwrap_err();
+
+        assert!(format!("{:?}", error)
+            .contains("Blog entry untagged-post has 0 tags, but at least 1 are required"));
+        assert!(site.build_with(&tag_count_config(false)).is_ok());
+    }
+
+    #[test]
+    fn too_many_tags_fail_strict_build() {
+        let site = TestSite::new();
+        site.add_entry(
+            "tagged-post",
+            &format!("tags = [\"a\", \"b\", \"c\"]\n{}", CREATED_AT),
+            "Some content",
+        );
+
+        let error = site.build_with(&tag_count_config(true)).unwrap_err();
+
+        assert!(format!("{:?}", error)
+            .contains("Blog entry tagged-post has 3 tags, but at most 2 are allowed"));
+        assert!(site.build_with(&tag_count_config(false)).is_ok());
+    }
+
+    #[test]
+    fn broken_entries_are_skipped_when_continuing_on_error() {
+        let site = TestSite::new();
+        site.add_entry("good-post", CREATED_AT, "Some content");
+        site.add_entry("broken-post", "title = ", "Some content");
+        let config = SiteConfig {
+            continue_on_error: true,
+            ..SiteConfig::default()
+        };
+
+        let built = site.build_with(&config).unwrap();
+
+        assert_eq!(1, built.blog_entries.len());
+        assert_eq!("good-post", built.blog_entries[0].metadata.slug);
+        let failed = &built.build_report.failed_blog_entries;
+        assert_eq!(1, failed.len());
+        assert!(failed[0].path.ends_with("broken-post"));
+        assert!(site.build_with(&SiteConfig::default()).is_err());
+    }
+
+    #[test]
+    fn line_endings_are_normalized() {
+        let site = TestSite::new();
+        let windows_file = site.add_file(
+            "windows.md",
+            "+++\r\ntitle = \"Windows\"\r\n+++\r\nFirst line\r\nSecond line",
+        );
+        let old_mac_file = site.add_file(
+            "old-mac.md",
+            "+++\rtitle = \"Old Mac\"\r+++\rFirst line\rSecond line",
+        );
+
+        for (file, title) in [(windows_file, "Windows"), (old_mac_file, "Old Mac")] {
+            let (_, front_matter, content) = read_front_matter_and_content(&file).unwrap();
+
+            assert_eq!(format!("title = \"{}\"\n", title), front_matter);
+            assert_eq!("First line\nSecond line", content);
+        }
+    }
+
+    #[test]
+    fn headings_are_offset_without_going_past_h6() {
+        let config = SiteConfig {
+            heading_level_offset: 2,
+            ..SiteConfig::default()
+        };
+
+        let rendered = markdown_to_html(
+            "# Top\n\n##### Deep",
+            &RenderOptions::for_blog_entry("some-post", &config, None, &[]),
+        )
+        .unwrap();
+
+        assert!(rendered.html.contains("<h3 id=\"top\">Top</h3>"));
+        assert!(rendered.html.contains("<h6 id=\"deep\">Deep</h6>"));
+    }
+
+    #[test]
+    fn reading_time_is_rounded_up_to_at_least_a_minute() {
+        let site = TestSite::new();
+        site.add_entry("short-post", CREATED_AT, "Just a few words");
+        site.add_entry(
+            "long-post",
+            "created_at = \"2021-01-02T00:00:00Z\"",
+            &"word ".repeat(401),
+        );
+
+        let built = site.build();
+        let words_per_minute = NonZeroUsize::new(200).unwrap();
+
+        let reading_time = |slug: &str| {
+            built
+                .blog_entries
+                .iter()
+                .find(|entry| entry.metadata.slug == slug)
+                .unwrap()
+                .reading_time_minutes(words_per_minute)
+        };
+        assert_eq!(1, reading_time("short-post"));
+        assert_eq!(3, reading_time("long-post"));
+    }
+
+    #[test]
+    fn excerpts_end_at_the_marker_or_word_limit() {
+        let long_text = "word ".repeat(EXCERPT_WORD_LIMIT + 10);
+
+        assert_eq!(
+            "Some intro text",
+            build_excerpt("Some *intro* text\n<!-- more -->\nThe rest").unwrap()
+        );
+        assert_eq!(
+            format!("{}…", "word ".repeat(EXCERPT_WORD_LIMIT).trim_end()),
+            build_excerpt(&long_text).unwrap()
+        );
+        assert_eq!("A short post", build_excerpt("# A short post").unwrap());
+    }
+
+    #[test]
+    fn entries_are_indexed_by_tag_newest_first() {
+        let site = TestSite::new();
+        site.add_entry(
+            "older-post",
+            "tags = [\"rust\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry(
+            "newer-post",
+            "tags = [\"rust\", \"cooking\"]\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        let slugs = |tag: &str| {
+            site.entries_tagged(tag)
+                .into_iter()
+                .map(|entry| entry.metadata.slug.clone())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(vec!["newer-post", "older-post"], slugs("rust"));
+        assert_eq!(vec!["newer-post"], slugs("cooking"));
+        assert!(slugs("baking").is_empty());
+    }
+
+    #[test]
+    fn rendered_content_is_kept_in_memory() {
+        let site = TestSite::new();
+        site.add_entry("some-post", CREATED_AT, "Some *content*");
+        let built = site.build();
+        let entry = &built.blog_entries[0];
+
+        std::fs::remove_file(&entry.metadata.html_content_file).unwrap();
+
+        assert!(entry.html_content.contains("<em>content</em>"));
+    }
+
+    #[test]
+    fn duplicate_slugs_are_reported_against_the_later_directory() {
+        let site = TestSite::new();
+        for i in 0..10 {
+            site.add_entry(
+                &format!("post-{}", i),
+                &format!("created_at = \"2021-01-{:02}T00:00:00Z\"", i + 1),
+                "Some content",
+            );
+        }
+        site.add_entry(
+            "post-a",
+            "slug = \"post-3\"\ncreated_at = \"2021-02-01T00:00:00Z\"",
+            "Some content",
+        );
+
+        let error = site
+            .build_with(&SiteConfig::default())
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert!(error.contains("post-a"));
+        assert!(error.ends_with("have the same slug: post-3"));
+    }
+
+    #[test]
+    fn yaml_front_matter_is_accepted_and_kept_as_yaml() {
+        let site = TestSite::new();
+        let file = site.add_file(
+            "blog/yaml-post/content.md",
+            "---\ntitle: YAML Post\ncreated_at: 2021-01-01T00:00:00Z\n---\nSome content",
+        );
+        let built = site.build();
+
+        assert_eq!("YAML Post", built.blog_entries[0].title);
+
+        write_front_matter_and_content(
+            &file,
+            "title: New Title\n",
+            "New content",
+            &SiteConfig::default(),
+        )
+        .unwrap();
+        let (format, front_matter, content) = read_front_matter_and_content(&file).unwrap();
+
+        assert_eq!(FrontMatterFormat::Yaml, format);
+        assert_eq!("title: New Title\n", front_matter);
+        assert_eq!("New content", content);
+    }
+
+    #[test]
+    fn unknown_front_matter_keys_are_rejected() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "titel = \"Some Post\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+
+        let error = format!(
+            "{:#}",
+            site.build_with(&SiteConfig::default()).err().unwrap()
+        );
+
+        assert!(error.contains("unknown field `titel`"));
+    }
+
+    #[test]
+    fn entries_without_a_creation_time_use_the_file_time() {
+        let site = TestSite::new();
+        site.add_entry("some-post", "title = \"Some Post\"", "Some content");
+        let before_build = Utc::now();
+
+        let built = site.build();
+        let created_at = built.blog_entries[0].created_at;
+
+        assert!(created_at <= before_build);
+        assert!(before_build - created_at < chrono::Duration::minutes(1));
+    }
+
+    #[test]
+    fn tags_get_unique_slugs_that_resolve_back_to_them() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "tags = [\"Web Dev\", \"web-dev\", \"C++\"]\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let site = site.build();
+
+        assert_eq!("web-dev", site.tag_slugs["web dev"]);
+        assert_eq!("web-dev-2", site.tag_slugs["web-dev"]);
+        assert_eq!("c", site.tag_slugs["c++"]);
+        assert_eq!("web dev", site.tag_for_slug("web-dev"));
+        assert_eq!("web-dev", site.tag_for_slug("web-dev-2"));
+        assert_eq!("web dev", site.tag_for_slug("Web Dev"));
+        assert_eq!("c++", site.tag_for_slug("c"));
+        assert_eq!("+++", tag_slug("+++"));
+    }
+
+    #[test]
+    fn duplicate_slug_errors_name_both_entries() {
+        let site = TestSite::new();
+        let first_dir = site.add_entry(
+            "first",
+            "slug = \"same\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        let second_dir = site.add_entry(
+            "second",
+            "slug = \"same\"\ncreated_at = \"2021-01-02T00:00:00Z\"",
+            "Some content",
+        );
+
+        let error = site
+            .build_with(&SiteConfig::default())
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            format!(
+                "Blog entries in {} and {} have the same slug: same",
+                first_dir.to_string_lossy(),
+                second_dir.to_string_lossy()
+            ),
+            error
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_are_skipped() {
+        let test_site = TestSite::new();
+        let dir = test_site.add_entry("some-post", CREATED_AT, "Some content");
+        test_site.add_file("blog/some-post/sub/pic.png", "picture");
+        std::os::unix::fs::symlink("..", dir.join("sub/loop")).unwrap();
+
+        let site = test_site.build();
+
+        assert_eq!(
+            vec![PathBuf::from("sub/pic.png")],
+            associated_paths(entry(&site, "some-post"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_within_the_entry_are_followed() {
+        let test_site = TestSite::new();
+        let dir = test_site.add_entry("some-post", CREATED_AT, "Some content");
+        test_site.add_file("blog/some-post/images/pic.png", "picture");
+        std::os::unix::fs::symlink("images", dir.join("pictures")).unwrap();
+
+        let site = test_site.build();
+
+        assert_eq!(
+            vec![
+                PathBuf::from("images/pic.png"),
+                PathBuf::from("pictures/pic.png")
+            ],
+            associated_paths(entry(&site, "some-post"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_outside_the_entry_are_skipped() {
+        let test_site = TestSite::new();
+        let dir = test_site.add_entry("some-post", CREATED_AT, "Some content");
+        test_site.add_entry("other-post", CREATED_AT, "Other content");
+        std::os::unix::fs::symlink("..", dir.join("up")).unwrap();
+        std::os::unix::fs::symlink("../other-post/content.md", dir.join("other.md")).unwrap();
+
+        let site = test_site.build();
+
+        assert!(associated_paths(entry(&site, "some-post")).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks_are_skipped() {
+        let test_site = TestSite::new();
+        let dir = test_site.add_entry("some-post", CREATED_AT, "Some content");
+        test_site.add_file("blog/some-post/pic.png", "picture");
+        std::os::unix::fs::symlink("nowhere", dir.join("broken")).unwrap();
+
+        let site = test_site.build();
+
+        assert_eq!(
+            vec![PathBuf::from("pic.png")],
+            associated_paths(entry(&site, "some-post"))
+        );
+    }
+
+    #[test]
+    fn nonexistent_templates_are_rejected() {
+        let site = TestSite::new();
+        site.add_entry(
+            "some-post",
+            "template = \"no_such_template\"\ncreated_at = \"2021-01-01T00:00:00Z\"",
+            "Some content",
+        );
+
+        let error = site
+            .build_with(&SiteConfig::default())
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert!(error.ends_with("uses nonexistent template: no_such_template"));
+    }
+
+    #[test]
+    fn template_names_include_subdirectories() {
+        let template_names = find_template_names(Path::new("templates")).unwrap();
+
+        assert!(template_names.contains("blog_entry"));
+        assert!(template_names.contains("icons/rss"));
+    }
+
+    fn render(markdown: &str, config: &SiteConfig) -> String {
+        let options = RenderOptions::for_blog_entry("some-post", config, None, &[]);
+        render_content(markdown, &options).unwrap().html
+    }
+
+    #[test]
+    fn smart_punctuation_is_enabled_by_default() {
+        let html = render("Pages 1--2 of \"it\"", &SiteConfig::default());
+
+        assert!(html.contains("Pages 1\u{2013}2 of \u{201c}it\u{201d}"));
+    }
+
+    #[test]
+    fn tasklists_render_checkboxes() {
+        let config = SiteConfig {
+            markdown_options: vec![MarkdownOption::Tasklists],
+            ..SiteConfig::default()
+        };
+
+        let html = render("- [ ] to do\n- [x] done", &config);
+
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\"/>"));
+        assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>"));
+    }
+
+    #[test]
+    fn smart_punctuation_leaves_code_untouched() {
+        let html = render(
+            "\"Wait...\" -- `\"a\" -- b...`\n\n```\nlet s = \"x\" -- 'y';\n```",
+            &SiteConfig::default(),
+        );
+
+        assert!(html.contains("\u{201c}Wait\u{2026}\u{201d} \u{2013}"));
+        assert!(html.contains("<code>&quot;a&quot; -- b...</code>"));
+        assert!(html.contains("let s = &quot;x&quot; -- 'y';"));
+    }
+
+    #[test]
+    fn smart_typography_can_be_disabled() {
+        let config = SiteConfig {
+            smart_typography: Some(false),
+            ..SiteConfig::default()
+        };
+
+        let html = render("\"it's\" -- done...", &config);
+
+        assert!(html.contains("&quot;it's&quot; -- done..."));
+    }
+
+    #[test]
+    fn outbound_links_are_external_and_deduplicated() {
+        let markdown = "[a](https://example.com/a) [b](post:other-post) [c](shared:file.pdf) \
+            [d](./notes.txt) [e](/blog) [f](https://example.com/a) ![g](//cdn.example.com/pic.png)";
+        let config = SiteConfig::default();
+        let options = RenderOptions::for_blog_entry("some-post", &config, None, &[]);
+
+        let rendered = render_content(markdown, &options).unwrap();
+
+        assert_eq!(
+            vec!["https://example.com/a", "//cdn.example.com/pic.png"],
+            rendered.outbound_links
+        );
+    }
+
+    #[test]
+    fn associated_image_urls_are_rewritten_to_the_cdn() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "some-post",
+            CREATED_AT,
+            "![a](pic.png) ![b](./pic.png) ![c](/blog/posts/some-post/pic.png)",
+        );
+        test_site.add_file("blog/some-post/pic.png", "picture");
+        let config = SiteConfig {
+            asset_cdn_base: Some("https://cdn.example.com/".to_string()),
+            ..SiteConfig::default()
+        };
+
+        let site = test_site.build_with(&config).unwrap();
+
+        assert_eq!(
+            3,
+            entry(&site, "some-post")
+                .html_content
+                .matches("src=\"https://cdn.example.com/blog/posts/some-post/pic.png\"")
+                .count()
+        );
+    }
+
+    #[test]
+    fn other_image_urls_are_not_rewritten_to_the_cdn() {
+        let test_site = TestSite::new();
+        test_site.add_entry(
+            "some-post",
+            CREATED_AT,
+            "![a](https://example.com/pic.png) ![b](/static/logo.png) ![c](missing.png) \
+            ![d](/blog/posts/other-post/pic.png)",
+        );
+        let config = SiteConfig {
+            asset_cdn_base: Some("https://cdn.example.com".to_string()),
+            ..SiteConfig::default()
+        };
+
+        let site = test_site.build_with(&config).unwrap();
+
+        let html = &entry(&site, "some-post").html_content;
+        assert!(!html.contains("cdn.example.com"));
+        assert!(html.contains("src=\"https://example.com/pic.png\""));
+        assert!(html.contains("src=\"/static/logo.png\""));
+        assert!(html.contains("src=\"missing.png\""));
+        assert!(html.contains("src=\"/blog/posts/other-post/pic.png\""));
+    }
+
+    #[test]
+    fn render_entry_html_renders_without_touching_disk() {
+        let front_matter = FrontMatterFormat::Toml
+            .parse::<FrontMatter>("slug = \"some-post\"\ntitle = \"Some Post\"")
+            .unwrap();
+
+        let html = render_entry_html(
+            &SiteConfig::default(),
+            &front_matter,
+            "# Intro\n\n{{< gist someone abc123 >}}\n\nSee [the other one](post:other-post) and ![a pic](pic.png).",
+        )
+        .unwrap();
+
+        assert_eq!(
+            "<h2 id=\"intro\">Intro</h2>\n\
+            <script src=\"https://gist.github.com/someone/abc123.js\"></script>\n\
+            <p>See <a href=\"/blog/posts/other-post\">the other one</a> and <img src=\"pic.png\" alt=\"a pic\" />.</p>\n",
+            html
+        );
+    }
+
+    #[test]
+    fn drafts_are_left_out_of_the_site() {
+        let test_site = TestSite::new();
+        test_site.add_entry("published", CREATED_AT, "Published");
+        test_site.add_entry("draft", &format!("{}\ndraft = true", CREATED_AT), "Draft");
+
+        let site = test_site.build();
+
+        assert_eq!(
+            vec!["published"],
+            site.blog_entries
+                .iter()
+                .map(|entry| entry.metadata.slug.as_str())
+                .collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn hidden_and_ignored_files_are_not_associated() {
+        let test_site = TestSite::new();
+        test_site.add_entry("some-post", CREATED_AT, "Some content");
+        test_site.add_file("blog/some-post/pic.png", "picture");
+        test_site.add_file("blog/some-post/.DS_Store", "junk");
+        test_site.add_file("blog/some-post/.content.md.swp", "junk");
+        test_site.add_file("blog/some-post/.hidden/notes.txt", "junk");
+        test_site.add_file("blog/some-post/pic.png~", "junk");
+        test_site.add_file("blog/some-post/notes.tmp", "junk");
+        let mut config = SiteConfig::default();
+        config.ignored_file_patterns.push("*.tmp".to_string());
+
+        let site = test_site.build_with(&config).unwrap();
+
+        assert_eq!(
+            vec![PathBuf::from("pic.png")],
+            associated_paths(entry(&site, "some-post"))
+        );
+    }
+
+    fn build_cache_config() -> SiteConfig {
+        SiteConfig {
+            build_cache: true,
+            ..SiteConfig::default()
+        }
+    }
+
+    #[test]
+    fn unchanged_entries_are_not_rendered_again_with_the_build_cache() {
+        let test_site = TestSite::new();
+        test_site.add_entry("first", CREATED_AT, "First content");
+        test_site.add_entry("second", CREATED_AT, "Second content");
+        let config = build_cache_config();
+
+        let site = test_site.build_with(&config).unwrap();
+        assert_eq!(2, site.build_report.rendered_blog_entries);
+
+        let site = test_site.build_with(&config).unwrap();
+        assert_eq!(0, site.build_report.rendered_blog_entries);
+
+        test_site.add_entry("first", CREATED_AT, "Edited content");
+        let site = test_site.build_with(&config).unwrap();
+        assert_eq!(1, site.build_report.rendered_blog_entries);
+        assert!(
+            read_to_string(&entry(&site, "first").metadata.html_content_file)
+                .unwrap()
+                .contains("Edited content")
+        );
+    }
+
+    #[test]
+    fn entries_are_rendered_again_when_their_slug_changes() {
+        let test_site = TestSite::new();
+        test_site.add_entry("first", CREATED_AT, "Content");
+        let config = build_cache_config();
+        test_site.build_with(&config).unwrap();
+
+        test_site.add_entry(
+            "first",
+            &format!("{}\nslug = \"renamed\"", CREATED_AT),
+            "Content",
+        );
+        let site = test_site.build_with(&config).unwrap();
+
+        assert_eq!(1, site.build_report.rendered_blog_entries);
+    }
+
+    #[test]
+    fn build_cache_is_not_used_unless_enabled() {
+        let test_site = TestSite::new();
+        test_site.add_entry("first", CREATED_AT, "First content");
+
+        test_site.build();
+        let site = test_site.build();
+
+        assert_eq!(1, site.build_report.rendered_blog_entries);
+    }
+
+    #[test]
+    fn build_cache_keeps_render_warnings() {
+        let test_site = TestSite::new();
+        test_site.add_entry("first", CREATED_AT, "![Missing](./missing.png)");
+        let config = build_cache_config();
+        test_site.build_with(&config).unwrap();
+
+        let site = test_site.build_with(&config).unwrap();
+        assert_eq!(0, site.build_report.rendered_blog_entries);
+        let cache = read_to_string(test_site.html_dir().join(".build_cache.toml")).unwrap();
+        assert!(cache.contains("blog entry first references missing image ./missing.png"));
+    }
+
+    #[test]
+    fn render_entry_html_uses_the_provided_config() {
+        let front_matter = FrontMatterFormat::Toml
+            .parse::<FrontMatter>("slug = \"some-post\"")
+            .unwrap();
+        let markdown = "![a pic](shared:missing.png)";
+
+        assert!(render_entry_html(&SiteConfig::default(), &front_matter, markdown).is_ok());
+        assert!(render_entry_html(&strict_config(), &front_matter, markdown).is_err());
+    }
+
+    #[test]
+    fn drafts_with_the_slug_of_another_entry_are_rejected() {
+        let site = TestSite::new();
+        // the draft's directory comes first, so it's handled before the published entry
+        site.add_entry(
+            "draft",
+            &format!("{}\nslug = \"published\"\ndraft = true", CREATED_AT),
+            "Some content",
+        );
+        site.add_entry("published", CREATED_AT, "Some content");
+
+        let error = site.build_with(&SiteConfig::default()).err().unwrap();
+
+        assert!(error.to_string().contains("have the same slug: published"));
+    }
+
+    #[test]
+    fn scheduled_entries_with_the_directory_slug_of_another_entry_are_rejected() {
+        let site = TestSite::new();
+        // the scheduled entry's directory comes first, so it's handled before the published entry
+        site.add_entry(
+            "2021-01-01-same",
+            "created_at = \"2999-01-01T00:00:00Z\"",
+            "Some content",
+        );
+        site.add_entry("same", CREATED_AT, "Some content");
+        let config = SiteConfig {
+            strip_date_prefix: true,
+            ..SiteConfig::default()
+        };
+
+        let error = site.build_with(&config).err().unwrap();
+
+        assert!(error.to_string().contains("have the same slug: same"));
+    }
+
+    #[test]
+    fn reserved_taxonomy_names_are_rejected() {
+        for name in RESERVED_TAXONOMY_NAMES {
+            let taxonomies = HashMap::from([(name.to_uppercase(), vec!["term".to_string()])]);
+
+            let error = canonicalize_taxonomies(taxonomies).unwrap_err();
+
+            assert_eq!(
+                format!("{} is a reserved taxonomy name", name),
+                error.to_string()
+            );
+        }
+    }
 }
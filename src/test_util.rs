@@ -0,0 +1,115 @@
+use std::fs::{create_dir_all, write};
+use std::path::{Path, PathBuf};
+
+use rocket::figment::Figment;
+use rocket::local::blocking::Client;
+use tempfile::TempDir;
+
+use crate::build_rocket;
+use crate::config::SiteConfig;
+use crate::site::Site;
+
+/// A site content directory in a temporary directory, for building sites to test against.
+/// The directory is deleted when this is dropped.
+pub struct TestSite {
+    dir: TempDir,
+}
+
+impl TestSite {
+    /// Creates a site without any content.
+    pub fn new() -> TestSite {
+        let site = TestSite {
+            dir: TempDir::new().expect("error creating temporary directory"),
+        };
+        create_dir_all(site.source_dir().join("blog")).expect("error creating blog directory");
+
+        site
+    }
+
+    /// The directory the site's content is in.
+    pub fn source_dir(&self) -> PathBuf {
+        self.dir.path().join("content")
+    }
+
+    /// The directory rendered HTML is put in.
+    pub fn html_dir(&self) -> PathBuf {
+        self.dir.path().join("html")
+    }
+
+    /// Adds a blog entry with the provided TOML front matter, without its delimiters, and markdown content.
+    /// Returns the entry's directory.
+    pub fn add_entry(&self, dir_name: &str, front_matter: &str, content: &str) -> PathBuf {
+        self.add_file(
+            &format!("blog/{}/content.md", dir_name),
+            format!("+++\n{}\n+++\n{}", front_matter.trim(), content),
+        );
+
+        self.source_dir().join("blog").join(dir_name)
+    }
+
+    /// Writes a file at the provided path relative to the content directory, creating any missing directories.
+    /// Returns the full path of the file.
+    pub fn add_file(&self, relative_path: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.source_dir().join(relative_path);
+        create_dir_all(path.parent().unwrap()).expect("error creating directory");
+        write(&path, contents).expect("error writing file");
+
+        path
+    }
+
+    /// Builds the site with the default config.
+    ///
+    /// # Panics
+    /// Panics if the site fails to build.
+    pub fn build(&self) -> Site {
+        self.build_with(&SiteConfig::default())
+            .unwrap_or_else(|e| panic!("error building site: {:?}", e))
+    }
+
+    /// Builds the site with the provided config.
+    pub fn build_with(&self, config: &SiteConfig) -> anyhow::Result<Site> {
+        Site::from_dir(&self.source_dir(), &self.html_dir(), config)
+    }
+
+    /// Starts a server for the site with the default config.
+    pub fn client(&self) -> Client {
+        self.client_with(Figment::new())
+    }
+
+    /// Starts a server for the site, with the provided config overriding the default config.
+    pub fn client_with(&self, config: Figment) -> Client {
+        let config = rocket::Config::figment()
+            .merge(("site_content_base_dir", path_string(&self.source_dir())))
+            .merge(("rendered_html_base_dir", path_string(&self.html_dir())))
+            .merge(config);
+
+        Client::tracked(build_rocket(config)).expect("error starting server")
+    }
+}
+
+/// Converts the provided path to a string to put in a config.
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Encodes the provided fields as the body of a URL-encoded form submission.
+pub fn form_body(fields: &[(&str, &str)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", percent_encode(name), percent_encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Percent-encodes every byte of the provided text that isn't an ASCII letter or number.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
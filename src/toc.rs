@@ -0,0 +1,280 @@
+use pulldown_cmark::escape::{escape_href, escape_html};
+use pulldown_cmark::{CowStr, Event, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The markers that can be put on their own line in markdown to indicate where the table of contents should go.
+/// Only the first marker in a document is replaced with the table of contents; any others are removed.
+const TOC_MARKERS: [&str; 2] = ["[[TOC]]", "{{toc}}"];
+
+/// An entry in a table of contents, corresponding to a heading.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TocEntry {
+    /// The ID of the heading's element.
+    pub id: String,
+    /// The text of the heading.
+    pub title: String,
+    /// The level of the heading, from 1 to 6.
+    pub level: u32,
+    /// The entries for the headings nested under this one.
+    pub children: Vec<TocEntry>,
+}
+
+/// A heading found in a document.
+struct Heading {
+    /// The index of the event that starts the heading.
+    event_index: usize,
+    id: String,
+    title: String,
+    level: u32,
+}
+
+/// Gives all the headings in the provided events unique IDs, and replaces the first table of contents marker with a table of contents.
+/// Returns the updated events and the table of contents.
+///
+/// # Arguments
+/// * `events` - The events to process.
+/// * `page_url` - The URL of the page the events will be rendered on, used to build links to the headings.
+pub fn add_heading_ids_and_toc<'a>(
+    events: Vec<Event<'a>>,
+    page_url: &str,
+) -> (Vec<Event<'a>>, Vec<TocEntry>) {
+    let headings = find_headings(&events);
+    let toc = nest_headings(&headings);
+    let marker_ranges = find_marker_paragraphs(&events);
+
+    let mut processed_events = Vec::with_capacity(events.len());
+    let mut headings = headings.iter().peekable();
+    let mut marker_ranges = marker_ranges.iter().peekable();
+    let mut toc_inserted = false;
+    let mut skip_until = 0;
+    for (i, event) in events.into_iter().enumerate() {
+        if i < skip_until {
+            continue;
+        }
+
+        if let Some((start, end)) = marker_ranges.peek() {
+            if *start == i {
+                if !toc_inserted {
+                    processed_events.push(Event::Html(CowStr::from(toc_to_html(&toc, page_url))));
+                    toc_inserted = true;
+                }
+                skip_until = *end + 1;
+                marker_ranges.next();
+                continue;
+            }
+        }
+
+        match headings.peek() {
+            Some(heading) if heading.event_index == i => {
+                processed_events.push(Event::Html(CowStr::from(format!(
+                    "<h{} id=\"{}\">",
+                    heading.level, heading.id
+                ))));
+                headings.next();
+            }
+            _ => processed_events.push(event),
+        }
+    }
+
+    (processed_events, toc)
+}
+
+/// Finds all the headings in the provided events and assigns them unique IDs.
+fn find_headings(events: &[Event]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut used_ids = HashSet::new();
+    let mut current_heading: Option<(usize, u32, String)> = None;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Heading(level)) => current_heading = Some((i, *level, String::new())),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, title)) = &mut current_heading {
+                    title.push_str(text);
+                }
+            }
+            Event::End(Tag::Heading(_)) => {
+                if let Some((event_index, level, title)) = current_heading.take() {
+                    let id = unique_id(&slugify(&title), &mut used_ids);
+                    headings.push(Heading {
+                        event_index,
+                        id,
+                        title,
+                        level,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    headings
+}
+
+/// Finds the start and end indices of all the paragraphs in the provided events that consist of just a table of contents marker.
+fn find_marker_paragraphs(events: &[Event]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut paragraph: Option<(usize, String)> = None;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Paragraph) => paragraph = Some((i, String::new())),
+            Event::Text(text) => {
+                if let Some((_, paragraph_text)) = &mut paragraph {
+                    paragraph_text.push_str(text);
+                }
+            }
+            Event::End(Tag::Paragraph) => {
+                if let Some((start, paragraph_text)) = paragraph.take() {
+                    if TOC_MARKERS.contains(&paragraph_text.trim()) {
+                        ranges.push((start, i));
+                    }
+                }
+            }
+            // anything other than plain text means this paragraph isn't just a marker
+            _ => paragraph = None,
+        }
+    }
+
+    ranges
+}
+
+/// Converts the provided heading text into a string suitable for use as an element ID.
+fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Makes the provided ID unique among `used_ids` by adding a numeric suffix if needed, and adds it to `used_ids`.
+fn unique_id(id: &str, used_ids: &mut HashSet<String>) -> String {
+    let mut unique = id.to_string();
+    let mut suffix = 1;
+    while used_ids.contains(&unique) {
+        unique = format!("{}-{}", id, suffix);
+        suffix += 1;
+    }
+    used_ids.insert(unique.clone());
+
+    unique
+}
+
+/// Nests the provided headings under the headings before them with lower levels.
+fn nest_headings(headings: &[Heading]) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < headings.len() {
+        let heading = &headings[i];
+        let mut children_end = i + 1;
+        while children_end < headings.len() && headings[children_end].level > heading.level {
+            children_end += 1;
+        }
+
+        entries.push(TocEntry {
+            id: heading.id.clone(),
+            title: heading.title.clone(),
+            level: heading.level,
+            children: nest_headings(&headings[i + 1..children_end]),
+        });
+        i = children_end;
+    }
+
+    entries
+}
+
+/// Renders the provided table of contents as HTML.
+fn toc_to_html(toc: &[TocEntry], page_url: &str) -> String {
+    let mut html = "<nav class=\"toc\">".to_string();
+    push_toc_list(&mut html, toc, page_url);
+    html.push_str("</nav>\n");
+
+    html
+}
+
+/// Renders the provided table of contents entries as a list, and appends it to `html`.
+fn push_toc_list(html: &mut String, entries: &[TocEntry], page_url: &str) {
+    if entries.is_empty() {
+        return;
+    }
+
+    html.push_str("<ul>");
+    for entry in entries {
+        html.push_str("<li><a href=\"");
+        // writing to a string can't fail
+        escape_href(&mut *html, page_url).unwrap();
+        html.push('#');
+        escape_href(&mut *html, &entry.id).unwrap();
+        html.push_str("\">");
+        escape_html(&mut *html, &entry.title).unwrap();
+        html.push_str("</a>");
+        push_toc_list(html, &entry.children, page_url);
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{html, Parser};
+
+    fn render(markdown: &str) -> String {
+        let (events, _) =
+            add_heading_ids_and_toc(Parser::new(markdown).collect(), "/blog/posts/some-post");
+        let mut html = String::new();
+        html::push_html(&mut html, events.into_iter());
+
+        html
+    }
+
+    #[test]
+    fn marker_is_replaced_with_toc() {
+        let html = render("[[TOC]]\n\n# Some Heading\n\n## Another Heading\n");
+
+        assert_eq!(
+            "<nav class=\"toc\"><ul><li><a href=\"/blog/posts/some-post#some-heading\">Some Heading</a><ul><li><a href=\"/blog/posts/some-post#another-heading\">Another Heading</a></li></ul></li></ul></nav>\n\
+            <h1 id=\"some-heading\">Some Heading</h1>\n\
+            <h2 id=\"another-heading\">Another Heading</h2>\n",
+            html
+        );
+    }
+
+    #[test]
+    fn only_first_marker_is_replaced() {
+        let html = render("{{toc}}\n\n# Some Heading\n\n[[TOC]]\n\nSome text\n");
+
+        assert_eq!(1, html.matches("<nav class=\"toc\">").count());
+        assert!(html.starts_with("<nav class=\"toc\">"));
+        assert!(!html.contains("[[TOC]]"));
+        assert!(html.contains("<p>Some text</p>"));
+    }
+
+    #[test]
+    fn no_toc_is_added_without_marker() {
+        let html = render("# Some Heading\n\nSome text\n");
+
+        assert_eq!(
+            "<h1 id=\"some-heading\">Some Heading</h1>\n<p>Some text</p>\n",
+            html
+        );
+    }
+
+    #[test]
+    fn duplicate_headings_get_unique_ids() {
+        let (_, toc) = add_heading_ids_and_toc(Parser::new("# Notes\n\n# Notes\n").collect(), "/");
+
+        let ids: Vec<&str> = toc.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(vec!["notes", "notes-1"], ids);
+    }
+}
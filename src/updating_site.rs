@@ -1,55 +1,331 @@
 use std::{
     error::Error,
-    path::PathBuf,
-    sync::{Arc, RwLock},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, RwLock},
+    thread,
+    time::Duration,
 };
 
+use anyhow::Context;
+use chrono::Utc;
 use hotwatch::{Event, Hotwatch};
+use log::{error, info, warn};
+use rocket::tokio::{task::spawn_blocking, time::timeout};
 
-use crate::site::Site;
+use crate::config::SiteConfig;
+use crate::site::{parse_entry_update, BuildReport, Site};
 
-/// Site that updates itself when changes to its source directory are detected.
+/// How often to check whether a blog entry scheduled for the future should be published.
+const SCHEDULED_PUBLISH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Site that updates itself when changes to its source directory are detected, or when a blog entry scheduled for the future should be published.
 pub struct UpdatingSite {
     /// The `Hotwatch` instance that handles updating the site.
     _hotwatch: Hotwatch,
     /// The site.
     pub site: Arc<RwLock<Site>>,
+    /// The directory the site is built from.
+    source_dir: PathBuf,
+    /// The directory rendered HTML is put in.
+    html_dir: PathBuf,
+    /// The configuration the site is built with.
+    pub config: SiteConfig,
 }
 
 impl UpdatingSite {
-    /// Builds an updating site from the provided source directory, and puts rendered HTML in the provided HTML directory.
+    /// Builds an updating site from the provided source directory with the provided config, and puts rendered HTML in the provided HTML directory.
     ///
     /// # Errors
     /// Returns any errors that occur while reading from the file system or parsing file contents.
     pub fn from_dir(
         source_dir: PathBuf,
         html_dir: PathBuf,
+        config: SiteConfig,
     ) -> Result<UpdatingSite, Box<dyn Error>> {
-        let site = Site::from_dir(&source_dir, &html_dir)?;
+        let site = Site::from_dir(&source_dir, &html_dir, &config)?;
 
         let shared_site = Arc::new(RwLock::new(site));
-        let hotwatch_site = Arc::clone(&shared_site);
 
+        // changes are handled on their own thread, so the ones that come in while waiting for a burst of changes to end can be collected
+        let (change_sender, change_receiver) = mpsc::channel();
         let mut hotwatch = Hotwatch::new()?;
         hotwatch.watch(source_dir.clone(), move |event: Event| {
             match event {
-                Event::NoticeRemove(_) | Event::NoticeWrite(_) | Event::Error(_, _) => return,
-                _ => (),
+                Event::NoticeRemove(_) | Event::NoticeWrite(_) | Event::Error(_, _) => (),
+                // the receiver only goes away if its thread panics, and then there's nothing left to handle changes anyway
+                _ => change_sender.send(event).unwrap_or(()),
             };
+        })?;
 
-            println!("Changes detected, rebuilding site... ({:?})", event);
-            match Site::from_dir(&source_dir, &html_dir) {
-                Ok(site) => {
-                    println!("Site rebuilt successfully.");
-                    *hotwatch_site.write().unwrap() = site;
+        let watcher_site = Arc::clone(&shared_site);
+        let watcher_source_dir = source_dir.clone();
+        let watcher_html_dir = html_dir.clone();
+        let watcher_config = config.clone();
+        let debounce = Duration::from_millis(config.rebuild_debounce_ms);
+        thread::spawn(move || {
+            handle_change_bursts(&change_receiver, debounce, |events| {
+                if let Some(changed_path) = single_changed_path(&events) {
+                    if update_shared_site_entry(
+                        &watcher_site,
+                        changed_path,
+                        &watcher_html_dir,
+                        &watcher_config,
+                    ) {
+                        return;
+                    }
                 }
-                Err(e) => println!("Error rebuilding site: {:?}", e),
-            };
-        })?;
+
+                info!("Changes detected, rebuilding site... ({:?})", events);
+                rebuild_shared_site(
+                    &watcher_site,
+                    &watcher_source_dir,
+                    &watcher_html_dir,
+                    &watcher_config,
+                );
+            });
+        });
+
+        let scheduler_site = Arc::clone(&shared_site);
+        let scheduler_source_dir = source_dir.clone();
+        let scheduler_html_dir = html_dir.clone();
+        let scheduler_config = config.clone();
+        thread::spawn(move || loop {
+            thread::sleep(SCHEDULED_PUBLISH_CHECK_INTERVAL);
+            let publish_due = scheduler_site
+                .read()
+                .unwrap()
+                .next_scheduled_publish
+                .is_some_and(|publish| publish <= Utc::now());
+            if publish_due {
+                info!("Scheduled blog entry publish time reached, rebuilding site...");
+                rebuild_shared_site(
+                    &scheduler_site,
+                    &scheduler_source_dir,
+                    &scheduler_html_dir,
+                    &scheduler_config,
+                );
+            }
+        });
 
         Ok(UpdatingSite {
             _hotwatch: hotwatch,
             site: shared_site,
+            source_dir,
+            html_dir,
+            config,
         })
     }
+
+    /// Rebuilds the site from its source directory, replacing the current site if the build succeeds.
+    /// Returns `None` if the build doesn't finish within the provided duration. The build keeps going in the background in that case, and still replaces the current site if it succeeds.
+    ///
+    /// # Errors
+    /// Returns any errors that occur while building the site. The current site is left in place if this happens.
+    pub async fn rebuild_within(&self, duration: Duration) -> Option<anyhow::Result<BuildReport>> {
+        let shared_site = Arc::clone(&self.site);
+        let source_dir = self.source_dir.clone();
+        let html_dir = self.html_dir.clone();
+        let config = self.config.clone();
+        let build = spawn_blocking(move || {
+            let site = Site::from_dir(&source_dir, &html_dir, &config)?;
+            let build_report = site.build_report.clone();
+            *shared_site.write().unwrap() = site;
+
+            Ok(build_report)
+        });
+
+        match timeout(duration, build).await {
+            Ok(joined) => Some(
+                joined
+                    .context("error waiting for site to build")
+                    .and_then(|result| result),
+            ),
+            Err(_) => None,
+        }
+    }
+}
+
+/// Waits for changes from the provided receiver and passes them to `handle_burst` in bursts, until the sending side goes away.
+/// A burst ends once no more changes come in for `debounce`, so a burst of changes is only handled once.
+fn handle_change_bursts(
+    receiver: &mpsc::Receiver<Event>,
+    debounce: Duration,
+    mut handle_burst: impl FnMut(Vec<Event>),
+) {
+    while let Ok(first_event) = receiver.recv() {
+        let mut events = vec![first_event];
+        while let Ok(event) = receiver.recv_timeout(debounce) {
+            events.push(event);
+        }
+
+        handle_burst(events);
+    }
+}
+
+/// Gets the path of the file the provided events are about, if they're all about the same file.
+/// Renames and rescans can affect more than one file, so they never count.
+fn single_changed_path(events: &[Event]) -> Option<&Path> {
+    let mut changed_path = None;
+    for event in events {
+        let path = match event {
+            Event::Create(path) | Event::Write(path) | Event::Chmod(path) | Event::Remove(path) => {
+                path
+            }
+            _ => return None,
+        };
+        if changed_path.is_some_and(|changed_path| changed_path != path) {
+            return None;
+        }
+        changed_path = Some(path);
+    }
+
+    changed_path.map(PathBuf::as_path)
+}
+
+/// Re-parses just the blog entry the provided changed file belongs to, if the change can be handled without rebuilding the whole site.
+/// Returns whether the entry was updated. If it wasn't, the site should be rebuilt instead.
+fn update_shared_site_entry(
+    shared_site: &RwLock<Site>,
+    changed_path: &Path,
+    html_dir: &Path,
+    config: &SiteConfig,
+) -> bool {
+    // the site is only locked to look things up and to swap the entry in, so requests aren't held up while the entry is parsed
+    let (entry_dir, shared_assets) = {
+        let site = shared_site.read().unwrap();
+        match site.changed_entry_dir(changed_path) {
+            Some(entry_dir) => (entry_dir, site.shared_assets.clone()),
+            None => return false,
+        }
+    };
+
+    let update = match parse_entry_update(&entry_dir, html_dir, config, &shared_assets) {
+        Ok(update) => update,
+        Err(e) => {
+            warn!(
+                "Error updating blog entry, rebuilding site instead: {:?}",
+                e
+            );
+            return false;
+        }
+    };
+
+    let updated = shared_site
+        .write()
+        .unwrap()
+        .apply_entry_update(update, config);
+    if updated {
+        info!(
+            "Blog entry updated successfully. ({})",
+            changed_path.to_string_lossy()
+        );
+    }
+    updated
+}
+
+/// Rebuilds the site from the provided source directory, replacing the shared site if the build succeeds.
+/// Errors are logged rather than returned, since there's nobody waiting on the rebuild to report them to.
+fn rebuild_shared_site(
+    shared_site: &RwLock<Site>,
+    source_dir: &Path,
+    html_dir: &Path,
+    config: &SiteConfig,
+) {
+    match Site::from_dir(source_dir, html_dir, config) {
+        Ok(site) => {
+            info!("Site rebuilt successfully.");
+            *shared_site.write().unwrap() = site;
+        }
+        Err(e) => error!("Error rebuilding site: {:?}", e),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestSite;
+
+    const CREATED_AT: &str = "created_at = \"2021-01-01T00:00:00Z\"";
+
+    fn html_content(site: &RwLock<Site>, slug: &str) -> String {
+        site.read()
+            .unwrap()
+            .blog_entries
+            .iter()
+            .find(|entry| entry.metadata.slug == slug)
+            .unwrap()
+            .html_content
+            .clone()
+    }
+
+    #[test]
+    fn changing_one_file_reparses_only_its_entry() {
+        let test_site = TestSite::new();
+        test_site.add_entry("first", CREATED_AT, "First content");
+        test_site.add_entry("second", CREATED_AT, "Second content");
+        let shared_site = RwLock::new(test_site.build());
+        let second_html = html_content(&shared_site, "second");
+
+        let changed_path = test_site
+            .add_entry("first", CREATED_AT, "Edited content")
+            .join("content.md");
+        let updated = update_shared_site_entry(
+            &shared_site,
+            &changed_path.canonicalize().unwrap(),
+            &test_site.html_dir(),
+            &SiteConfig::default(),
+        );
+
+        assert!(updated);
+        assert_eq!(
+            1,
+            shared_site
+                .read()
+                .unwrap()
+                .build_report
+                .rendered_blog_entries
+        );
+        assert!(html_content(&shared_site, "first").contains("Edited content"));
+        assert_eq!(second_html, html_content(&shared_site, "second"));
+    }
+
+    #[test]
+    fn changes_outside_entry_directories_need_a_rebuild() {
+        let test_site = TestSite::new();
+        test_site.add_entry("first", CREATED_AT, "First content");
+        let shared_site = RwLock::new(test_site.build());
+
+        let changed_path = test_site.add_file("blog/notes.txt", "notes");
+        let updated = update_shared_site_entry(
+            &shared_site,
+            &changed_path.canonicalize().unwrap(),
+            &test_site.html_dir(),
+            &SiteConfig::default(),
+        );
+
+        assert!(!updated);
+    }
+
+    #[test]
+    fn each_burst_of_changes_is_handled_once() {
+        let (sender, receiver) = mpsc::channel();
+        let path = PathBuf::from("blog/first/content.md");
+        let writes = 5;
+        thread::spawn(move || {
+            for _ in 0..writes {
+                sender.send(Event::Write(path.clone())).unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+            // a change after the debounce period has passed starts a new burst
+            thread::sleep(Duration::from_millis(500));
+            sender.send(Event::Write(path)).unwrap();
+        });
+
+        let mut bursts = Vec::new();
+        handle_change_bursts(&receiver, Duration::from_millis(100), |events| {
+            bursts.push(events.len())
+        });
+
+        assert_eq!(vec![writes, 1], bursts);
+    }
 }
@@ -0,0 +1,75 @@
+use log::error;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Request, Response};
+use std::io::Cursor;
+
+/// Fairing that strips the whitespace between tags out of XML responses, like the feeds.
+pub struct XmlMinifier;
+
+#[rocket::async_trait]
+impl Fairing for XmlMinifier {
+    fn info(&self) -> Info {
+        Info {
+            name: "XML minifier",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
+        // XML-based formats like Atom have their own media types, like `application/atom+xml`
+        if !response.content_type().is_some_and(|content_type| {
+            content_type.is_xml() || content_type.sub().as_str().ends_with("+xml")
+        }) {
+            return;
+        }
+
+        let xml = match response.body_mut().to_string().await {
+            Ok(xml) => xml,
+            Err(e) => {
+                error!("Error reading XML response to minify: {}", e);
+                return;
+            }
+        };
+        let minified = minify_xml(&xml);
+        response.set_sized_body(minified.len(), Cursor::new(minified));
+    }
+}
+
+/// Removes the runs of whitespace that sit directly between a `>` and a `<`, leaving all other text untouched.
+/// This doesn't understand CDATA sections, so whitespace-only text between tags inside them is removed too.
+fn minify_xml(xml: &str) -> String {
+    let mut minified = String::with_capacity(xml.len());
+    let mut pending_whitespace = String::new();
+    let mut after_tag = false;
+    for c in xml.chars() {
+        if after_tag && c.is_whitespace() {
+            pending_whitespace.push(c);
+            continue;
+        }
+
+        if c != '<' {
+            minified.push_str(&pending_whitespace);
+        }
+        pending_whitespace.clear();
+        minified.push(c);
+        after_tag = c == '>';
+    }
+    minified.push_str(&pending_whitespace);
+
+    minified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace_between_tags_is_removed() {
+        let xml = "<rss>\n  <channel>\n    <title>Some  Title </title>\n  </channel>\n</rss>\n";
+
+        assert_eq!(
+            "<rss><channel><title>Some  Title </title></channel></rss>\n",
+            minify_xml(xml)
+        );
+    }
+}